@@ -0,0 +1,41 @@
+// The parsers must return errors, not panic, whatever the (untrusted) crontab: run with
+// `cargo +nightly fuzz run parse_crontab`
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate crontabcheck;
+
+use std::str;
+
+use crontabcheck::parser::{CrontabParserOptions, error_offset, parse_crontab, walk_errors};
+use crontabcheck::schedule::parse_schedule;
+use crontabcheck::validate::validate_crontab;
+
+
+fuzz_target!(|data: &[u8]| {
+    // the parsers take text, invalid UTF-8 is rejected before
+    let text = match str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let usernames = ["root", "backup"];
+    for owner in &[None, Some("alice")] {
+        let options = CrontabParserOptions {
+            allowed_usernames: Some(&usernames[..]),
+            forbidden_usernames: Some(&usernames[..1]),
+            crontab_owner: *owner,
+            ..Default::default()
+        };
+        validate_crontab(text, &options);
+        for line in text.lines() {
+            if let Err(errors) = parse_crontab(line, &options) {
+                walk_errors(&errors);
+            }
+            error_offset(line, &options);
+        }
+    }
+    if let Ok(schedule) = parse_schedule(text) {
+        schedule.bits();
+    }
+});
@@ -0,0 +1,46 @@
+/*
+ * C API of the crontabcheck library (libcrontabcheck), to check the syntax of crontabs without running the
+ * crontabcheck command. The checks which need the system (users, commands, files...) are only done by the command.
+ *
+ * Strings are NUL-terminated UTF-8. Results must be freed with crontabcheck_result_free, the messages they hold
+ * are valid until then.
+ */
+
+#ifndef CRONTABCHECK_H
+#define CRONTABCHECK_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct CheckResult crontabcheck_result;
+
+/*
+ * Check a crontab. owner is the user of a user crontab (whose lines have no user field), NULL for system crontabs.
+ * Returns NULL if a string is not valid UTF-8, or if the check failed unexpectedly.
+ */
+crontabcheck_result *crontabcheck_check(const char *contents, const char *owner);
+
+/* Check a single line of a crontab, as crontabcheck_check (its errors are on line 1) */
+crontabcheck_result *crontabcheck_check_line(const char *line, const char *owner);
+
+/* The number of errors, 0 if the crontab is valid */
+size_t crontabcheck_result_count(const crontabcheck_result *result);
+
+/*
+ * Get an error: its line number (from 1), its column (in bytes, from 1; 0 when the error is not at a place of the
+ * line, e.g. a missing final newline) and its message.
+ * The pointers may be NULL when not needed. Returns 0 if index is out of range, 1 otherwise.
+ */
+int crontabcheck_result_get(const crontabcheck_result *result, size_t index,
+                            size_t *line_number, size_t *column, const char **message);
+
+void crontabcheck_result_free(crontabcheck_result *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
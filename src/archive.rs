@@ -0,0 +1,273 @@
+// Crontabs inside archives: tarballs of a root filesystem, `docker save` images and OCI image layouts
+// (plain or gzipped tar). Image layers are applied in order, so that files removed by a layer are not checked.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Component, Path};
+
+use flate2::read::GzDecoder;
+use serde_json;
+use tar::Archive;
+
+use files::crontab_file;
+
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+// image metadata (manifests, indexes) are small JSON files, unlike layers
+const MAX_METADATA_SIZE: u64 = 1 << 20;
+// files removed by an image layer are marked with a ".wh." prefix ("whiteout")
+const WHITEOUT_PREFIX: &str = ".wh.";
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+pub struct ArchivedCrontab {
+    // absolute path in the archived filesystem
+    pub path: String,
+    pub owner: Option<String>,
+    pub contents: Vec<u8>,
+}
+
+// `docker save`: manifest.json
+#[derive(Deserialize)]
+struct DockerManifest {
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
+}
+
+// OCI image layout: index.json, pointing to manifests, pointing to layers
+#[derive(Deserialize)]
+struct OciIndex {
+    manifests: Vec<OciDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct OciManifest {
+    layers: Vec<OciDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct OciDescriptor {
+    digest: String,
+}
+
+// Change to a crontab (or a directory which may contain crontabs) made by a filesystem archive.
+// A path ending with '/' and no contents means the whole directory was removed.
+type Change = (String, Option<Vec<u8>>);
+
+fn decompress<'a, R: Read + 'a>(reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    let mut reader = BufReader::new(reader);
+    let gzipped = reader.fill_buf()?.starts_with(GZIP_MAGIC);
+    Ok(if gzipped { Box::new(GzDecoder::new(reader)) } else { Box::new(reader) })
+}
+
+// "./etc/cron.d/x" -> "/etc/cron.d/x"
+fn absolute_path(path: &Path) -> String {
+    let parts: Vec<String> = path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    format!("/{}", parts.join("/"))
+}
+
+// The crontabs (and whiteouts) of a filesystem tarball. Whiteouts only remove files of the lower layers, whatever
+// their place in the tarball: they come first.
+fn filesystem_changes<R: Read>(reader: R) -> io::Result<Vec<Change>> {
+    let mut removals = vec![];
+    let mut crontabs = vec![];
+    let mut archive = Archive::new(decompress(reader)?);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = absolute_path(&entry.path()?);
+        let (directory, name) = match path.rfind('/') {
+            Some(index) => (path[..index].to_string(), path[index + 1..].to_string()),
+            None => continue,
+        };
+        if name == OPAQUE_WHITEOUT {
+            removals.push((format!("{}/", directory), None));
+        } else if let Some(removed_name) = name.strip_prefix(WHITEOUT_PREFIX) {
+            let removed = format!("{}/{}", directory, removed_name);
+            // the removed path may be a directory of crontabs
+            removals.push((format!("{}/", removed), None));
+            removals.push((removed, None));
+        } else if entry.header().entry_type().is_file() && crontab_file(Path::new(&path)).is_some() {
+            let mut contents = vec![];
+            entry.read_to_end(&mut contents)?;
+            crontabs.push((path, Some(contents)));
+        }
+    }
+    removals.extend(crontabs);
+    Ok(removals)
+}
+
+fn apply_changes(files: &mut BTreeMap<String, Vec<u8>>, changes: Vec<Change>) {
+    for (path, contents) in changes {
+        match contents {
+            Some(contents) => { files.insert(path, contents); },
+            None if path.ends_with('/') => {
+                let removed: Vec<String> = files.keys().filter(|file| file.starts_with(&path)).cloned().collect();
+                for file in removed {
+                    files.remove(&file);
+                }
+            },
+            None => { files.remove(&path); },
+        }
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Names (as archive paths) of the layers of an image, from the first to the last one.
+// None when the archive is not an image.
+fn image_layers(metadata: &HashMap<String, Vec<u8>>) -> io::Result<Option<Vec<String>>> {
+    if let Some(manifest) = metadata.get("/manifest.json") {
+        let manifests: Vec<DockerManifest> = serde_json::from_slice(manifest)
+            .map_err(|e| invalid_data(format!("invalid manifest.json: {}", e)))?;
+        let manifest = manifests.into_iter().next().ok_or(invalid_data("no image in manifest.json".to_string()))?;
+        return Ok(Some(manifest.layers.iter().map(|layer| absolute_path(Path::new(layer))).collect()));
+    }
+    if let Some(index) = metadata.get("/index.json") {
+        let index: OciIndex = serde_json::from_slice(index).map_err(|e| invalid_data(format!("invalid index.json: {}", e)))?;
+        let descriptor = index.manifests.first().ok_or(invalid_data("no image in index.json".to_string()))?;
+        let manifest = metadata.get(&blob_path(&descriptor.digest))
+            .ok_or(invalid_data(format!("image manifest {} not found", descriptor.digest)))?;
+        let manifest: OciManifest = serde_json::from_slice(manifest)
+            .map_err(|e| invalid_data(format!("invalid image manifest {}: {}", descriptor.digest, e)))?;
+        return Ok(Some(manifest.layers.iter().map(|layer| blob_path(&layer.digest)).collect()));
+    }
+    Ok(None)
+}
+
+// Files an image is made of (so that the other files of a filesystem tarball are not read)
+fn is_image_file(path: &str) -> bool {
+    path.starts_with("/blobs/") || path.ends_with(".json") || path.ends_with(".tar") || path.ends_with(".tar.gz")
+}
+
+// "sha256:abcd..." -> "/blobs/sha256/abcd..."
+fn blob_path(digest: &str) -> String {
+    format!("/blobs/{}", digest.replacen(':', "/", 1))
+}
+
+// The crontabs of the filesystem stored in the archive, sorted by path
+pub fn crontabs_in_archive(path: &Path) -> io::Result<Vec<ArchivedCrontab>> {
+    let mut archive = Archive::new(decompress(File::open(path)?)?);
+    // the archive read as a filesystem tarball
+    let mut changes = vec![];
+    // the archive read as an image: its layers (any entry which is a tarball) and metadata
+    let mut layers = HashMap::new();
+    let mut metadata = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = absolute_path(&entry.path()?);
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        if crontab_file(Path::new(&path)).is_some() {
+            let mut contents = vec![];
+            entry.read_to_end(&mut contents)?;
+            changes.push((path, Some(contents)));
+        } else if !is_image_file(&path) {
+            continue;
+        } else if entry.header().size()? <= MAX_METADATA_SIZE {
+            let mut contents = vec![];
+            entry.read_to_end(&mut contents)?;
+            // entries which are not tarballs are not layers
+            if let Ok(layer_changes) = filesystem_changes(&contents[..]) {
+                layers.insert(path.clone(), layer_changes);
+            }
+            metadata.insert(path, contents);
+        } else if let Ok(layer_changes) = filesystem_changes(&mut entry) {
+            layers.insert(path, layer_changes);
+        }
+    }
+    let mut files = BTreeMap::new();
+    match image_layers(&metadata)? {
+        Some(layer_names) => for name in layer_names {
+            let layer_changes = layers.remove(&name).ok_or(invalid_data(format!("layer {} not found in the image", name)))?;
+            apply_changes(&mut files, layer_changes);
+        },
+        None => apply_changes(&mut files, changes),
+    }
+    Ok(files.into_iter()
+        .filter_map(|(path, contents)| crontab_file(Path::new(&path)).map(|crontab| ArchivedCrontab {
+            path,
+            owner: crontab.owner,
+            contents,
+        }))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+
+    use tar::{Builder, Header};
+
+    use archive::*;
+
+    fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = Builder::new(vec![]);
+        for &(path, contents) in files {
+            let mut header = Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, contents).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn crontabs(archive: &[u8]) -> Vec<(String, Option<String>)> {
+        let path = env::temp_dir().join(format!("crontabcheck-archive-{}.tar", ::std::process::id()));
+        fs::write(&path, archive).unwrap();
+        let crontabs = crontabs_in_archive(&path);
+        fs::remove_file(&path).unwrap();
+        crontabs.unwrap().into_iter().map(|crontab| (crontab.path, crontab.owner)).collect()
+    }
+
+    #[test]
+    fn test_filesystem_tarball() {
+        let archive = tarball(&[
+            ("./etc/passwd", b"root:x:0:0::/root:/bin/sh\n"),
+            ("./etc/cron.d/backup", b"0 1 * * * root /usr/bin/backup\n"),
+            ("./var/spool/cron/crontabs/alice", b"0 1 * * * /usr/bin/true\n"),
+        ]);
+        assert_eq!(crontabs(&archive), vec![
+            ("/etc/cron.d/backup".to_string(), None),
+            ("/var/spool/cron/crontabs/alice".to_string(), Some("alice".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn test_docker_image() {
+        let base = tarball(&[("etc/cron.d/backup", b"0 1 * * * root /usr/bin/backup\n"), ("etc/cron.d/logs", b"")]);
+        let top = tarball(&[("etc/cron.d/.wh.backup", b""), ("etc/cron.d/reports", b"")]);
+        let image = tarball(&[
+            ("manifest.json", br#"[{"Config": "config.json", "Layers": ["base/layer.tar", "top/layer.tar"]}]"#),
+            ("base/layer.tar", &base),
+            ("top/layer.tar", &top),
+        ]);
+        assert_eq!(crontabs(&image), vec![
+            ("/etc/cron.d/logs".to_string(), None),
+            ("/etc/cron.d/reports".to_string(), None),
+        ]);
+    }
+
+    #[test]
+    fn test_opaque_whiteout() {
+        let base = tarball(&[("etc/cron.d/backup", b"0 1 * * * root /usr/bin/backup\n")]);
+        // after the file of its own layer in the tarball, which it does not remove
+        let top = tarball(&[("etc/cron.d/reports", b""), ("etc/cron.d/.wh..wh..opq", b"")]);
+        let image = tarball(&[
+            ("manifest.json", br#"[{"Config": "config.json", "Layers": ["base/layer.tar", "top/layer.tar"]}]"#),
+            ("base/layer.tar", &base),
+            ("top/layer.tar", &top),
+        ]);
+        assert_eq!(crontabs(&image), vec![("/etc/cron.d/reports".to_string(), None)]);
+    }
+
+}
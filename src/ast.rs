@@ -0,0 +1,87 @@
+// --print-ast: what the parser makes of each line of a crontab, in its Debug form or as JSON, to debug the parser and
+// for tools built on its results. Only the syntax is involved, the checks are not run.
+
+use serde_json;
+
+use parser::{CrontabLine, CrontabParserOptions, parse_crontab, walk_errors};
+
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Node<'a> {
+    Empty,
+    Comment,
+    EnvironmentVariable { name: &'a str, value: &'a str },
+    Job { schedule: &'a str, user: &'a str, command: &'a str },
+    Invalid { error: String },
+}
+
+#[derive(Serialize)]
+struct AstLine<'a> {
+    line_number: usize,
+    line: &'a str,
+    #[serde(flatten)]
+    node: Node<'a>,
+}
+
+fn parse_lines<'a, T: AsRef<str>>(contents: &'a str, options: &CrontabParserOptions<'a, T>) -> Vec<(usize, &'a str, Result<CrontabLine<'a>, String>)> {
+    contents.lines().enumerate().map(|(index, line)| {
+        (index + 1, line, parse_crontab(line, options).map_err(|errors| walk_errors(&errors)))
+    }).collect()
+}
+
+// "path:line: Ok(Job { ... })" for each line, and whether they are all valid
+pub fn ast_text<T: AsRef<str>>(contents: &str, path: &str, options: &CrontabParserOptions<T>) -> (String, bool) {
+    let lines = parse_lines(contents, options);
+    let valid = lines.iter().all(|(_, _, parsed)| parsed.is_ok());
+    let text = lines.iter().map(|(line_number, _, parsed)| format!("{}:{}: {:?}\n", path, line_number, parsed)).collect();
+    (text, valid)
+}
+
+// {"path": ..., "lines": [{"line_number": 1, "line": "...", "kind": "job", "schedule": ...}, ...]}, and whether
+// the lines are all valid
+pub fn ast_json<T: AsRef<str>>(contents: &str, path: &str, options: &CrontabParserOptions<T>) -> (serde_json::Value, bool) {
+    let lines = parse_lines(contents, options);
+    let valid = lines.iter().all(|(_, _, parsed)| parsed.is_ok());
+    let lines: Vec<AstLine> = lines.into_iter().map(|(line_number, line, parsed)| AstLine {
+        line_number,
+        line,
+        node: match parsed {
+            Ok(CrontabLine::Empty) => Node::Empty,
+            Ok(CrontabLine::Comment) => Node::Comment,
+            Ok(CrontabLine::EnvironmentVariable { name, value }) => Node::EnvironmentVariable { name, value },
+            Ok(CrontabLine::Job { schedule, user, command }) => Node::Job { schedule, user, command },
+            Err(error) => Node::Invalid { error },
+        },
+    }).collect();
+    (json!({ "path": path, "lines": lines }), valid)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use ast::*;
+
+    #[test]
+    fn test_ast() {
+        let contents = "# backups\nMAILTO=ops@example.com\n0 2 * * * root /usr/bin/backup\n\n61 * * * * root true\n";
+        let options = CrontabParserOptions::<&str>::default();
+        let (text, valid) = ast_text(contents, "crontab", &options);
+        assert!(!valid);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "crontab:1: Ok(Comment)");
+        assert_eq!(lines[2], "crontab:3: Ok(Job { schedule: \"0 2 * * *\", user: \"root\", command: \"/usr/bin/backup\" })");
+        assert!(lines[4].starts_with("crontab:5: Err(\""));
+
+        let (json, valid) = ast_json(contents, "crontab", &options);
+        assert!(!valid);
+        assert_eq!(json["path"], "crontab");
+        assert_eq!(json["lines"][1], json!({ "line_number": 2, "line": "MAILTO=ops@example.com", "kind": "environment_variable",
+                                             "name": "MAILTO", "value": "ops@example.com" }));
+        assert_eq!(json["lines"][2]["kind"], "job");
+        assert_eq!(json["lines"][3]["kind"], "empty");
+        assert_eq!(json["lines"][4]["kind"], "invalid");
+        assert!(ast_text("0 2 * * * root true\n", "crontab", &options).1);
+    }
+
+}
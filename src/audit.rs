@@ -0,0 +1,226 @@
+// The audit subcommand: the security findings of the crontabs of a system (or of the given files), ranked by risk,
+// for host reviews. Unlike the checks, the audit always looks at the files the jobs run.
+
+use std::path::Path;
+
+use serde_json;
+
+use command::{check_denied_commands, check_denied_paths, executed_files, foreign_owner_errors, setuid_warnings,
+              writable_file_warnings};
+use checker::DEFAULT_CRON_PATH;
+use config::{Config, RuleLevel};
+use environment::path_security_warnings;
+use files::{check_permissions, uid0_usernames};
+use lints::{hidden_character_warnings, pipe_to_shell_warning, plain_http_warning, relative_program_warning};
+use parser::{CrontabLine, CrontabParserOptions, parse_crontab};
+use secrets::{command_secret_warnings, variable_secret_warning};
+
+
+// Most urgent first
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Risk {
+    // anyone, or another user, can run code as root
+    High,
+    Medium,
+    Low,
+}
+
+impl Risk {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Risk::High => "high",
+            Risk::Medium => "medium",
+            Risk::Low => "low",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub risk: Risk,
+    // name of the check, e.g. "writable-file"
+    pub rule: &'static str,
+    // the crontab file and line, 0 for findings about the whole file
+    pub source: String,
+    pub line_number: usize,
+    pub line: String,
+    pub message: String,
+}
+
+pub struct Auditor<'a> {
+    config: &'a Config,
+    sysroot: &'a Path,
+    // root and the other uid 0 accounts
+    privileged_users: Vec<String>,
+}
+
+impl<'a> Auditor<'a> {
+    pub fn new(config: &'a Config, sysroot: &'a Path) -> Self {
+        let mut privileged_users = vec!["root".to_string()];
+        privileged_users.extend(uid0_usernames(sysroot).unwrap_or_default());
+        Auditor { config, sysroot, privileged_users }
+    }
+
+    // The findings of the crontab file at `path`, which cron only reads if it belongs to root for system crontabs
+    pub fn audit_file_permissions(&self, path: &Path) -> Vec<Finding> {
+        match check_permissions(path) {
+            Ok(()) => vec![],
+            Err(errors) => errors.into_iter().map(|message| Finding {
+                risk: Risk::High,
+                rule: "file-permissions",
+                source: path.display().to_string(),
+                line_number: 0,
+                line: String::new(),
+                message,
+            }).collect(),
+        }
+    }
+
+    // `owner` is the user of a user crontab. Lines which cannot be parsed are left to the checks.
+    pub fn audit_crontab(&self, contents: &str, source: &str, owner: Option<&str>) -> Vec<Finding> {
+        let parser_options = CrontabParserOptions::<&str> { crontab_owner: owner, ..Default::default() };
+        let rules = &self.config.rules;
+        let mut search_path = DEFAULT_CRON_PATH.to_string();
+        let mut findings = vec![];
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            let mut report = |risk, rule, message| findings.push(Finding {
+                risk,
+                rule,
+                source: source.to_string(),
+                line_number: index + 1,
+                line: line.to_string(),
+                message,
+            });
+            match parse_crontab(line, &parser_options) {
+                Ok(CrontabLine::Job { user, command, .. }) => {
+                    if rules.hidden_characters != RuleLevel::Off {
+                        for message in hidden_character_warnings(command) {
+                            report(Risk::High, "hidden-characters", message);
+                        }
+                    }
+                    if let Err(message) = check_denied_commands(command, &self.config.denied_commands) {
+                        report(Risk::High, "denied-command", message);
+                    }
+                    if let Err(message) = check_denied_paths(command, &self.config.denied_paths) {
+                        report(Risk::High, "denied-path", message);
+                    }
+                    if let (true, Some(message)) = (rules.pipe_to_shell != RuleLevel::Off, pipe_to_shell_warning(command)) {
+                        report(Risk::High, "pipe-to-shell", message);
+                    }
+                    if let (true, Some(message)) = (rules.plain_http != RuleLevel::Off, plain_http_warning(command)) {
+                        report(Risk::Medium, "plain-http", message);
+                    }
+                    if rules.secrets != RuleLevel::Off {
+                        for message in command_secret_warnings(command) {
+                            report(Risk::Medium, "secrets", message);
+                        }
+                    }
+                    let files = executed_files(command, &search_path, self.sysroot);
+                    for message in setuid_warnings(&files) {
+                        report(Risk::Medium, "setuid", message);
+                    }
+                    if self.privileged_users.iter().any(|privileged| privileged == user) {
+                        for message in foreign_owner_errors(&files, self.sysroot, user) {
+                            report(Risk::High, "foreign-owner", message);
+                        }
+                        for message in writable_file_warnings(&files) {
+                            report(Risk::High, "writable-file", message);
+                        }
+                        if let Some(message) = relative_program_warning(command) {
+                            report(Risk::Low, "relative-program", message);
+                        }
+                    }
+                },
+                Ok(CrontabLine::EnvironmentVariable { name, value }) => {
+                    if let (true, Some(message)) = (rules.secrets != RuleLevel::Off, variable_secret_warning(name, value)) {
+                        report(Risk::Medium, "secrets", message);
+                    }
+                    if name == "PATH" {
+                        for message in path_security_warnings(value) {
+                            report(Risk::Medium, "path-current-directory", message);
+                        }
+                        search_path = value.to_string();
+                    }
+                },
+                _ => (),
+            }
+        }
+        findings
+    }
+}
+
+// Highest risks first, then in the order of the files
+pub fn sort_findings(findings: &mut [Finding]) {
+    findings.sort_by(|a, b| (a.risk, &a.source, a.line_number).cmp(&(b.risk, &b.source, b.line_number)));
+}
+
+// "high    /etc/cron.d/backup:3 [writable-file] ...", with the line below, and the number of findings of each risk
+pub fn audit_text(findings: &[Finding]) -> String {
+    let mut text = String::new();
+    for finding in findings {
+        let location = if finding.line_number == 0 { finding.source.clone() } else { format!("{}:{}", finding.source, finding.line_number) };
+        text.push_str(&format!("{:6}  {} [{}] {}\n", finding.risk.name(), location, finding.rule, finding.message));
+        if !finding.line.is_empty() {
+            text.push_str(&format!("        {}\n", finding.line));
+        }
+    }
+    let count = |risk| findings.iter().filter(|finding| finding.risk == risk).count();
+    text.push_str(&format!("{} findings: {} high, {} medium, {} low\n", findings.len(), count(Risk::High), count(Risk::Medium), count(Risk::Low)));
+    text
+}
+
+#[derive(Serialize)]
+struct AuditFinding<'a> {
+    risk: &'static str,
+    rule: &'static str,
+    source: &'a str,
+    line: usize,
+    content: &'a str,
+    message: &'a str,
+}
+
+// A JSON array of the findings, for security tooling
+pub fn audit_json(findings: &[Finding]) -> String {
+    let findings: Vec<AuditFinding> = findings.iter().map(|finding| AuditFinding {
+        risk: finding.risk.name(),
+        rule: finding.rule,
+        source: &finding.source,
+        line: finding.line_number,
+        content: &finding.line,
+        message: &finding.message,
+    }).collect();
+    serde_json::to_string_pretty(&findings).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use audit::*;
+    use config::{Config, RuleLevel};
+
+    #[test]
+    fn test_audit_crontab() {
+        let mut config = Config::default();
+        let auditor = Auditor::new(&config, Path::new("/"));
+        let crontab = "PATH=/usr/bin:/bin:.\nDB_PASSWORD=hunter2\n0 2 * * * root backup\n*/5 * * * * www-data /tmp/poll.sh\n";
+        let mut findings = auditor.audit_crontab(crontab, "/etc/cron.d/jobs", None);
+        sort_findings(&mut findings);
+        let summary: Vec<(Risk, &str, usize)> = findings.iter().map(|finding| (finding.risk, finding.rule, finding.line_number)).collect();
+        assert_eq!(summary, vec![
+            (Risk::High, "denied-path", 4),
+            (Risk::Medium, "path-current-directory", 1),
+            (Risk::Medium, "secrets", 2),
+            (Risk::Low, "relative-program", 3),
+        ]);
+        assert!(audit_text(&findings).ends_with("4 findings: 1 high, 2 medium, 1 low\n"));
+        assert!(audit_json(&findings).contains("\"rule\": \"denied-path\""));
+
+        config.rules.secrets = RuleLevel::Off;
+        let auditor = Auditor::new(&config, Path::new("/"));
+        assert!(auditor.audit_crontab("DB_PASSWORD=hunter2\n", "/etc/crontab", None).is_empty());
+    }
+
+}
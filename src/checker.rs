@@ -0,0 +1,885 @@
+// Checks run on parsed crontab lines, keeping track of what cron would know at each line
+// (environment variables set so far, jobs seen...)
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::str;
+
+use command::{check_command, check_denied_commands, check_denied_paths, check_shell_syntax, executed_files, foreign_owner_errors,
+              setuid_warnings, writable_file_warnings};
+use config::Config;
+use dialect::Dialect;
+use environment::{DEFAULT_MAX_ENV_VALUE_LENGTH, ZONEINFO_DIRECTORY, check_random_delay, check_shell, check_start_hours_range,
+                  check_timezone, check_value, mailto_warnings, path_warnings, shells_from_etc_shells};
+use files::{in_root, uid0_usernames};
+use lints::{FieldSeparator, environment_expansion_warning, field_separator, has_output_redirection, hidden_character_warnings,
+            indentation_warning, line_continuation_warning, pipe_to_shell_warning, plain_http_warning, redirection_order_warning,
+            relative_argument_warnings, relative_program_warning, trailing_comment_warning, trailing_whitespace_warning,
+            unset_variable_warnings};
+
+use parser::{CrontabLine, CrontabParserOptions, OwnedCrontabLine, check_crontab_owner, parse_crontab, walk_errors};
+use policy::policy_violations;
+use secrets::{command_secret_warnings, variable_secret_warning};
+use suggest::closest_match;
+
+
+// PATH used by cron when the crontab does not set one
+pub const DEFAULT_CRON_PATH: &str = "/usr/bin:/bin";
+// SHELL used by cron when the crontab does not set one
+const DEFAULT_CRON_SHELL: &str = "/bin/sh";
+
+const UTF8_BOM: char = '\u{feff}';
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Diagnostic {
+    // 0 for diagnostics about the whole file
+    pub line_number: usize,
+    pub line: String,
+    pub severity: Severity,
+    // name of the check, e.g. "syntax" or "pipe-to-shell"
+    pub rule: &'static str,
+    pub message: String,
+}
+
+pub struct CheckerOptions<'a> {
+    pub config: &'a Config,
+    pub dialect: Dialect,
+    // check commands, SHELL and PATH against the filesystem
+    pub check_commands: bool,
+    // root of the filesystem the crontab runs in (e.g. an OS image), for the filesystem checks
+    pub sysroot: &'a Path,
+    // when set, SHELL must be one of these (as listed in /etc/shells)
+    pub allowed_shells: Option<Vec<String>>,
+    // when set, only these environment variables may be assigned
+    pub allowed_variables: Option<Vec<String>>,
+    pub check_shell_syntax: bool,
+    pub warn_relative_arguments: bool,
+    // replace invalid UTF-8 sequences and go on checking, rather than rejecting the file
+    pub lossy: bool,
+}
+
+impl<'a> CheckerOptions<'a> {
+    // the checks without the filesystem nor the shell, as the tests of the modules run them
+    #[cfg(test)]
+    pub fn test_defaults(config: &'a Config) -> CheckerOptions<'a> {
+        CheckerOptions {
+            config,
+            dialect: Dialect::Vixie,
+            check_commands: false,
+            sysroot: Path::new("/"),
+            allowed_shells: None,
+            allowed_variables: None,
+            check_shell_syntax: false,
+            warn_relative_arguments: false,
+            lossy: false,
+        }
+    }
+}
+
+pub struct Checker<'a> {
+    options: &'a CheckerOptions<'a>,
+    // commands are looked up the way cron does: with the PATH set in the crontab, or cron's default
+    search_path: String,
+    shell: String,
+    mailto_set: bool,
+    // names of the environment variables assigned so far
+    variables: Vec<String>,
+    // line of the last assignment of each variable
+    assignment_lines: HashMap<String, usize>,
+    job_count: usize,
+    // the first jobs (for the messages which list them)
+    first_job_lines: Vec<usize>,
+    // separator of the first job line, as (line number, separator)
+    field_separator: Option<(usize, FieldSeparator)>,
+    // root and the other uid 0 accounts, whose jobs' files are checked with check_commands
+    privileged_users: Vec<String>,
+    // the shells check_shell_syntax may run: those of the /etc/shells of the host (which runs them), if allowed
+    trusted_shells: Vec<String>,
+}
+
+// line numbers listed in messages
+const MAX_LISTED_LINES: usize = 5;
+
+// "line 3", "lines 3, 4 and 5"... for `count` jobs, of which `line_numbers` are the first ones
+fn format_line_numbers(line_numbers: &[usize], count: usize) -> String {
+    let listed: Vec<String> = line_numbers.iter().take(MAX_LISTED_LINES).map(|n| n.to_string()).collect();
+    match listed.len() {
+        1 => format!("line {}", listed[0]),
+        _ if count > MAX_LISTED_LINES => format!("lines {}... ({} jobs)", listed.join(", "), count),
+        n => format!("lines {} and {}", listed[..n - 1].join(", "), listed[n - 1]),
+    }
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(options: &'a CheckerOptions<'a>) -> Self {
+        Checker {
+            options,
+            search_path: DEFAULT_CRON_PATH.to_string(),
+            shell: DEFAULT_CRON_SHELL.to_string(),
+            mailto_set: false,
+            variables: vec![],
+            assignment_lines: HashMap::new(),
+            job_count: 0,
+            first_job_lines: vec![],
+            field_separator: None,
+            privileged_users: if options.check_commands {
+                let mut users = vec!["root".to_string()];
+                users.extend(uid0_usernames(options.sysroot).unwrap_or_default());
+                users
+            } else {
+                vec![]
+            },
+            trusted_shells: if options.check_shell_syntax {
+                let allowed = |shell: &String| options.allowed_shells.as_ref().map(|allowed| allowed.contains(shell)).unwrap_or(true);
+                shells_from_etc_shells(Path::new("/")).unwrap_or_default().into_iter().filter(allowed).collect()
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    pub fn check_line(&mut self, line_number: usize, line: &str, parsed: &CrontabLine) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        {
+            let mut report = |severity, rule, message| diagnostics.push(Diagnostic {
+                line_number,
+                line: line.to_string(),
+                severity,
+                rule,
+                message,
+            });
+            let rules = &self.options.config.rules;
+            if let (Some(severity), Some(message)) = (rules.trailing_whitespace.severity(), trailing_whitespace_warning(line)) {
+                report(severity, "trailing-whitespace", message);
+            }
+            match *parsed {
+                CrontabLine::Job { schedule, user, command } => {
+                    if let (Some(severity), Some(message)) = (rules.indentation.severity(), indentation_warning(line)) {
+                        report(severity, "indentation", message);
+                    }
+                    if let Some(severity) = rules.mixed_separators.severity() {
+                        // the command runs until the end of the line
+                        let fields = &line[..line.len() - command.len()];
+                        match (field_separator(fields), self.field_separator) {
+                            (Some(FieldSeparator::Mixed), _) => report(severity, "mixed-separators", format!(
+                                "the fields of this line are separated with {}", FieldSeparator::Mixed.description()
+                            )),
+                            (Some(separator), Some((first_line_number, first_separator))) if separator != first_separator => report(severity, "mixed-separators", format!(
+                                "the fields of this line are separated with {}, while line {} uses {}",
+                                separator.description(), first_line_number, first_separator.description()
+                            )),
+                            (Some(separator), None) => self.field_separator = Some((line_number, separator)),
+                            _ => (),
+                        }
+                    }
+                    if let Err(reason) = check_denied_commands(command, &self.options.config.denied_commands) {
+                        report(Severity::Error, "denied-command", reason);
+                    }
+                    if let Err(reason) = check_denied_paths(command, &self.options.config.denied_paths) {
+                        report(Severity::Error, "denied-path", reason);
+                    }
+                    for policy in policy_violations(&self.options.config.policies, user, schedule, command, &self.variables) {
+                        if let Some(severity) = policy.level.severity() {
+                            report(severity, "policy", format!("against policy: {}", policy.message));
+                        }
+                    }
+                    if self.options.check_commands {
+                        if let Err(reason) = check_command(command, &self.search_path, self.options.sysroot) {
+                            report(Severity::Error, "command-not-found", reason);
+                        }
+                        let files = executed_files(command, &self.search_path, self.options.sysroot);
+                        for warning in setuid_warnings(&files) {
+                            report(Severity::Warning, "setuid", warning);
+                        }
+                        if self.privileged_users.iter().any(|privileged| privileged == user) {
+                            for error in foreign_owner_errors(&files, self.options.sysroot, user) {
+                                report(Severity::Error, "foreign-owner", error);
+                            }
+                            for warning in writable_file_warnings(&files) {
+                                report(Severity::Warning, "writable-file", warning);
+                            }
+                        }
+                    }
+                    if self.options.check_shell_syntax {
+                        if let Err(reason) = check_shell_syntax(command, &self.shell, &self.trusted_shells) {
+                            report(Severity::Error, "shell-syntax", reason);
+                        }
+                    }
+                    if let Some(warning) = relative_program_warning(command) {
+                        report(Severity::Warning, "relative-program", warning);
+                    }
+                    if self.options.warn_relative_arguments {
+                        for warning in relative_argument_warnings(command) {
+                            report(Severity::Warning, "relative-argument", warning);
+                        }
+                    }
+                    if let (Some(severity), Some(message)) = (self.options.config.rules.pipe_to_shell.severity(), pipe_to_shell_warning(command)) {
+                        report(severity, "pipe-to-shell", message);
+                    }
+                    if let (Some(severity), Some(message)) = (self.options.config.rules.plain_http.severity(), plain_http_warning(command)) {
+                        report(severity, "plain-http", message);
+                    }
+                    if let Some(warning) = redirection_order_warning(command) {
+                        report(Severity::Warning, "redirection-order", warning);
+                    }
+                    if let Some(warning) = line_continuation_warning(command) {
+                        report(Severity::Warning, "line-continuation", warning);
+                    }
+                    if let (Some(severity), Some(message)) = (rules.trailing_comment.severity(), trailing_comment_warning(command)) {
+                        report(severity, "trailing-comment", message);
+                    }
+                    if let Some(severity) = self.options.config.rules.hidden_characters.severity() {
+                        for message in hidden_character_warnings(command) {
+                            report(severity, "hidden-characters", message);
+                        }
+                    }
+                    if let Some(severity) = self.options.config.rules.secrets.severity() {
+                        for message in command_secret_warnings(command) {
+                            report(severity, "secrets", message);
+                        }
+                    }
+                    for warning in unset_variable_warnings(command, &self.variables) {
+                        report(Severity::Warning, "unset-variable", warning);
+                    }
+                    // MAILTO only applies to the jobs below it
+                    if !self.mailto_set && !has_output_redirection(command) {
+                        report(Severity::Warning, "unredirected-output", "the output of this job is not redirected and MAILTO is not set: \
+                                                                          it will be mailed to the crontab owner, or lost if no mail agent is installed".to_string());
+                    }
+                    self.job_count += 1;
+                    if self.first_job_lines.len() < MAX_LISTED_LINES {
+                        self.first_job_lines.push(line_number);
+                    }
+                },
+                CrontabLine::EnvironmentVariable { name, value } => {
+                    if let Some(ref allowed) = self.options.allowed_variables {
+                        if !allowed.iter().any(|variable| variable == name) {
+                            let message = match closest_match(name, allowed) {
+                                Some(suggestion) => format!("environment variable {} is not allowed, did you mean {}?", name, suggestion),
+                                None => format!("environment variable {} is not allowed (allowed: {})", name, allowed.join(", ")),
+                            };
+                            report(Severity::Error, "env-not-allowed", message);
+                        }
+                    }
+                    let max_length = self.options.config.max_env_value_length.unwrap_or(DEFAULT_MAX_ENV_VALUE_LENGTH);
+                    if let Err(reason) = check_value(name, value, max_length) {
+                        report(Severity::Error, "env-value", reason);
+                    }
+                    if let Some(warning) = environment_expansion_warning(name, value) {
+                        report(Severity::Warning, "env-expansion", warning);
+                    }
+                    if let (Some(severity), Some(message)) = (self.options.config.rules.secrets.severity(), variable_secret_warning(name, value)) {
+                        report(severity, "secrets", message);
+                    }
+                    let filesystem = if self.options.check_commands { Some(self.options.sysroot) } else { None };
+                    match name {
+                        "PATH" => {
+                            for warning in path_warnings(value, filesystem) {
+                                report(Severity::Warning, "path", warning);
+                            }
+                            self.search_path = value.to_string();
+                        },
+                        "SHELL" => {
+                            let allowed_shells = self.options.allowed_shells.as_ref().map(|shells| &shells[..]);
+                            if let Err(reason) = check_shell(value, filesystem, allowed_shells) {
+                                report(Severity::Error, "shell", reason);
+                            }
+                            self.shell = value.to_string();
+                        },
+                        "TZ" | "CRON_TZ" => {
+                            if let Err(reason) = check_timezone(name, value, &in_root(self.options.sysroot, ZONEINFO_DIRECTORY)) {
+                                report(Severity::Error, "timezone", reason);
+                            }
+                        },
+                        "RANDOM_DELAY" if self.options.dialect == Dialect::Cronie => {
+                            if let Err(reason) = check_random_delay(value) {
+                                report(Severity::Error, "random-delay", reason);
+                            }
+                        },
+                        "START_HOURS_RANGE" if self.options.dialect == Dialect::Cronie => {
+                            if let Err(reason) = check_start_hours_range(value) {
+                                report(Severity::Error, "start-hours-range", reason);
+                            }
+                        },
+                        "MAILTO" => {
+                            for warning in mailto_warnings(value) {
+                                report(Severity::Warning, "mailto", warning);
+                            }
+                            self.mailto_set = true;
+                        },
+                        _ => (),
+                    }
+                    match self.assignment_lines.insert(name.to_string(), line_number) {
+                        Some(previous_line_number) => report(Severity::Warning, "env-reassigned", format!(
+                            "{} was already set on line {}: jobs after line {} use the new value", name, previous_line_number, line_number
+                        )),
+                        None => {
+                            if self.job_count > 0 {
+                                report(Severity::Info, "env-order", format!(
+                                    "{} is only set for the jobs below: it does not apply to the jobs on {}", name, format_line_numbers(&self.first_job_lines, self.job_count)
+                                ));
+                            }
+                            self.variables.push(name.to_string());
+                        },
+                    }
+                },
+                _ => (),
+            }
+        }
+        diagnostics
+    }
+
+    // Diagnostics which can only be given once the whole crontab has been read
+    pub fn finish(self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        match (self.options.config.max_jobs, self.options.config.rules.too_many_jobs.severity()) {
+            (Some(max_jobs), Some(severity)) if self.job_count > max_jobs => diagnostics.push(Diagnostic {
+                line_number: 0,
+                line: String::new(),
+                severity,
+                rule: "too-many-jobs",
+                message: format!("the file contains {} jobs (maximum: {}): split it into smaller files", self.job_count, max_jobs),
+            }),
+            _ => (),
+        }
+        diagnostics
+    }
+}
+
+// Decode crontab contents and check them, in place (they may be a memory-mapped file): valid UTF-8 is not copied
+pub fn check_crontab_bytes<T: AsRef<str>>(bytes: &[u8], from_file: bool, parser_options: &CrontabParserOptions<T>, options: &CheckerOptions) -> Vec<Diagnostic> {
+    match str::from_utf8(bytes) {
+        Ok(contents) => check_crontab(contents, from_file, parser_options, options),
+        Err(e) => {
+            let offset = e.valid_up_to();
+            let line_number = bytes[..offset].iter().filter(|&&c| c == b'\n').count() + 1;
+            let line = bytes.split(|&c| c == b'\n').nth(line_number - 1).unwrap_or(&[]);
+            let mut diagnostics = vec![Diagnostic {
+                line_number,
+                line: String::from_utf8_lossy(line).into_owned(),
+                severity: if options.lossy { Severity::Warning } else { Severity::Error },
+                rule: "utf8",
+                message: if options.lossy {
+                    format!("invalid UTF-8 at byte offset {}: invalid sequences were replaced", offset)
+                } else {
+                    format!("invalid UTF-8 at byte offset {} (use --lossy to check the rest of the file anyway)", offset)
+                },
+            }];
+            if options.lossy {
+                diagnostics.extend(check_crontab(&String::from_utf8_lossy(bytes), from_file, parser_options, options));
+            }
+            diagnostics
+        }
+    }
+}
+
+// Lines left by git (or other VCS) in files with unresolved merge conflicts
+fn is_conflict_marker(line: &str) -> bool {
+    let marker = |c: &str| line.starts_with(&c.repeat(7)) && line[7..].chars().next().map(|c| c == ' ').unwrap_or(true);
+    marker("<") || marker(">") || marker("|") || line == "======="
+}
+
+// files edited on Windows: for cron, the carriage return is part of the line (and of the command)
+fn without_carriage_return(line: &str) -> &str {
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+// The line parsed, or the message of its errors
+type ParsedLine<'a> = Result<CrontabLine<'a>, String>;
+
+fn parse_line<'a, T: AsRef<str>>(line: &'a str, parser_options: &CrontabParserOptions<'a, T>) -> ParsedLine<'a> {
+    parse_crontab(line, parser_options).map_err(|errors| walk_errors(&errors))
+}
+
+// Parse results of the lines of a document being edited (by their text, without the '\r'): after a change, only the
+// new and modified lines need to be parsed
+#[derive(Default)]
+pub struct ParseCache {
+    lines: HashMap<String, Result<OwnedCrontabLine, String>>,
+}
+
+impl ParseCache {
+    // Parse the lines which are not in the cache, and forget the ones which are no longer in the document
+    pub fn update<T: AsRef<str>>(&mut self, contents: &str, parser_options: &CrontabParserOptions<T>) {
+        let lines: HashSet<&str> = contents.trim_start_matches(UTF8_BOM).split('\n').map(without_carriage_return).collect();
+        self.lines.retain(|line, _| lines.contains(&line[..]));
+        for line in lines {
+            if !self.lines.contains_key(line) {
+                let parsed = parse_line(line, parser_options).map(|parsed| parsed.to_owned_line());
+                self.lines.insert(line.to_string(), parsed);
+            }
+        }
+    }
+
+    fn get(&self, line: &str) -> Option<ParsedLine<'_>> {
+        self.lines.get(line).map(|parsed| match *parsed {
+            Ok(ref parsed) => Ok(parsed.as_line()),
+            Err(ref message) => Err(message.clone()),
+        })
+    }
+}
+
+// Check a line (without its '\n'), returns the line (without its '\r') and what it was parsed as, if it is valid
+fn check_crontab_line<'l, 'p, P>(checker: &mut Checker, line_number: usize, line: &'l str, parse: &P, diagnostics: &mut Vec<Diagnostic>) -> Option<(&'l str, CrontabLine<'p>)>
+    where P: Fn(&'l str) -> ParsedLine<'p> {
+    let stripped = without_carriage_return(line);
+    if let (true, Some(severity)) = (stripped.len() < line.len(), checker.options.config.rules.crlf.severity()) {
+        diagnostics.push(Diagnostic {
+            line_number,
+            line: stripped.to_string(),
+            severity,
+            rule: "crlf",
+            message: "the line ends with a carriage return (CRLF line ending): cron will pass it to the command".to_string(),
+        });
+    }
+    let line = stripped;
+    let error = |rule, message| Diagnostic { line_number, line: line.to_string(), severity: Severity::Error, rule, message };
+    if is_conflict_marker(line) {
+        diagnostics.push(error("conflict-marker", "unresolved merge conflict marker".to_string()));
+        return None;
+    }
+    match parse(line) {
+        Ok(parsed) => {
+            diagnostics.extend(checker.check_line(line_number, line, &parsed));
+            Some((line, parsed))
+        },
+        Err(message) => { diagnostics.push(error("syntax", message)); None },
+    }
+}
+
+// Parse and check a whole crontab. Some checks only make sense when it is read from a file,
+// rather than from a stream.
+pub fn check_crontab<T: AsRef<str>>(contents: &str, from_file: bool, parser_options: &CrontabParserOptions<T>, options: &CheckerOptions) -> Vec<Diagnostic> {
+    check_lines(contents, from_file, parser_options, options, &|line| parse_line(line, parser_options))
+}
+
+// Check a crontab whose lines are in the cache (see ParseCache::update)
+pub fn check_crontab_cached<T: AsRef<str>>(contents: &str, parser_options: &CrontabParserOptions<T>, options: &CheckerOptions, cache: &ParseCache) -> Vec<Diagnostic> {
+    check_lines(contents, true, parser_options, options, &|line| cache.get(line).unwrap_or_else(|| parse_line(line, parser_options)))
+}
+
+fn check_lines<'c, 'p, T: AsRef<str>, P>(contents: &'c str, from_file: bool, parser_options: &CrontabParserOptions<T>, options: &CheckerOptions, parse: &P) -> Vec<Diagnostic>
+    where P: Fn(&'c str) -> ParsedLine<'p> {
+    let mut checker = Checker::new(options);
+    let mut diagnostics = vec![];
+    let mut last_line = None;
+    let mut has_jobs = false;
+    if let Err(error) = check_crontab_owner(parser_options) {
+        diagnostics.push(Diagnostic {
+            line_number: 0,
+            line: String::new(),
+            severity: Severity::Error,
+            rule: "crontab-owner",
+            message: format!("user crontab: {}", error),
+        });
+    }
+    // editors on Windows may add a byte order mark, which cron would read as part of the first field
+    let bom_stripped = contents.starts_with(UTF8_BOM);
+    let contents = contents.trim_start_matches(UTF8_BOM);
+    if bom_stripped {
+        diagnostics.push(Diagnostic {
+            line_number: 1,
+            line: contents.lines().next().unwrap_or("").to_string(),
+            severity: Severity::Warning,
+            rule: "bom",
+            message: "the file starts with a UTF-8 byte order mark (BOM): remove it, cron does not expect it".to_string(),
+        });
+    }
+    let mut lines: Vec<&str> = contents.split('\n').collect();
+    if lines.last() == Some(&"") {
+        lines.pop();
+    }
+    for (index, line) in lines.into_iter().enumerate() {
+        let line_number = index + 1;
+        if let Some((line, parsed)) = check_crontab_line(&mut checker, line_number, line, parse, &mut diagnostics) {
+            if let CrontabLine::Job { .. } = parsed {
+                has_jobs = true;
+            }
+            last_line = Some((line_number, line, parsed));
+        }
+    }
+    diagnostics.extend(checker.finish());
+    // a file in /etc/cron.d without jobs is usually the output of a broken template (on stdin, it may be a fragment)
+    if from_file && !has_jobs {
+        diagnostics.push(Diagnostic {
+            line_number: 0,
+            line: String::new(),
+            severity: Severity::Info,
+            rule: "no-jobs",
+            message: "the file does not contain any job".to_string(),
+        });
+    }
+    // vixie cron silently ignores a last line which is not terminated
+    if from_file && !contents.is_empty() && !contents.ends_with('\n') {
+        match last_line {
+            Some((line_number, line, CrontabLine::Job { .. })) | Some((line_number, line, CrontabLine::EnvironmentVariable { .. })) => {
+                diagnostics.push(Diagnostic {
+                    line_number,
+                    line: line.to_string(),
+                    severity: Severity::Error,
+                    rule: "final-newline",
+                    message: "the file does not end with a newline: cron ignores this last line".to_string(),
+                });
+            },
+            _ => (),
+        }
+    }
+    diagnostics
+}
+
+// Checks crontabs streamed through a pipe, giving the diagnostics of each line as soon as it is read
+pub struct CrontabChecker<'a, T: AsRef<str> + 'a> {
+    parser_options: &'a CrontabParserOptions<'a, T>,
+    options: &'a CheckerOptions<'a>,
+}
+
+impl<'a, T: AsRef<str> + 'a> CrontabChecker<'a, T> {
+    pub fn new(parser_options: &'a CrontabParserOptions<'a, T>, options: &'a CheckerOptions<'a>) -> Self {
+        CrontabChecker { parser_options, options }
+    }
+
+    // Only the line being checked is kept in memory, with what the checks remember of the previous ones: the variables
+    // assigned and counters, not the previous jobs. Checks which need the whole file are left out, as for stdin.
+    pub fn check_stream<R: BufRead>(&self, reader: R) -> DiagnosticStream<'a, R, T> {
+        let mut pending = VecDeque::new();
+        if let Err(error) = check_crontab_owner(self.parser_options) {
+            pending.push_back(Diagnostic {
+                line_number: 0,
+                line: String::new(),
+                severity: Severity::Error,
+                rule: "crontab-owner",
+                message: format!("user crontab: {}", error),
+            });
+        }
+        DiagnosticStream {
+            reader,
+            parser_options: self.parser_options,
+            checker: Some(Checker::new(self.options)),
+            line: vec![],
+            line_number: 0,
+            offset: 0,
+            pending,
+        }
+    }
+}
+
+pub struct DiagnosticStream<'a, R, T: AsRef<str> + 'a> {
+    reader: R,
+    parser_options: &'a CrontabParserOptions<'a, T>,
+    // None once the stream has been checked
+    checker: Option<Checker<'a>>,
+    line: Vec<u8>,
+    line_number: usize,
+    // of the line in the stream, in bytes
+    offset: usize,
+    pending: VecDeque<Diagnostic>,
+}
+
+impl<'a, R: BufRead, T: AsRef<str> + 'a> DiagnosticStream<'a, R, T> {
+    // lines read so far
+    pub fn line_count(&self) -> usize {
+        self.line_number
+    }
+
+    fn check_line(&mut self) {
+        let mut checker = match self.checker.take() {
+            Some(checker) => checker,
+            None => return,
+        };
+        let bytes = if self.line.ends_with(b"\n") { &self.line[..self.line.len() - 1] } else { &self.line[..] };
+        let decoded = String::from_utf8_lossy(bytes);
+        let mut line = &decoded[..];
+        let mut diagnostics = vec![];
+        if let Err(e) = str::from_utf8(bytes) {
+            let offset = self.offset + e.valid_up_to();
+            let lossy = checker.options.lossy;
+            diagnostics.push(Diagnostic {
+                line_number: self.line_number,
+                line: line.to_string(),
+                severity: if lossy { Severity::Warning } else { Severity::Error },
+                rule: "utf8",
+                message: if lossy {
+                    format!("invalid UTF-8 at byte offset {}: invalid sequences were replaced", offset)
+                } else {
+                    format!("invalid UTF-8 at byte offset {} (use --lossy to check the rest of the file anyway)", offset)
+                },
+            });
+            // as for files, the rest is not checked
+            if !lossy {
+                self.pending.extend(diagnostics);
+                return;
+            }
+        }
+        if self.line_number == 1 && line.starts_with(UTF8_BOM) {
+            line = line.trim_start_matches(UTF8_BOM);
+            diagnostics.push(Diagnostic {
+                line_number: 1,
+                line: line.to_string(),
+                severity: Severity::Warning,
+                rule: "bom",
+                message: "the file starts with a UTF-8 byte order mark (BOM): remove it, cron does not expect it".to_string(),
+            });
+        }
+        let parser_options = self.parser_options;
+        check_crontab_line(&mut checker, self.line_number, line, &|line| parse_line(line, parser_options), &mut diagnostics);
+        self.pending.extend(diagnostics);
+        self.checker = Some(checker);
+    }
+}
+
+impl<'a, R: BufRead, T: AsRef<str> + 'a> Iterator for DiagnosticStream<'a, R, T> {
+    type Item = io::Result<Diagnostic>;
+
+    fn next(&mut self) -> Option<io::Result<Diagnostic>> {
+        loop {
+            if let Some(diagnostic) = self.pending.pop_front() {
+                return Some(Ok(diagnostic));
+            }
+            self.checker.as_ref()?;
+            self.offset += self.line.len();
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => {
+                    let checker = self.checker.take().unwrap();
+                    self.pending.extend(checker.finish());
+                },
+                Ok(_) => {
+                    self.line_number += 1;
+                    self.check_line();
+                },
+                Err(e) => {
+                    self.checker = None;
+                    return Some(Err(e));
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::slice;
+    use checker::*;
+    use config::{Config, RuleLevel};
+    use parser::{CrontabLine, CrontabParserOptions};
+
+    fn check(lines: &[CrontabLine]) -> Vec<Diagnostic> {
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let mut checker = Checker::new(&options);
+        let mut diagnostics = vec![];
+        for (index, line) in lines.iter().enumerate() {
+            diagnostics.extend(checker.check_line(index + 1, "", line));
+        }
+        diagnostics.extend(checker.finish());
+        diagnostics
+    }
+
+    #[test]
+    fn test_unredirected_output_without_mailto() {
+        let job = CrontabLine::Job { schedule: "0 5 * * *", user: "root", command: "/usr/bin/backup" };
+        let diagnostics = check(slice::from_ref(&job));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+
+        let mailto = CrontabLine::EnvironmentVariable { name: "MAILTO", value: "ops@example.com" };
+        assert!(check(&[mailto.clone(), job.clone()]).is_empty());
+        // the jobs above MAILTO still mail their output to the crontab owner
+        let diagnostics = check(&[job.clone(), mailto, job.clone()]);
+        assert_eq!(diagnostics.iter().filter(|diagnostic| diagnostic.rule == "unredirected-output").count(), 1);
+        assert_eq!(diagnostics[0].line_number, 1);
+
+        let redirected = CrontabLine::Job { schedule: "0 5 * * *", user: "root", command: "/usr/bin/backup >/dev/null 2>&1" };
+        assert!(check(&[redirected]).is_empty());
+    }
+
+    #[test]
+    fn test_assignment_after_jobs() {
+        let job = CrontabLine::Job { schedule: "0 5 * * *", user: "root", command: "/usr/bin/backup >/dev/null" };
+        let diagnostics = check(&[
+            CrontabLine::EnvironmentVariable { name: "LANG", value: "C" },
+            job.clone(),
+            job.clone(),
+            CrontabLine::EnvironmentVariable { name: "MAILTO", value: "ops@example.com" },
+        ]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 4);
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+        assert_eq!(diagnostics[0].message, "MAILTO is only set for the jobs below: it does not apply to the jobs on lines 2 and 3");
+    }
+
+    #[test]
+    fn test_check_crontab() {
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let diagnostics = check_crontab("MAILTO=\"\"\n* * * * * root /bin/true\n61 * * * * root /bin/true\n", true, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 3);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_missing_final_newline() {
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let contents = "MAILTO=\"\"\n* * * * * root /bin/true";
+        let diagnostics = check_crontab(contents, true, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 2);
+        assert_eq!(diagnostics[0].line, "* * * * * root /bin/true");
+        assert!(check_crontab(contents, false, &parser_options, &options).is_empty());
+        assert!(check_crontab("MAILTO=\"\"\n* * * * * root /bin/true\n# the end", true, &parser_options, &options).is_empty());
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let diagnostics = check_crontab("MAILTO=\"\"\r\n* * * * * root /bin/true\r\n", true, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1].line, "* * * * * root /bin/true");
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_no_jobs() {
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let diagnostics = check_crontab("# nothing here\nMAILTO=\"\"\n", true, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+        assert_eq!(diagnostics[0].line_number, 0);
+        assert!(check_crontab("# nothing here\n", false, &parser_options, &options).is_empty());
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let diagnostics = check_crontab("\u{feff}MAILTO=\"\"\n* * * * * root /bin/true\n", true, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, "MAILTO=\"\"");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_conflict_markers() {
+        assert!(is_conflict_marker("<<<<<<< HEAD"));
+        assert!(is_conflict_marker("======="));
+        assert!(is_conflict_marker(">>>>>>> feature/new-jobs"));
+        assert!(is_conflict_marker("||||||| merged common ancestors"));
+        assert!(!is_conflict_marker("<<<<<<<< HEAD"));
+        assert!(!is_conflict_marker("# ======="));
+
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let contents = "MAILTO=\"\"\n<<<<<<< HEAD\n0 * * * * root /bin/true\n=======\n1 * * * * root /bin/true\n>>>>>>> topic\n";
+        let diagnostics = check_crontab(contents, true, &parser_options, &options);
+        assert_eq!(diagnostics.iter().map(|d| d.line_number).collect::<Vec<_>>(), vec![2, 4, 6]);
+        assert!(diagnostics.iter().all(|d| d.message == "unresolved merge conflict marker"));
+    }
+
+    #[test]
+    fn test_max_jobs() {
+        let config = Config { max_jobs: Some(2), ..Default::default() };
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let contents = "MAILTO=\"\"\n0 * * * * root /bin/true\n1 * * * * root /bin/true\n";
+        assert!(check_crontab(contents, true, &parser_options, &options).is_empty());
+        let contents = "MAILTO=\"\"\n0 * * * * root /bin/true\n1 * * * * root /bin/true\n2 * * * * root /bin/true\n";
+        let diagnostics = check_crontab(contents, true, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 0);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let config = Config::default();
+        let mut options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let contents = b"MAILTO=\"\"\n# caf\xe9\n61 * * * * root /bin/true\n".to_vec();
+        let diagnostics = check_crontab_bytes(&contents, true, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 2);
+        assert_eq!(diagnostics[0].message, "invalid UTF-8 at byte offset 15 (use --lossy to check the rest of the file anyway)");
+
+        options.lossy = true;
+        let diagnostics = check_crontab_bytes(&contents, false, &parser_options, &options);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].line_number, 3);
+    }
+
+    #[test]
+    fn test_check_stream() {
+        let config = Config::default();
+        let options = CheckerOptions::test_defaults(&config);
+        let parser_options = CrontabParserOptions::<&str>::default();
+        let contents = "PATH=/usr/bin\r\n61 * * * * root /bin/true\n0 2 * * * root /usr/bin/backup >/dev/null 2>&1\n";
+        let streamed: Vec<Diagnostic> = CrontabChecker::new(&parser_options, &options)
+            .check_stream(contents.as_bytes())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(streamed, check_crontab(contents, false, &parser_options, &options));
+        assert_eq!(streamed.iter().map(|diagnostic| diagnostic.line_number).collect::<Vec<usize>>(), vec![1, 2]);
+
+        let mut stream = CrontabChecker::new(&parser_options, &options).check_stream(&b"# caf\xe9\n61 * * * * root /bin/true\n"[..]);
+        assert_eq!(stream.next().unwrap().unwrap().message, "invalid UTF-8 at byte offset 5 (use --lossy to check the rest of the file anyway)");
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_whitespace_style() {
+        let mut config = Config::default();
+        config.rules.trailing_whitespace = RuleLevel::Warning;
+        config.rules.mixed_separators = RuleLevel::Error;
+        let options = CheckerOptions::test_defaults(&config);
+        let mut checker = Checker::new(&options);
+        assert!(checker.check_line(1, "0 5 * * * root /bin/true >/dev/null", &CrontabLine::Job { schedule: "0 5 * * *", user: "root", command: "/bin/true >/dev/null" }).is_empty());
+        let diagnostics = checker.check_line(2, "0\t5\t*\t*\t*\troot\t/bin/true >/dev/null ", &CrontabLine::Job { schedule: "0 5 * * *", user: "root", command: "/bin/true >/dev/null " });
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "trailing whitespace at the end of the line");
+        assert_eq!(diagnostics[1].message, "the fields of this line are separated with tabs, while line 1 uses spaces");
+    }
+
+    #[test]
+    fn test_format_line_numbers() {
+        assert_eq!(format_line_numbers(&[4], 1), "line 4");
+        assert_eq!(format_line_numbers(&[1, 2, 3], 3), "lines 1, 2 and 3");
+        assert_eq!(format_line_numbers(&[1, 2, 3, 4, 5], 6), "lines 1, 2, 3, 4, 5... (6 jobs)");
+    }
+
+    #[test]
+    fn test_duplicate_assignments() {
+        let diagnostics = check(&[
+            CrontabLine::EnvironmentVariable { name: "MAILTO", value: "ops@example.com" },
+            CrontabLine::EnvironmentVariable { name: "LANG", value: "C" },
+            CrontabLine::EnvironmentVariable { name: "MAILTO", value: "dev@example.com" },
+        ]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 3);
+        assert_eq!(diagnostics[0].message, "MAILTO was already set on line 1: jobs after line 3 use the new value");
+    }
+
+}
@@ -0,0 +1,176 @@
+// Stable codes of the rules (CTC001...), and their explanations for `crontabcheck explain-code`, so that the messages
+// of the checks can stay short. Codes are never renumbered nor reused: new rules take the next one.
+
+// (code, rule, title, what the rule looks for and why it matters, a line it reports, how to write it instead), the
+// examples being empty when there is nothing to show
+const RULE_CODES: &[(&str, &str, &str, &str, &str, &str)] = &[
+    ("CTC001", "syntax", "invalid line",
+     "The line is neither a comment, an environment variable assignment nor a job cron can parse: a field is out of \
+      bounds or misspelled, a field or the user is missing... cron skips it (some versions refuse the whole file).",
+     "0 25 * * * root /usr/bin/backup", "0 23 * * * root /usr/bin/backup"),
+    ("CTC002", "utf8", "invalid UTF-8",
+     "The file is not valid UTF-8, usually because it was saved in another encoding (e.g. Latin-1). Commands and \
+      values would not get the bytes their authors meant.", "", ""),
+    ("CTC003", "crlf", "CRLF line ending",
+     "The line ends with a carriage return, as files edited on Windows do. cron passes it to the command, as part of \
+      its last argument, which then names another file.", "", ""),
+    ("CTC004", "bom", "byte order mark",
+     "The file starts with a UTF-8 byte order mark, which cron reads as part of the first field of the first line.", "", ""),
+    ("CTC005", "conflict-marker", "merge conflict marker",
+     "The line is a marker left by git in a file with an unresolved merge conflict: both versions of the conflicting \
+      lines are still in the file.", "<<<<<<< HEAD", ""),
+    ("CTC006", "final-newline", "no final newline",
+     "The last line of the file does not end with a newline: cron ignores it.", "", ""),
+    ("CTC007", "no-jobs", "no jobs",
+     "The file does not contain any job, which is usually a mistake (e.g. a truncated file).", "", ""),
+    ("CTC008", "crontab-owner", "invalid user crontab",
+     "The user a user crontab (in a cron spool directory) belongs to is not a valid user.", "", ""),
+    ("CTC009", "too-many-jobs", "too many jobs",
+     "The file contains more jobs than the max_jobs of the configuration: large files are hard to review.", "", ""),
+    ("CTC010", "unredirected-output", "output sent by mail",
+     "Jobs write output which is not redirected, and MAILTO is not set above them: cron mails it to the owner of the crontab, \
+      where it is usually lost.", "0 2 * * * root /usr/bin/backup", "0 2 * * * root /usr/bin/backup >> /var/log/backup.log 2>&1"),
+    ("CTC011", "trailing-whitespace", "trailing whitespace",
+     "The line ends with spaces or tabs, which become part of the command.", "", ""),
+    ("CTC012", "mixed-separators", "mixed field separators",
+     "The fields of the line are separated with tabs while other lines use spaces (or the other way around).", "", ""),
+    ("CTC013", "denied-command", "denied command",
+     "The command matches one of the denied_commands patterns of the configuration.", "", ""),
+    ("CTC014", "denied-path", "denied path",
+     "The command runs a program from a directory anyone can write to (/tmp, /var/tmp, /dev/shm) or from one of the \
+      denied_paths of the configuration: anyone could replace it.", "*/5 * * * * root /tmp/poll.sh", "*/5 * * * * root /usr/local/bin/poll.sh"),
+    ("CTC015", "policy", "against policy",
+     "The job breaks one of the [[policies]] of the configuration, whose message says why.", "", ""),
+    ("CTC016", "command-not-found", "command not found",
+     "The program of the command is not in the PATH cron runs jobs with (checked with --check-commands).",
+     "0 2 * * * root backup.sh", "0 2 * * * root /usr/local/bin/backup.sh"),
+    ("CTC017", "setuid", "setuid program",
+     "The job runs a setuid or setgid program: it runs with the privileges of the owner of the file, not of the user \
+      of the job.", "", ""),
+    ("CTC018", "foreign-owner", "file owned by another user",
+     "A privileged job runs a file (or a file in a directory) owned by another user than root: that user can change \
+      what runs as root.", "", ""),
+    ("CTC019", "writable-file", "writable file",
+     "A privileged job runs a file which its group or other users can write to: they can change what runs as root.", "", ""),
+    ("CTC020", "shell-syntax", "shell syntax error",
+     "The command is not valid for the SHELL of the crontab (checked with --check-shell-syntax): the job would fail \
+      each time it runs.", "0 2 * * * root echo 'unterminated", "0 2 * * * root echo 'terminated'"),
+    ("CTC021", "relative-program", "relative program",
+     "A privileged job runs a program by its name or a relative path: cron looks it up with its own PATH, from the \
+      home directory of the user, so the program may not be the one intended.",
+     "0 2 * * * root backup.sh", "0 2 * * * root /usr/local/bin/backup.sh"),
+    ("CTC022", "relative-argument", "relative argument",
+     "An argument of the command is a relative path (with --warn-relative-arguments): jobs run from the home \
+      directory of their user, not from where the crontab is.", "0 2 * * * root /usr/bin/tar czf backup.tgz data", "0 2 * * * root /usr/bin/tar czf /srv/backup.tgz /srv/data"),
+    ("CTC023", "pipe-to-shell", "download piped into a shell",
+     "The job downloads code and runs it at once: whoever controls the server (or the network) controls the job.",
+     "0 3 * * * root curl -s https://example.com/update.sh | sh", "0 3 * * * root /usr/local/bin/update.sh"),
+    ("CTC024", "plain-http", "download over plain HTTP",
+     "The job downloads over http://, which anyone on the network can read or change.",
+     "0 3 * * * root wget -q http://example.com/list", "0 3 * * * root wget -q https://example.com/list"),
+    ("CTC025", "redirection-order", "redirection order",
+     "'2>&1 > file' sends errors to where the output went before (the mail of cron), not to the file: redirections \
+      apply from left to right.", "0 2 * * * root /usr/bin/backup 2>&1 >> /var/log/backup.log",
+     "0 2 * * * root /usr/bin/backup >> /var/log/backup.log 2>&1"),
+    ("CTC026", "line-continuation", "line continuation",
+     "The line ends with a backslash: cron does not join lines, the next line is a separate entry.", "", ""),
+    ("CTC027", "hidden-characters", "hidden characters",
+     "The command contains invisible characters, bidirectional controls or letters looking like Latin ones (e.g. \
+      Cyrillic): what runs is not what reviewers read.", "", ""),
+    ("CTC028", "secrets", "credentials",
+     "A password, token or key is written in the crontab, which is often readable by others and kept in backups and \
+      version control. Read it from a protected file instead.", "0 2 * * * root mysqldump --password=hunter2 app",
+     "0 2 * * * root mysqldump --defaults-extra-file=/root/.my.cnf app"),
+    ("CTC029", "unset-variable", "unset variable",
+     "The command uses a variable cron does not set (cron only sets HOME, LOGNAME, PATH, SHELL and the variables of \
+      the crontab): it expands to nothing.", "", ""),
+    ("CTC030", "env-not-allowed", "variable not allowed",
+     "The variable is not one of those allowed by --allowed-env-vars or the configuration.", "", ""),
+    ("CTC031", "env-value", "invalid variable value",
+     "The value of the variable contains control characters, or is longer than cron accepts.", "", ""),
+    ("CTC032", "env-expansion", "variable not expanded",
+     "cron does not expand variables in assignments: the value is set literally, with the $.",
+     "PATH=$PATH:/opt/bin", "PATH=/usr/bin:/bin:/opt/bin"),
+    ("CTC033", "path", "PATH entry",
+     "An entry of PATH is relative, empty (the current directory), or does not exist: commands may be looked up in \
+      unexpected places.", "PATH=/usr/bin:/bin:", "PATH=/usr/bin:/bin"),
+    ("CTC034", "shell", "invalid SHELL",
+     "SHELL is not an absolute path to an existing shell (or not in /etc/shells with --check-etc-shells).", "", ""),
+    ("CTC035", "timezone", "unknown time zone",
+     "The time zone of TZ or CRON_TZ is not in the time zone database: cron uses UTC (or the time of the machine).",
+     "CRON_TZ=Europe/Pariss", "CRON_TZ=Europe/Paris"),
+    ("CTC036", "random-delay", "invalid RANDOM_DELAY",
+     "RANDOM_DELAY (cronie) must be a number of minutes.", "", ""),
+    ("CTC037", "start-hours-range", "invalid START_HOURS_RANGE",
+     "START_HOURS_RANGE (cronie) must be a range of hours between 0 and 24.", "", ""),
+    ("CTC038", "mailto", "invalid MAILTO",
+     "MAILTO contains an empty or invalid recipient: the output of the jobs may not be delivered.", "", ""),
+    ("CTC039", "env-reassigned", "variable set again",
+     "The variable was already set above: the jobs in between use the first value, those below the new one.", "", ""),
+    ("CTC040", "env-order", "variable set after jobs",
+     "The variable is set after some jobs: it only applies to the jobs below it.", "", ""),
+    ("CTC041", "file-permissions", "file permissions",
+     "The crontab file is not owned by root or can be written by others: cron ignores such system crontabs.", "", ""),
+    ("CTC042", "unreadable", "unreadable file",
+     "The crontab file could not be read.", "", ""),
+    ("CTC043", "path-current-directory", "PATH searching the current directory",
+     "PATH has an empty entry or '.': commands are looked up in the directory jobs run from.", "", ""),
+    ("CTC044", "oncalendar", "invalid OnCalendar",
+     "The OnCalendar= setting of a systemd timer is not a valid calendar event.", "", ""),
+    ("CTC045", "jenkins-schedule", "invalid Jenkins schedule",
+     "A cron trigger of a Jenkinsfile is not a valid schedule.", "", ""),
+    ("CTC046", "cronjob-schedule", "invalid CronJob schedule",
+     "The schedule or time zone of a Kubernetes CronJob is not valid.", "", ""),
+    ("CTC047", "trailing-comment", "comment at the end of a job",
+     "cron has no comments at the end of job lines: '# ...' is part of the command, which the shell gets whole (and \
+      reports as its own in logs and mails). Set trailing-comment = \"error\" in [rules] to reject such lines.",
+     "0 2 * * * root /usr/bin/backup  # nightly backup", "# nightly backup\n    0 2 * * * root /usr/bin/backup"),
+    ("CTC048", "indentation", "indented job",
+     "There is whitespace before the minute field of the job. cron skips it, but the sites which keep their crontabs \
+      flush left (for grep, or other tools reading them) can report it with indentation = \"error\" in [rules].",
+     "  0 2 * * * root /usr/bin/backup", "0 2 * * * root /usr/bin/backup"),
+];
+
+pub fn rule_code(rule: &str) -> Option<&'static str> {
+    RULE_CODES.iter().find(|rule_code| rule_code.1 == rule).map(|rule_code| rule_code.0)
+}
+
+// The explanation of a code (or of a rule name), for `crontabcheck explain-code`
+pub fn explain_code(code: &str) -> Option<String> {
+    let &(code, rule, title, description, example, fixed_example) = RULE_CODES.iter()
+        .find(|rule_code| rule_code.0.eq_ignore_ascii_case(code) || rule_code.1 == code)?;
+    let mut explanation = format!("{} ({}): {}\n\n{}\n", code, rule, title, description);
+    if !example.is_empty() {
+        explanation.push_str(&format!("\nFor example:\n    {}\n", example));
+    }
+    if !fixed_example.is_empty() {
+        explanation.push_str(&format!("Instead:\n    {}\n", fixed_example));
+    }
+    Some(explanation)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use codes::*;
+
+    #[test]
+    fn test_codes() {
+        for (index, rule_code) in RULE_CODES.iter().enumerate() {
+            assert_eq!(rule_code.0, format!("CTC{:03}", index + 1));
+            assert_eq!(RULE_CODES.iter().filter(|other| other.1 == rule_code.1).count(), 1);
+        }
+        assert_eq!(rule_code("pipe-to-shell"), Some("CTC023"));
+        assert_eq!(rule_code("nonexistent"), None);
+    }
+
+    #[test]
+    fn test_explain_code() {
+        let explanation = explain_code("ctc025").unwrap();
+        assert!(explanation.starts_with("CTC025 (redirection-order): redirection order\n\n'2>&1 > file'"));
+        assert!(explanation.ends_with("Instead:\n    0 2 * * * root /usr/bin/backup >> /var/log/backup.log 2>&1\n"));
+        assert_eq!(explain_code("syntax"), explain_code("CTC001"));
+        assert!(explain_code("CTC999").is_none());
+    }
+
+}
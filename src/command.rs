@@ -0,0 +1,322 @@
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use config::DeniedCommand;
+use files::in_root;
+
+
+// shell keywords and builtins: there is no file to look for
+const SHELL_BUILTINS: &[&str] = &[
+    "!", ".", ":", "[", "[[", "{", "(", "alias", "break", "case", "cd", "command", "continue", "echo",
+    "eval", "exec", "exit", "export", "false", "for", "if", "printf", "pwd", "read", "return", "set",
+    "shift", "source", "test", "times", "trap", "true", "type", "ulimit", "umask", "unset", "until",
+    "wait", "while",
+];
+
+// programs running the script given as their first argument (`bash /opt/backup.sh`)
+const INTERPRETERS: &[&str] = &[
+    "sh", "bash", "dash", "ksh", "zsh", "python", "python2", "python3", "perl", "ruby", "php", "node",
+];
+
+fn is_variable_assignment(word: &str) -> bool {
+    match word.find('=') {
+        Some(index) => index > 0 && word[..index].chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    }
+}
+
+// The program a command line runs, skipping leading `VAR=value` assignments.
+// Returns None when there is nothing to look up (shell builtin, empty command...)
+pub fn program_name(command: &str) -> Option<&str> {
+    command.split_whitespace()
+        .find(|word| !is_variable_assignment(word))
+        .and_then(|word| if SHELL_BUILTINS.contains(&word) { None } else { Some(word) })
+}
+
+pub fn check_executable(path: &Path) -> Result<(), String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a regular file", path.display()));
+    }
+    if metadata.permissions().mode() & 0o111 == 0 {
+        return Err(format!("{} is not executable", path.display()));
+    }
+    Ok(())
+}
+
+fn find_in_path(program: &str, search_path: &str, root: &Path) -> Option<PathBuf> {
+    search_path.split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| in_root(root, dir).join(program))
+        .find(|candidate| check_executable(candidate).is_ok())
+}
+
+// Make sure the program run by the command line exists and is executable.
+// Bare program names are looked up in `search_path` (a PATH-like, colon-separated list).
+// Paths are resolved in the filesystem under `root`.
+pub fn check_command(command: &str, search_path: &str, root: &Path) -> Result<(), String> {
+    let program = match program_name(command) {
+        Some(program) => program,
+        None => return Ok(()),
+    };
+    if program.starts_with('/') {
+        check_executable(&in_root(root, program))
+    } else if program.contains('/') {
+        // relative to the user's home directory, which we know nothing about
+        Ok(())
+    } else {
+        find_in_path(program, search_path, root)
+            .map(|_| ())
+            .ok_or(format!("command '{}' not found in PATH ({})", program, search_path))
+    }
+}
+
+// The program a command line runs, as written, and the script it interprets when given by its absolute path
+fn executed_words(command: &str) -> Vec<&str> {
+    let mut words = command.split_whitespace().skip_while(|word| is_variable_assignment(word));
+    let program = match words.next() {
+        Some(program) => program,
+        None => return vec![],
+    };
+    let mut executed = vec![program];
+    let name = program.rsplit('/').next().unwrap_or(program);
+    if INTERPRETERS.contains(&name) {
+        executed.extend(words.next().filter(|script| script.starts_with('/')));
+    }
+    executed
+}
+
+// The files a command line runs, under `root`: its program, and the script it interprets
+pub fn executed_files(command: &str, search_path: &str, root: &Path) -> Vec<PathBuf> {
+    executed_words(command).into_iter().filter_map(|word| {
+        if word.starts_with('/') {
+            Some(in_root(root, word))
+        } else if !word.contains('/') && !SHELL_BUILTINS.contains(&word) {
+            find_in_path(word, search_path, root)
+        } else {
+            None
+        }
+    }).collect()
+}
+
+// Files others than their owner may modify: whoever can write them runs code as the user of the job
+pub fn writable_file_warnings(files: &[PathBuf]) -> Vec<String> {
+    files.iter().filter_map(|path| {
+        let mode = fs::metadata(path).ok()?.permissions().mode();
+        let writers = if mode & 0o002 != 0 { "anyone" } else if mode & 0o020 != 0 { "its group" } else { return None };
+        Some(format!("{} is writable by {} (mode {:o}): whoever can modify it can run code as the user of the job",
+                     path.display(), writers, mode & 0o7777))
+    }).collect()
+}
+
+// Files run by a privileged job which belong to another user, or are in a directory which does: that user can replace
+// them and run any code as `user`. Directories are checked up to `root`, the filesystem the crontab belongs to.
+pub fn foreign_owner_errors(files: &[PathBuf], root: &Path, user: &str) -> Vec<String> {
+    files.iter().filter_map(|file| {
+        let (path, uid) = file.ancestors()
+            .take_while(|path| path.starts_with(root) && (path != &root || root == Path::new("/")))
+            .filter_map(|path| fs::metadata(path).ok().map(|metadata| (path, metadata.uid())))
+            .find(|&(_, uid)| uid != 0)?;
+        Some(if path == file.as_path() {
+            format!("{} is owned by uid {}, not root: that user can make this job run any code as {}", file.display(), uid, user)
+        } else {
+            format!("{} is in {}, owned by uid {}, not root: that user can replace it and run any code as {}",
+                    file.display(), path.display(), uid, user)
+        })
+    }).collect()
+}
+
+// Programs run with the privileges of their owner or group: cron running them needs a review
+pub fn setuid_warnings(files: &[PathBuf]) -> Vec<String> {
+    files.iter().filter_map(|path| {
+        let metadata = fs::metadata(path).ok()?;
+        let bits = match (metadata.mode() & 0o4000 != 0, metadata.mode() & 0o2000 != 0) {
+            (true, true) => "setuid and setgid",
+            (true, false) => "setuid",
+            (false, true) => "setgid",
+            (false, false) => return None,
+        };
+        Some(format!("{} is {} (owner uid {}, group gid {}): it runs with other privileges than the user of the job",
+                     path.display(), bits, metadata.uid(), metadata.gid()))
+    }).collect()
+}
+
+// What cron hands to the shell: the command line up to its first unescaped '%' (the rest is sent to the standard
+// input of the job), with the escaped ones turned back into plain percent signs, as Vixie cron's do_command does
+fn shell_command(command: &str) -> String {
+    let mut shell_command = String::new();
+    let mut escaped = false;
+    for c in command.chars() {
+        if escaped {
+            if c != '%' {
+                shell_command.push('\\');
+            }
+        } else if c == '%' {
+            break;
+        }
+        escaped = c == '\\';
+        if !escaped {
+            shell_command.push(c);
+        }
+    }
+    shell_command
+}
+
+// Have the shell parse (without running) the command line, as `$SHELL -n` does. SHELL comes from the crontab, which
+// may not be trusted (serve, daemon): only the shells of `trusted_shells` are run.
+pub fn check_shell_syntax<T: AsRef<str>>(command: &str, shell: &str, trusted_shells: &[T]) -> Result<(), String> {
+    if !trusted_shells.iter().any(|trusted| trusted.as_ref() == shell) {
+        return Err(format!("the syntax was not checked: SHELL {} is not listed in /etc/shells", shell));
+    }
+    let command = shell_command(command);
+    let output = Command::new(shell)
+        .arg("-n")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("could not run {}: {}", shell, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("shell syntax error: {}", stderr.trim()))
+    }
+}
+
+// Reject command lines matching one of the patterns denied by the configuration
+pub fn check_denied_commands(command: &str, denied_commands: &[DeniedCommand]) -> Result<(), String> {
+    match denied_commands.iter().find(|denied| denied.pattern.is_match(command)) {
+        Some(&DeniedCommand { reason: Some(ref reason), .. }) => Err(format!("command denied by policy: {}", reason)),
+        Some(denied) => Err(format!("command matches denied pattern '{}'", denied.pattern)),
+        None => Ok(()),
+    }
+}
+
+// directories anyone can write to, where another user could replace the files run by a job
+pub const TEMPORARY_DIRECTORIES: &[&str] = &["/tmp", "/var/tmp", "/dev/shm"];
+
+// Reject command lines running a program or script from a temporary directory, or from one of the directories
+// denied by the configuration
+pub fn check_denied_paths<T: AsRef<str>>(command: &str, denied_paths: &[T]) -> Result<(), String> {
+    for word in executed_words(command).into_iter().filter(|word| word.starts_with('/')) {
+        let path = Path::new(word);
+        if let Some(directory) = TEMPORARY_DIRECTORIES.iter().find(|directory| path.starts_with(directory)) {
+            return Err(format!("{} is in {}, where any user can replace it", word, directory));
+        }
+        if let Some(directory) = denied_paths.iter().find(|directory| path.starts_with(directory.as_ref())) {
+            return Err(format!("{} is in {}, denied by policy", word, directory.as_ref()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use command::*;
+
+    #[test]
+    fn test_program_name() {
+        assert_eq!(program_name("/usr/local/bin/yay --flag"), Some("/usr/local/bin/yay"));
+        assert_eq!(program_name("LANG=C FOO_1=bar ls -l"), Some("ls"));
+        assert_eq!(program_name("cd /tmp && ls"), None);
+        assert_eq!(program_name("   "), None);
+    }
+
+    #[test]
+    fn test_check_command() {
+        let root = Path::new("/");
+        assert!(check_command("/bin/sh -c true", "", root).is_ok());
+        assert!(check_command("sh -c true", "/nonexistent:/bin", root).is_ok());
+        assert!(check_command("/nonexistent/script.sh", "/bin", root).is_err());
+        assert!(check_command("nonexistent-command", "/bin:/usr/bin", root).is_err());
+        assert!(check_command("/etc/passwd", "/bin", root).is_err());
+        assert!(check_command("/bin/sh -c true", "", Path::new("/nonexistent")).is_err());
+    }
+
+    #[test]
+    fn test_executed_files() {
+        let root = Path::new("/");
+        assert_eq!(executed_files("LANG=C /usr/bin/backup --all", "", root), vec![PathBuf::from("/usr/bin/backup")]);
+        assert_eq!(executed_files("sh /opt/backup.sh", "/bin", root), vec![PathBuf::from("/bin/sh"), PathBuf::from("/opt/backup.sh")]);
+        assert_eq!(executed_files("sh -c 'backup'", "/nonexistent", root), Vec::<PathBuf>::new());
+        assert_eq!(executed_files("/usr/bin/python3 /opt/job.py", "", Path::new("/mnt/image")),
+                   vec![PathBuf::from("/mnt/image/usr/bin/python3"), PathBuf::from("/mnt/image/opt/job.py")]);
+    }
+
+    #[test]
+    fn test_check_denied_paths() {
+        let no_paths: &[&str] = &[];
+        assert_eq!(check_denied_paths("/tmp/backup.sh --full", no_paths), Err("/tmp/backup.sh is in /tmp, where any user can replace it".to_string()));
+        assert!(check_denied_paths("LANG=C bash /dev/shm/x", no_paths).is_err());
+        assert!(check_denied_paths("/usr/bin/backup --to /tmp/out", no_paths).is_ok());
+        assert!(check_denied_paths("/tmpfiles/backup", no_paths).is_ok());
+        assert_eq!(check_denied_paths("/home/alice/bin/job", &["/home"]), Err("/home/alice/bin/job is in /home, denied by policy".to_string()));
+        assert!(check_denied_paths("/opt/bin/job", &["/home/"]).is_ok());
+    }
+
+    #[test]
+    fn test_writable_file_warnings() {
+        let path = ::std::env::temp_dir().join(format!("crontabcheck-writable-{}", ::std::process::id()));
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o757)).unwrap();
+        let warnings = writable_file_warnings(&[path.clone(), PathBuf::from("/nonexistent")]);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(writable_file_warnings(::std::slice::from_ref(&path)).is_empty());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("writable by anyone (mode 757)"));
+    }
+
+    #[test]
+    fn test_setuid_warnings() {
+        let path = ::std::env::temp_dir().join(format!("crontabcheck-setuid-{}", ::std::process::id()));
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o2755)).unwrap();
+        let warnings = setuid_warnings(::std::slice::from_ref(&path));
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(setuid_warnings(::std::slice::from_ref(&path)).is_empty());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains(" is setgid (owner uid "));
+    }
+
+    #[test]
+    fn test_foreign_owner_errors() {
+        let directory = ::std::env::temp_dir().join(format!("crontabcheck-owner-{}", ::std::process::id()));
+        let path = directory.join("job.sh");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        // owned by a regular user: when the tests do not run as root, they own the directory already
+        let _ = ::std::os::unix::fs::chown(&directory, Some(1000), None);
+        let errors = foreign_owner_errors(&[path.clone(), PathBuf::from("/nonexistent")], Path::new("/"), "root");
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("not root: that user can"));
+        assert!(errors[0].ends_with("as root"));
+    }
+
+    #[test]
+    fn test_check_shell_syntax() {
+        let shells = ["/bin/sh", "/nonexistent/shell"];
+        assert!(check_shell_syntax("echo ok | grep -q ok && date +\\%s", "/bin/sh", &shells).is_ok());
+        assert!(check_shell_syntax("echo 'unterminated", "/bin/sh", &shells).is_err());
+        assert!(check_shell_syntax("if true; then echo", "/bin/sh", &shells).is_err());
+        assert!(check_shell_syntax("true", "/nonexistent/shell", &shells).is_err());
+        // the rest of the line is the standard input of the job
+        assert!(check_shell_syntax("mail -s report root%it's done%", "/bin/sh", &shells).is_ok());
+        assert_eq!(check_shell_syntax("true", "/usr/bin/perl", &shells),
+                   Err("the syntax was not checked: SHELL /usr/bin/perl is not listed in /etc/shells".to_string()));
+    }
+
+    #[test]
+    fn test_shell_command() {
+        assert_eq!(shell_command("date +\\%s > /tmp/x"), "date +%s > /tmp/x");
+        assert_eq!(shell_command("mail root%line 1%line 2"), "mail root");
+        assert_eq!(shell_command("echo a\\b"), "echo a\\b");
+    }
+
+}
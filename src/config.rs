@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use chrono::Duration;
+use regex::Regex;
+use serde::de::{self, Deserialize, Deserializer};
+use toml;
+
+use checker::Severity;
+use report::parse_duration;
+
+
+// Settings read from the TOML file given with --config
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // jobs whose command matches one of these patterns are rejected
+    pub denied_commands: Vec<DeniedCommand>,
+    // jobs running a program or script from these directories are rejected, as from /tmp, /var/tmp and /dev/shm
+    pub denied_paths: Vec<String>,
+    // when set, assigning any other environment variable is an error
+    pub allowed_env_vars: Option<Vec<String>>,
+    // maximum length of environment variable values (defaults to cron's own limit)
+    pub max_env_value_length: Option<usize>,
+    // maximum number of jobs in a single file
+    pub max_jobs: Option<usize>,
+    // rules of the organization on jobs, in [[policies]] sections
+    pub policies: Vec<Policy>,
+    pub rules: Rules,
+}
+
+// How a rule's findings are reported
+#[derive(Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Warning,
+    Error,
+}
+
+impl RuleLevel {
+    pub fn severity(&self) -> Option<Severity> {
+        match *self {
+            RuleLevel::Off => None,
+            RuleLevel::Warning => Some(Severity::Warning),
+            RuleLevel::Error => Some(Severity::Error),
+        }
+    }
+}
+
+// Level of the rules which can be tuned per environment, in the [rules] section
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Rules {
+    // downloaded code piped into a shell (`curl ... | sh`)
+    pub pipe_to_shell: RuleLevel,
+    // downloads over plain HTTP (`curl http://...`)
+    pub plain_http: RuleLevel,
+    // lines ending with "\r\n"
+    pub crlf: RuleLevel,
+    // files with more jobs than `max_jobs`
+    pub too_many_jobs: RuleLevel,
+    // style: spaces or tabs at the end of lines
+    pub trailing_whitespace: RuleLevel,
+    // style: job fields separated with tabs in some places and spaces in others
+    pub mixed_separators: RuleLevel,
+    // passwords, access keys and tokens written in commands and environment variables
+    pub secrets: RuleLevel,
+    // invisible, bidirectional control and lookalike characters in commands
+    pub hidden_characters: RuleLevel,
+    // "# ..." at the end of job lines, which cron passes to the shell
+    pub trailing_comment: RuleLevel,
+    // style: whitespace before the fields of jobs
+    pub indentation: RuleLevel,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            pipe_to_shell: RuleLevel::Warning,
+            plain_http: RuleLevel::Warning,
+            crlf: RuleLevel::Warning,
+            too_many_jobs: RuleLevel::Error,
+            trailing_whitespace: RuleLevel::Off,
+            mixed_separators: RuleLevel::Off,
+            secrets: RuleLevel::Warning,
+            hidden_characters: RuleLevel::Error,
+            trailing_comment: RuleLevel::Warning,
+            indentation: RuleLevel::Off,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeniedCommand {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    pub reason: Option<String>,
+}
+
+// A policy applies to the jobs matching all its conditions, which are reported with its message, e.g. "non-root users
+// may not schedule jobs more frequent than hourly":
+//   except_users = ["root"]
+//   more_frequent_than = "1h"
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    pub message: String,
+    #[serde(default = "default_policy_level")]
+    pub level: RuleLevel,
+    // jobs of these users
+    pub users: Option<Vec<String>>,
+    // jobs of other users than these
+    #[serde(default)]
+    pub except_users: Vec<String>,
+    // jobs whose command matches this pattern
+    #[serde(default, deserialize_with = "deserialize_optional_regex")]
+    pub command: Option<Regex>,
+    // jobs running more often than this ("30m", "1h", "1d"...)
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub more_frequent_than: Option<Duration>,
+    // jobs after which the crontab sets these environment variables, or does not
+    #[serde(default)]
+    pub env_set: Vec<String>,
+    #[serde(default)]
+    pub env_unset: Vec<String>,
+}
+
+fn default_policy_level() -> RuleLevel {
+    RuleLevel::Error
+}
+
+fn deserialize_optional_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    deserialize_regex(deserializer).map(Some)
+}
+
+fn deserialize_optional_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let duration = String::deserialize(deserializer)?;
+    parse_duration(&duration).map(Some).map_err(de::Error::custom)
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(de::Error::custom)
+}
+
+pub fn parse_config(contents: &str) -> Result<Config, String> {
+    toml::from_str(contents).map_err(|e| e.to_string())
+}
+
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config, String> {
+    let path = path.as_ref();
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|e| format!("could not read configuration file {}: {}", path.display(), e))?;
+    parse_config(&contents).map_err(|e| format!("invalid configuration file {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use config::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config("").unwrap();
+        assert!(config.denied_commands.is_empty());
+        assert!(config.denied_paths.is_empty());
+        assert_eq!(config.rules.pipe_to_shell, RuleLevel::Warning);
+        assert_eq!(config.allowed_env_vars, None);
+        assert_eq!(config.max_env_value_length, None);
+        assert_eq!(config.max_jobs, None);
+
+        let config = parse_config(r#"
+            [[denied_commands]]
+            pattern = 'rm\s+-rf\s+/(\s|$)'
+            reason = "recursive removal of /"
+
+            [[denied_commands]]
+            pattern = '\bmkfs'
+        "#).unwrap();
+        assert_eq!(config.denied_commands.len(), 2);
+        assert!(config.denied_commands[0].pattern.is_match("rm -rf /"));
+        assert_eq!(config.denied_commands[1].reason, None);
+
+        let config = parse_config("denied_paths = ['/home']").unwrap();
+        assert_eq!(config.denied_paths, vec!["/home".to_string()]);
+
+        let config = parse_config("allowed_env_vars = ['MAILTO', 'PATH']\n[rules]\npipe-to-shell = 'error'\ncrlf = 'off'").unwrap();
+        assert_eq!(config.rules.crlf, RuleLevel::Off);
+        assert_eq!(config.allowed_env_vars, Some(vec!["MAILTO".to_string(), "PATH".to_string()]));
+        assert_eq!(config.rules.pipe_to_shell, RuleLevel::Error);
+    }
+
+    #[test]
+    fn test_parse_invalid_config() {
+        assert!(parse_config("[[denied_commands]]\npattern = '('").is_err());
+        assert!(parse_config("unknown_setting = 1").is_err());
+        assert!(parse_config("[rules]\npipe-to-shell = 'fatal'").is_err());
+        assert!(parse_config("[[policies]]\nmessage = 'x'\nmore_frequent_than = 'hourly'").is_err());
+        assert!(parse_config("[[policies]]\ncommand = 'x'").is_err());
+    }
+
+}
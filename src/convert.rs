@@ -0,0 +1,387 @@
+// Conversions of crontabs to other schedulers
+
+use parser::{CrontabLine, CrontabParserOptions, parse_crontab};
+use schedule::{Field, FieldValue, Schedule, parse_schedule};
+use systemd::{CalendarComponent, CalendarEvent, CalendarValue, parse_calendar, unit_settings};
+
+
+const SYSTEMD_WEEKDAYS: &[&str] = &["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+// what cron uses when the crontab does not set them
+const CRON_SHELL: &str = "/bin/sh";
+const CRON_PATH: &str = "/usr/bin:/bin";
+// timer settings which schedule the service other than by the calendar
+const MONOTONIC_TIMERS: &[&str] = &["OnActiveSec", "OnBootSec", "OnStartupSec", "OnUnitActiveSec", "OnUnitInactiveSec"];
+
+pub struct Unit {
+    // file name, e.g. "backup-3.timer"
+    pub name: String,
+    pub contents: String,
+}
+
+// [1, 2, 3, 5] -> "1..3,5"
+fn format_runs(values: &[u32], format: &dyn Fn(u32) -> String) -> String {
+    let mut runs: Vec<(u32, u32)> = vec![];
+    for &value in values {
+        if runs.last().map(|&(_, end)| end + 1 == value).unwrap_or(false) {
+            runs.last_mut().unwrap().1 = value;
+        } else {
+            runs.push((value, value));
+        }
+    }
+    runs.into_iter()
+        .map(|(start, end)| match end - start {
+            0 => format(start),
+            1 => format!("{},{}", format(start), format(end)),
+            _ => format!("{}..{}", format(start), format(end)),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// A field as a component of a calendar event ("*", "0/15", "1..5,10")
+fn calendar_component(field: &Field) -> String {
+    match field.values[..] {
+        [FieldValue::All { step: 1 }] => "*".to_string(),
+        [FieldValue::All { step }] => format!("{:02}/{}", field.min, step),
+        _ => format_runs(&field.expand(), &|value| format!("{:02}", value)),
+    }
+}
+
+// "Mon..Fri", "Sat,Sun"
+fn calendar_weekdays(field: &Field) -> String {
+    // cron counts from Sunday (0 or 7), systemd from Monday
+    let mut days: Vec<u32> = field.expand().into_iter().map(|day| if day == 0 { 7 } else { day }).collect();
+    days.sort();
+    days.dedup();
+    format_runs(&days, &|day| SYSTEMD_WEEKDAYS[day as usize - 1].to_string())
+}
+
+// The OnCalendar= expressions of a schedule (several when the job runs on some days of the month *or* of the week)
+pub fn calendar_events(schedule: &Schedule, timezone: Option<&str>) -> Result<Vec<String>, String> {
+    let time = format!("{}:{}:00", calendar_component(&schedule.hour), calendar_component(&schedule.minute));
+    let month = calendar_component(&schedule.month);
+    let suffix = timezone.map(|timezone| format!(" {}", timezone)).unwrap_or_default();
+    // "~" counts the days from the end of the month: "~01" is the last one, "~07/1" the last seven
+    let by_month_day = match schedule.day_of_month.values[..] {
+        [FieldValue::LastDayOfMonth] => format!("*-{}~01 {}{}", month, time, suffix),
+        _ => format!("*-{}-{} {}{}", month, calendar_component(&schedule.day_of_month), time, suffix),
+    };
+    let by_weekday = match schedule.day_of_week.values[..] {
+        [FieldValue::LastDayOfWeek { day }] => {
+            format!("{} *-{}~07/1 {}{}", SYSTEMD_WEEKDAYS[((day + 6) % 7) as usize], month, time, suffix)
+        },
+        // the second Monday is the Monday between the 8th and the 14th
+        [FieldValue::NthDayOfWeek { day, n }] => {
+            let first = (n - 1) * 7 + 1;
+            format!("{} *-{}-{:02}..{:02} {}{}", SYSTEMD_WEEKDAYS[((day + 6) % 7) as usize], month, first, (first + 6).min(31), time, suffix)
+        },
+        _ => format!("{} *-{}-* {}{}", calendar_weekdays(&schedule.day_of_week), month, time, suffix),
+    };
+    if schedule.day_of_week.is_any() {
+        return Ok(vec![by_month_day]);
+    }
+    if schedule.day_of_month.is_any() {
+        return Ok(vec![by_weekday]);
+    }
+    if !schedule.day_of_month.starts_with_star() && !schedule.day_of_week.starts_with_star() {
+        return Ok(vec![by_weekday, by_month_day]);
+    }
+    // cron runs the job on the days matching both fields, as systemd does with a weekday and a date
+    if !schedule.month_relative_days().is_empty() {
+        return Err(format!("the days of \"{}\" cannot be converted to a calendar event", schedule));
+    }
+    Ok(vec![format!("{} *-{}-{} {}{}", calendar_weekdays(&schedule.day_of_week), month, calendar_component(&schedule.day_of_month), time, suffix)])
+}
+
+// A value in double quotes, for unit files (where % starts a specifier and $ a variable)
+fn quote_unit_value(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn unit_prefix(crontab_name: &str) -> String {
+    crontab_name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' }).collect()
+}
+
+// A .timer and a .service unit for each job of a (valid) crontab
+pub fn to_systemd_units<T: AsRef<str>>(contents: &str, crontab_name: &str, parser_options: &CrontabParserOptions<T>) -> Result<Vec<Unit>, String> {
+    let mut units = vec![];
+    let mut variables: Vec<(String, String)> = vec![("PATH".to_string(), CRON_PATH.to_string())];
+    let mut shell = CRON_SHELL.to_string();
+    let mut timezone = None;
+    let prefix = unit_prefix(crontab_name);
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        match parse_crontab(line, parser_options) {
+            Ok(CrontabLine::EnvironmentVariable { name, value }) => match name {
+                "SHELL" => shell = value.to_string(),
+                "CRON_TZ" => timezone = Some(value.to_string()),
+                _ => {
+                    variables.retain(|(variable, _)| variable != name);
+                    variables.push((name.to_string(), value.to_string()));
+                },
+            },
+            Ok(CrontabLine::Job { schedule, user, command }) => {
+                let schedule = parse_schedule(schedule).map_err(|e| format!("line {}: {}", line_number, e))?;
+                let description = format!("cron job from {}, line {}", crontab_name, line_number);
+                let name = format!("{}-{}", prefix, line_number);
+                let mut timer = format!("[Unit]\nDescription=Timer for {}\n\n[Timer]\n", description);
+                let events = calendar_events(&schedule, timezone.as_ref().map(|timezone| &timezone[..]))
+                    .map_err(|e| format!("line {}: {}", line_number, e))?;
+                for event in events {
+                    timer.push_str(&format!("OnCalendar={}\n", event));
+                }
+                timer.push_str("\n[Install]\nWantedBy=timers.target\n");
+                let mut service = format!("[Unit]\nDescription={}\n\n[Service]\nType=oneshot\nUser={}\n", description, user);
+                for (variable, value) in &variables {
+                    service.push_str(&format!("Environment={}\n", quote_unit_value(&format!("{}={}", variable, value))));
+                }
+                // cron turns escaped percent signs into plain ones
+                let command = command.replace("\\%", "%");
+                service.push_str(&format!("ExecStart={} -c {}\n", shell, quote_unit_value(&command)));
+                units.push(Unit { name: format!("{}.timer", name), contents: timer });
+                units.push(Unit { name: format!("{}.service", name), contents: service });
+            },
+            Ok(_) => (),
+            Err(_) => return Err(format!("line {}: invalid line", line_number)),
+        }
+    }
+    Ok(units)
+}
+
+// A calendar component as a cron field ("*", "*/15", "1-5,10")
+fn cron_field(component: &CalendarComponent, min: u32, max: u32) -> String {
+    match *component {
+        None => "*".to_string(),
+        Some(ref values) => values.iter().map(|value| match (value.end, value.repeat) {
+            (None, None) => value.start.to_string(),
+            (Some(end), None) => format!("{}-{}", value.start, end),
+            (end, Some(repeat)) => match end.unwrap_or(max) {
+                end if value.start == min && end == max => format!("*/{}", repeat),
+                end => format!("{}-{}/{}", value.start, end, repeat),
+            },
+        }).collect::<Vec<String>>().join(","),
+    }
+}
+
+// systemd weekday ranges (1 is Monday, 7 Sunday) as a cron field ("1-5", "0,6")
+fn cron_weekdays(weekdays: &[(u32, u32)]) -> String {
+    weekdays.iter().map(|&(start, end)| match (start, end) {
+        (7, 7) => "0".to_string(),
+        (start, end) if start == end => start.to_string(),
+        (start, end) if start < end => format!("{}-{}", start, end),
+        // "Sat..Mon"
+        (start, end) => format!("{}-7,1-{}", start, end),
+    }).collect::<Vec<String>>().join(",")
+}
+
+// The five time and date fields of a crontab line, or why cron cannot run jobs at these times
+pub fn cron_schedule(event: &CalendarEvent) -> Result<String, Vec<String>> {
+    let mut problems = vec![];
+    let on_the_minute = Some(vec![CalendarValue { start: 0, end: None, repeat: None }]);
+    if event.seconds != on_the_minute {
+        problems.push("cron only runs jobs at the start of a minute (seconds must be 00)".to_string());
+    }
+    if event.years.is_some() {
+        problems.push("cron has no year field".to_string());
+    }
+    if event.last_days {
+        problems.push("cron cannot count days from the end of the month (~)".to_string());
+    }
+    if event.weekdays.is_some() && event.days.is_some() {
+        problems.push("cron runs the job when either the day of the month or the day of the week matches, not only when both do".to_string());
+    }
+    if let Some(ref timezone) = event.timezone {
+        problems.push(format!("cron runs jobs in the system time zone, not {} (set CRON_TZ if your cron supports it)", timezone));
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    let weekdays = event.weekdays.as_ref().map(|weekdays| cron_weekdays(weekdays)).unwrap_or_else(|| "*".to_string());
+    Ok(format!("{} {} {} {} {}",
+               cron_field(&event.minutes, 0, 59),
+               cron_field(&event.hours, 0, 23),
+               cron_field(&event.days, 1, 31),
+               cron_field(&event.months, 1, 12),
+               weekdays))
+}
+
+// A command line of a unit file as a cron command (cron passes it to a shell, where % ends the command)
+fn cron_command(command: &str) -> Result<String, String> {
+    // "-" (ignore failures), "+", "!" and ":" (privileges, expansion) prefixes do not change what is run
+    let command = command.trim_start_matches(['-', '+', '!', ':']);
+    if command.starts_with('@') {
+        return Err("the @ prefix (argv[0] different from the executable) has no cron equivalent".to_string());
+    }
+    let mut converted = String::new();
+    let mut characters = command.chars();
+    while let Some(c) = characters.next() {
+        match c {
+            '%' => match characters.next() {
+                Some('%') => converted.push_str("\\%"),
+                Some(specifier) => return Err(format!("specifier %{} has no cron equivalent", specifier)),
+                None => return Err("incomplete specifier at the end of the command".to_string()),
+            },
+            c => converted.push(c),
+        }
+    }
+    Ok(converted)
+}
+
+// "A=1 \"B=2 3\"" -> ["A=1", "B=2 3"]
+fn environment_assignments(value: &str) -> Vec<String> {
+    let mut assignments = vec![];
+    let mut current = String::new();
+    let mut quote = None;
+    for c in value.chars() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, c) if c.is_whitespace() => if !current.is_empty() {
+                assignments.push(current.clone());
+                current.clear();
+            },
+            (_, c) => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        assignments.push(current);
+    }
+    assignments
+}
+
+// "NAME=value" as a shell assignment
+fn shell_assignment(assignment: &str) -> String {
+    let mut parts = assignment.splitn(2, '=');
+    let name = parts.next().unwrap_or("");
+    let value = parts.next().unwrap_or("");
+    format!("{}='{}'", name, value.replace('\'', "'\\''").replace('%', "\\%"))
+}
+
+// The lines of /etc/cron.d/ crontab running the service of a timer, where systemd's schedule can be expressed in cron,
+// and the problems preventing the conversion of the rest
+pub fn to_crontab_lines(timer_name: &str, timer: &str, service: &str) -> (Vec<String>, Vec<String>) {
+    let mut lines = vec![];
+    let mut problems = vec![];
+    let mut schedules = vec![];
+    for setting in unit_settings(timer).into_iter().filter(|setting| setting.section == "[Timer]") {
+        let problem = match setting.key {
+            // an empty value resets the list of events
+            "OnCalendar" if setting.value.is_empty() => { schedules.clear(); None },
+            "OnCalendar" => match parse_calendar(setting.value).map_err(|e| vec![e]).and_then(|event| cron_schedule(&event)) {
+                Ok(schedule) => { schedules.push(schedule); None },
+                Err(reasons) => Some(format!("OnCalendar={}: {}", setting.value, reasons.join(", "))),
+            },
+            key if MONOTONIC_TIMERS.contains(&key) && !setting.value.is_empty() => Some(format!("{}= has no cron equivalent", key)),
+            "Persistent" if ["true", "yes", "1", "on"].contains(&setting.value) => {
+                Some("Persistent= has no cron equivalent: runs missed while the system was down are lost".to_string())
+            },
+            "RandomizedDelaySec" if setting.value != "0" => Some("RandomizedDelaySec= has no cron equivalent".to_string()),
+            _ => None,
+        };
+        problems.extend(problem.map(|problem| format!("{} line {}: {}", timer_name, setting.line_number, problem)));
+    }
+    let mut user = "root".to_string();
+    let mut commands = vec![];
+    let mut environment = vec![];
+    let mut directory = None;
+    for setting in unit_settings(service).into_iter().filter(|setting| setting.section == "[Service]") {
+        match setting.key {
+            "User" => user = setting.value.to_string(),
+            "WorkingDirectory" => directory = Some(setting.value.trim_start_matches('-').to_string()),
+            "Environment" if setting.value.is_empty() => environment.clear(),
+            "Environment" => environment.extend(environment_assignments(setting.value)),
+            "ExecStart" if setting.value.is_empty() => commands.clear(),
+            "ExecStart" => match cron_command(setting.value) {
+                Ok(command) => commands.push(command),
+                Err(e) => problems.push(format!("service line {}: {}", setting.line_number, e)),
+            },
+            _ => (),
+        }
+    }
+    if commands.is_empty() {
+        problems.push("the service has no ExecStart= command".to_string());
+        return (lines, problems);
+    }
+    // like systemd, stop at the first failing command
+    let mut command = commands.join(" && ");
+    if !environment.is_empty() {
+        let assignments: Vec<String> = environment.iter().map(|assignment| shell_assignment(assignment)).collect();
+        command = format!("{} {}", assignments.join(" "), command);
+    }
+    if let Some(directory) = directory {
+        command = format!("cd {} && {}", directory, command);
+    }
+    for schedule in schedules {
+        lines.push(format!("{} {} {}", schedule, user, command));
+    }
+    (lines, problems)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use convert::*;
+    use schedule::parse_schedule;
+    use systemd::parse_calendar;
+
+    #[test]
+    fn test_calendar_events() {
+        let events = |schedule| calendar_events(&parse_schedule(schedule).unwrap(), None).unwrap();
+        assert_eq!(events("*/15 2 * * *"), vec!["*-*-* 02:00/15:00"]);
+        assert_eq!(events("30 1-4,6 * * 1-5"), vec!["Mon..Fri *-*-* 01..04,06:30:00"]);
+        assert_eq!(events("0 0 1 jan *"), vec!["*-01-01 00:00:00"]);
+        assert_eq!(events("0 12 1 * 0,6"), vec!["Sat,Sun *-*-* 12:00:00", "*-*-01 12:00:00"]);
+        assert_eq!(events("0 23 L * *"), vec!["*-*~01 23:00:00"]);
+        assert_eq!(events("0 9 * 5 1L"), vec!["Mon *-05~07/1 09:00:00"]);
+        assert_eq!(events("0 9 * * mon#2"), vec!["Mon *-*-08..14 09:00:00"]);
+        assert_eq!(events("0 2 */2 * 1"), vec!["Mon *-*-01/2 02:00:00"]);
+        assert_eq!(events("0 2 1-7 * *,sat"), vec!["Mon..Sun *-*-01..07 02:00:00"]);
+        assert!(calendar_events(&parse_schedule("0 2 */2 * 5L").unwrap(), None).is_err());
+        assert_eq!(calendar_events(&parse_schedule("0 3 * * *").unwrap(), Some("Europe/Paris")).unwrap(), vec!["*-*-* 03:00:00 Europe/Paris"]);
+    }
+
+    #[test]
+    fn test_to_systemd_units() {
+        let crontab = "SHELL=/bin/bash\n0 5 * * 1-5 root /usr/bin/backup --full\n";
+        let units = to_systemd_units(crontab, "backup.cron", &CrontabParserOptions::<&str>::default()).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].name, "backup-cron-2.timer");
+        assert!(units[0].contents.contains("OnCalendar=Mon..Fri *-*-* 05:00:00\n"));
+        assert!(units[1].contents.contains("User=root\n"));
+        assert!(units[1].contents.contains("ExecStart=/bin/bash -c \"/usr/bin/backup --full\"\n"));
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let schedule = |expression| cron_schedule(&parse_calendar(expression).unwrap());
+        assert_eq!(schedule("Mon..Fri *-*-* 02:30"), Ok("30 2 * * 1-5".to_string()));
+        assert_eq!(schedule("*:0/15"), Ok("*/15 * * * *".to_string()));
+        assert_eq!(schedule("*-*-01 06:10/20"), Ok("10-59/20 6 1 * *".to_string()));
+        assert_eq!(schedule("Sat,Sun 12:00"), Ok("0 12 * * 6,0".to_string()));
+        assert_eq!(schedule("quarterly"), Ok("0 0 1 1,4,7,10 *".to_string()));
+        assert!(schedule("*:*:30").is_err());
+        assert!(schedule("2030-01-01").is_err());
+        assert_eq!(schedule("Mon *-*-01 Europe/Paris").unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn test_to_crontab_lines() {
+        let timer = "[Timer]\nOnCalendar=daily\nOnCalendar=*-*-* 12:00:30\nPersistent=true\n";
+        let service = "[Service]\nUser=backup\nEnvironment=\"TARGET=/srv 1\"\nExecStart=-/usr/bin/backup --at 100%%\n";
+        let (lines, problems) = to_crontab_lines("backup.timer", timer, service);
+        assert_eq!(lines, vec!["0 0 * * * backup TARGET='/srv 1' /usr/bin/backup --at 100\\%"]);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("backup.timer line 3: OnCalendar=*-*-* 12:00:30"));
+
+        let (lines, problems) = to_crontab_lines("home.timer", "[Timer]\nOnCalendar=hourly\n", "[Service]\nExecStart=/bin/ls %h\n");
+        assert!(lines.is_empty());
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn test_quote_unit_value() {
+        assert_eq!(quote_unit_value("echo \"$HOME\" 100%"), "\"echo \\\"$$HOME\\\" 100%%\"");
+    }
+
+}
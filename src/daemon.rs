@@ -0,0 +1,184 @@
+// A long-running checker listening on a Unix socket, for hosts checking crontabs often: the options (usernames read
+// from /etc/passwd, configuration...) are loaded once, rather than by a new process for each check.
+//
+// Clients send a JSON request per line, {"contents": "...", "path": "/var/spool/cron/crontabs/alice"} ("path" is
+// optional, it tells user crontabs apart), and get a JSON response per line:
+// {"valid": false, "diagnostics": [{"line": 3, "severity": "error", "message": "...", "text": "..."}]}
+// The metrics of the checks can be written to a file after each one, for the textfile collector of the node exporter.
+
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use serde_json;
+
+use checker::{CheckerOptions, Diagnostic, Severity, check_crontab};
+use codes::rule_code;
+use files::{user_crontab_owner, write_atomically};
+use metrics::Metrics;
+use parser::CrontabParserOptions;
+
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckRequest {
+    contents: String,
+    path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DiagnosticResponse<'a> {
+    // 0 for diagnostics about the whole file
+    line: usize,
+    severity: &'static str,
+    rule: &'static str,
+    code: Option<&'static str>,
+    message: &'a str,
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct CheckResponse<'a> {
+    valid: bool,
+    diagnostics: Vec<DiagnosticResponse<'a>>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "info",
+    }
+}
+
+pub fn error_response(error: &str) -> String {
+    serde_json::to_string(&ErrorResponse { error: error.to_string() }).unwrap_or_default()
+}
+
+// The JSON response to a crontab, and its diagnostics (`path` tells user crontabs apart)
+pub fn check_response<T: AsRef<str>>(contents: &str, path: Option<&str>, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> (String, Vec<Diagnostic>) {
+    let owner = path.and_then(|path| user_crontab_owner(Path::new(path), checker_options.sysroot));
+    let parser_options = CrontabParserOptions { crontab_owner: owner.as_ref().map(|owner| &owner[..]), ..*parser_options };
+    let diagnostics = check_crontab(contents, true, &parser_options, checker_options);
+    let response = serde_json::to_string(&CheckResponse {
+        valid: !diagnostics.iter().any(|diagnostic| matches!(diagnostic.severity, Severity::Error)),
+        diagnostics: diagnostics.iter().map(|diagnostic| DiagnosticResponse {
+            line: diagnostic.line_number,
+            severity: severity_name(diagnostic.severity),
+            rule: diagnostic.rule,
+            code: rule_code(diagnostic.rule),
+            message: &diagnostic.message,
+            text: &diagnostic.line,
+        }).collect(),
+    }).unwrap_or_default();
+    (response, diagnostics)
+}
+
+// The response line to a request line
+fn check_request<T: AsRef<str>>(request: &str, metrics: &mut Metrics, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> String {
+    match serde_json::from_str::<CheckRequest>(request) {
+        Ok(request) => {
+            let (response, diagnostics) = check_response(&request.contents, request.path.as_ref().map(|path| &path[..]), parser_options, checker_options);
+            metrics.record(&diagnostics);
+            response
+        },
+        Err(e) => error_response(&format!("invalid request: {}", e)),
+    }
+}
+
+// Answer the requests of a client until it closes the connection
+fn serve_client<T: AsRef<str>>(stream: UnixStream, metrics: &mut Metrics, metrics_file: Option<&Path>, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for request in BufReader::new(stream).lines() {
+        let request = request?;
+        if request.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", check_request(&request, metrics, parser_options, checker_options))?;
+        if let Some(path) = metrics_file {
+            if let Err(e) = write_atomically(path, metrics.prometheus_text(true).as_bytes()) {
+                eprintln!("could not write {}: {}", path.display(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Listen on a Unix socket, answering one client at a time. Only returns if the socket cannot be set up.
+pub fn run_daemon<T: AsRef<str>>(socket_path: &Path, metrics_file: Option<&Path>, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> io::Result<()> {
+    // left by a previous daemon which did not stop cleanly (anything else is not ours to remove)
+    if let Ok(metadata) = fs::symlink_metadata(socket_path) {
+        if metadata.file_type().is_socket() {
+            fs::remove_file(socket_path)?;
+        }
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    let mut metrics = Metrics::default();
+    for stream in listener.incoming() {
+        // a client going away must not stop the daemon
+        if let Err(e) = stream.and_then(|stream| serve_client(stream, &mut metrics, metrics_file, parser_options, checker_options)) {
+            eprintln!("{}: {}", socket_path.display(), e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Read;
+    use std::net::Shutdown;
+
+    use config::Config;
+    use daemon::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_check_request() {
+        let config = Config::default();
+        let mut metrics = Metrics::default();
+        let mut check = |request: &str| {
+            let response = check_request(request, &mut metrics, &CrontabParserOptions::<&str>::default(), &CheckerOptions::test_defaults(&config));
+            serde_json::from_str::<Value>(&response).unwrap()
+        };
+        let response = check(r#"{"contents": "MAILTO=ops@example.com\n0 2 * * * root /usr/bin/backup\n"}"#);
+        assert_eq!(response["valid"], true);
+
+        let response = check(r#"{"contents": "0 2 * * * /usr/bin/backup\n", "path": "/var/spool/cron/crontabs/alice"}"#);
+        assert_eq!(response["valid"], true);
+
+        let response = check(r#"{"contents": "0 25 * * * root /usr/bin/backup\n"}"#);
+        assert_eq!(response["valid"], false);
+        assert_eq!(response["diagnostics"][0]["line"], 1);
+        assert_eq!(response["diagnostics"][0]["severity"], "error");
+        assert_eq!(response["diagnostics"][0]["code"], "CTC001");
+        assert_eq!(response["diagnostics"][0]["text"], "0 25 * * * root /usr/bin/backup");
+
+        assert!(check(r#"{"content": ""}"#)["error"].as_str().unwrap().starts_with("invalid request"));
+        assert_eq!((metrics.files_checked, metrics.invalid_files), (3, 1));
+    }
+
+    #[test]
+    fn test_serve_client() {
+        let config = Config::default();
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.write_all(b"{\"contents\": \"0 2 * * * root /usr/bin/backup\\n\"}\n\n{\"contents\": \"x\\n\"}\n").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        serve_client(server, &mut Metrics::default(), None, &CrontabParserOptions::<&str>::default(), &CheckerOptions::test_defaults(&config)).unwrap();
+        let mut responses = String::new();
+        client.read_to_string(&mut responses).unwrap();
+        let responses: Vec<Value> = responses.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["valid"], true);
+        assert_eq!(responses[1]["valid"], false);
+    }
+
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+use std::str::FromStr;
+
+
+// The cron implementation a crontab is written for
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(Default)]
+pub enum Dialect {
+    // Vixie cron, as shipped by Debian and Ubuntu
+    #[default]
+    Vixie,
+    // cronie (Fedora, RHEL...), which also understands anacron's variables
+    Cronie,
+    // Jenkins build triggers: schedules only, where "H" stands for a value derived from the job name
+    Jenkins,
+    // OpenBSD cron, where "~" stands for a random value ("0~30")
+    OpenBsd,
+    // FreeBSD cron, which adds @every_minute, @every_second and @<seconds> to the @ shortcuts
+    FreeBsd,
+    // POSIX crontab, as minimal implementations (busybox crond...) read it: numbers, ranges and lists only
+    Posix,
+    // the Quartz-style extensions of the day fields some crons and scheduling libraries accept: '?'
+    Extended,
+}
+
+pub const DIALECT_NAMES: &[&str] = &["vixie", "cronie", "jenkins", "openbsd", "freebsd", "posix", "extended"];
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vixie" => Ok(Dialect::Vixie),
+            "cronie" => Ok(Dialect::Cronie),
+            "jenkins" => Ok(Dialect::Jenkins),
+            "openbsd" => Ok(Dialect::OpenBsd),
+            "freebsd" => Ok(Dialect::FreeBsd),
+            "posix" => Ok(Dialect::Posix),
+            "extended" => Ok(Dialect::Extended),
+            _ => Err(format!("unknown dialect '{}' (expected one of: {})", s, DIALECT_NAMES.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Dialect::Vixie => write!(f, "vixie"),
+            Dialect::Cronie => write!(f, "cronie"),
+            Dialect::Jenkins => write!(f, "jenkins"),
+            Dialect::OpenBsd => write!(f, "openbsd"),
+            Dialect::FreeBsd => write!(f, "freebsd"),
+            Dialect::Posix => write!(f, "posix"),
+            Dialect::Extended => write!(f, "extended"),
+        }
+    }
+}
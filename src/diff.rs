@@ -0,0 +1,186 @@
+// The diff subcommand: the changes between two crontabs at the level of their entries (jobs, environment variables),
+// with the schedule changes in English, for change reviews. Whitespace and comments are not changes.
+
+use schedule::{describe_schedule, nickname_schedule, parse_schedule};
+use parser::{CrontabLine, CrontabParserOptions, parse_crontab};
+
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    JobAdded { user: String, command: String, schedule: String, runs: String },
+    JobRemoved { user: String, command: String, schedule: String, runs: String },
+    ScheduleChanged { user: String, command: String, old_schedule: String, new_schedule: String, old_runs: String, new_runs: String },
+    VariableAdded { name: String, value: String },
+    VariableRemoved { name: String, value: String },
+    VariableChanged { name: String, old_value: String, new_value: String },
+    // lines which are not valid entries
+    LineAdded { line: String },
+    LineRemoved { line: String },
+}
+
+impl Change {
+    pub fn text(&self) -> String {
+        match *self {
+            Change::JobAdded { ref user, ref command, ref runs, .. } => format!("+ {} (as {}), runs {}", command, user, runs),
+            Change::JobRemoved { ref user, ref command, ref runs, .. } => format!("- {} (as {}), ran {}", command, user, runs),
+            Change::ScheduleChanged { ref user, ref command, ref old_runs, ref new_runs, .. } => {
+                format!("~ {} (as {}) now runs {} instead of {}", command, user, new_runs, old_runs)
+            },
+            Change::VariableAdded { ref name, ref value } => format!("+ {}={}", name, value),
+            Change::VariableRemoved { ref name, ref value } => format!("- {}={}", name, value),
+            Change::VariableChanged { ref name, ref old_value, ref new_value } => format!("~ {} is now {} instead of {}", name, new_value, old_value),
+            Change::LineAdded { ref line } => format!("+ {}", line),
+            Change::LineRemoved { ref line } => format!("- {}", line),
+        }
+    }
+}
+
+enum Entry {
+    Job { user: String, command: String, schedule: String },
+    Variable { name: String, value: String },
+    Line(String),
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn entries(contents: &str, options: &CrontabParserOptions<&str>) -> Vec<Entry> {
+    contents.lines().filter_map(|line| match parse_crontab(line.trim_end_matches('\r'), options) {
+        Ok(CrontabLine::Job { schedule, user, command }) => {
+            Some(Entry::Job { user: user.to_string(), command: normalize(command), schedule: normalize(schedule) })
+        },
+        Ok(CrontabLine::EnvironmentVariable { name, value }) => Some(Entry::Variable { name: name.to_string(), value: value.to_string() }),
+        Ok(_) => None,
+        Err(_) => Some(Entry::Line(normalize(line))),
+    }).collect()
+}
+
+// Whether two schedules run the job at the same times ("@daily" and "0 0 * * *" do)
+fn same_schedule(a: &str, b: &str) -> bool {
+    let bits = |schedule: &str| parse_schedule(nickname_schedule(schedule).unwrap_or(schedule)).ok()
+        .map(|schedule| (schedule.bits(), schedule.month_relative_days()));
+    a == b || (bits(a).is_some() && bits(a) == bits(b))
+}
+
+// The values in `a` and not in `b`, and those in `b` and not in `a`, in order (a value repeated twice in `a` and
+// once in `b` is in the first list once)
+fn unmatched<T: Clone, F: Fn(&T, &T) -> bool>(a: &[T], b: &[T], same: F) -> (Vec<T>, Vec<T>) {
+    let mut b_left: Vec<Option<&T>> = b.iter().map(Some).collect();
+    let mut a_left = vec![];
+    for value in a {
+        match b_left.iter().position(|other| other.map(|other| same(value, other)).unwrap_or(false)) {
+            Some(index) => b_left[index] = None,
+            None => a_left.push(value.clone()),
+        }
+    }
+    (a_left, b_left.into_iter().filter_map(|value| value.cloned()).collect())
+}
+
+// Jobs are identified by their user and command: a job which keeps them and changes its schedule is changed, a
+// job whose command changes is removed and added. `owner` is the user of user crontabs (which have no user field).
+pub fn diff_crontabs(old: &str, new: &str, owner: Option<&str>) -> Vec<Change> {
+    let options = CrontabParserOptions::<&str> { crontab_owner: owner, ..Default::default() };
+    let (old, new) = (entries(old, &options), entries(new, &options));
+    let mut changes = vec![];
+
+    // jobs, in the order of their first appearance
+    let mut jobs: Vec<(&str, &str)> = vec![];
+    for entry in old.iter().chain(new.iter()) {
+        if let Entry::Job { ref user, ref command, .. } = *entry {
+            if !jobs.contains(&(&user[..], &command[..])) {
+                jobs.push((user, command));
+            }
+        }
+    }
+    let schedules = |entries: &[Entry], user: &str, command: &str| -> Vec<String> {
+        entries.iter().filter_map(|entry| match *entry {
+            Entry::Job { user: ref job_user, command: ref job_command, ref schedule } if job_user == user && job_command == command => Some(schedule.clone()),
+            _ => None,
+        }).collect()
+    };
+    for (user, command) in jobs {
+        let (removed, added) = unmatched(&schedules(&old, user, command), &schedules(&new, user, command), |a, b| same_schedule(a, b));
+        let (user, command) = (user.to_string(), command.to_string());
+        for index in 0..removed.len().max(added.len()) {
+            changes.push(match (removed.get(index), added.get(index)) {
+                (Some(old_schedule), Some(new_schedule)) => Change::ScheduleChanged {
+                    user: user.clone(),
+                    command: command.clone(),
+                    old_runs: describe_schedule(old_schedule),
+                    new_runs: describe_schedule(new_schedule),
+                    old_schedule: old_schedule.clone(),
+                    new_schedule: new_schedule.clone(),
+                },
+                (Some(schedule), None) => Change::JobRemoved { user: user.clone(), command: command.clone(), runs: describe_schedule(schedule), schedule: schedule.clone() },
+                (None, Some(schedule)) => Change::JobAdded { user: user.clone(), command: command.clone(), runs: describe_schedule(schedule), schedule: schedule.clone() },
+                (None, None) => unreachable!(),
+            });
+        }
+    }
+
+    // variables, compared by their values (a variable may be assigned several times)
+    let mut names: Vec<&str> = vec![];
+    for entry in old.iter().chain(new.iter()) {
+        if let Entry::Variable { ref name, .. } = *entry {
+            if !names.contains(&&name[..]) {
+                names.push(name);
+            }
+        }
+    }
+    let values = |entries: &[Entry], name: &str| -> Vec<String> {
+        entries.iter().filter_map(|entry| match *entry {
+            Entry::Variable { name: ref variable, ref value } if variable == name => Some(value.clone()),
+            _ => None,
+        }).collect()
+    };
+    for name in names {
+        let (removed, added) = unmatched(&values(&old, name), &values(&new, name), |a, b| a == b);
+        let name = name.to_string();
+        for index in 0..removed.len().max(added.len()) {
+            changes.push(match (removed.get(index), added.get(index)) {
+                (Some(old_value), Some(new_value)) => Change::VariableChanged { name: name.clone(), old_value: old_value.clone(), new_value: new_value.clone() },
+                (Some(value), None) => Change::VariableRemoved { name: name.clone(), value: value.clone() },
+                (None, Some(value)) => Change::VariableAdded { name: name.clone(), value: value.clone() },
+                (None, None) => unreachable!(),
+            });
+        }
+    }
+
+    let lines = |entries: &[Entry]| -> Vec<String> {
+        entries.iter().filter_map(|entry| match *entry { Entry::Line(ref line) => Some(line.clone()), _ => None }).collect()
+    };
+    let (removed, added) = unmatched(&lines(&old), &lines(&new), |a, b| a == b);
+    changes.extend(removed.into_iter().map(|line| Change::LineRemoved { line }));
+    changes.extend(added.into_iter().map(|line| Change::LineAdded { line }));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+
+    use diff::*;
+
+    #[test]
+    fn test_diff_crontabs() {
+        let old = "# backups\nMAILTO=ops@example.com\n0 2 * * * root /usr/bin/backup --full\n0 0 * * * root  /usr/bin/rotate\n*/5 * * * * www /usr/bin/poll\n";
+        let new = "MAILTO=admin@example.com\n0 * * * * root /usr/bin/backup  --full\n0 0 * * *   root /usr/bin/rotate\n# poll removed\n30 6 * * mon root /usr/bin/report\n";
+        let changes: Vec<String> = diff_crontabs(old, new, None).iter().map(|change| change.text()).collect();
+        assert_eq!(changes, vec![
+            "~ /usr/bin/backup --full (as root) now runs hourly instead of daily at 02:00",
+            "- /usr/bin/poll (as www), ran every 5 minutes",
+            "+ /usr/bin/report (as root), runs every Monday at 06:30",
+            "~ MAILTO is now admin@example.com instead of ops@example.com",
+        ]);
+        assert!(diff_crontabs(old, &old.replace(" * * * ", "  *  *  *  "), None).is_empty());
+        assert_eq!(diff_crontabs("", "0 6 * * 1-5 /usr/bin/start\n", Some("alice")), vec![Change::JobAdded {
+            user: "alice".to_string(),
+            command: "/usr/bin/start".to_string(),
+            schedule: "0 6 * * 1-5".to_string(),
+            runs: "at 06:00 on weekdays".to_string(),
+        }]);
+        assert_eq!(diff_crontabs("0 6 * * *\n", "", None)[0].text(), "- 0 6 * * *");
+    }
+
+}
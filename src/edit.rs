@@ -0,0 +1,123 @@
+// visudo-like editing: the crontab is edited in a temporary copy, which only replaces the original once it is valid
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use files::write_atomically;
+
+
+const DEFAULT_EDITOR: &str = "vi";
+
+fn run_editor(path: &Path) -> io::Result<()> {
+    let editor = env::var("VISUAL").or(env::var("EDITOR")).unwrap_or(DEFAULT_EDITOR.to_string());
+    // through the shell, so that editors may be given with arguments (e.g. "code --wait")
+    let status = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} exited with {}", editor, status)))
+    }
+}
+
+fn ask_edit_again() -> io::Result<bool> {
+    loop {
+        print!("What now? (e)dit again, e(x)it without saving: ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Ok(false);
+        }
+        match answer.trim() {
+            "e" => return Ok(true),
+            "x" => return Ok(false),
+            _ => (),
+        }
+    }
+}
+
+// Edit the file (created if it does not exist) until `check` accepts it, or the user gives up.
+// Returns whether the file was saved.
+pub fn edit_crontab<F: FnMut(&[u8]) -> bool>(path: &Path, mut check: F) -> io::Result<bool> {
+    let original = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
+    };
+    let temporary = create_temporary_copy(path, &original)?;
+    let result = edit_until_valid(path, &temporary, &original, &mut check);
+    let _ = fs::remove_file(&temporary);
+    result
+}
+
+// The copy is created in the shared temporary directory, which others may write to: it must be a new file (not a
+// symlink planted under its name, which would make a root edit overwrite what it points to), readable by the user only
+fn create_temporary_copy(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    for attempt in 0..100 {
+        let temporary = env::temp_dir().join(format!("crontabcheck-{}-{}-{}", process::id(), attempt, name));
+        match OpenOptions::new().write(true).create_new(true).mode(0o600).open(&temporary) {
+            Ok(mut file) => {
+                file.write_all(contents)?;
+                return Ok(temporary);
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "could not create a temporary copy of the crontab"))
+}
+
+fn edit_until_valid<F: FnMut(&[u8]) -> bool>(path: &Path, temporary: &Path, original: &[u8], check: &mut F) -> io::Result<bool> {
+    loop {
+        run_editor(temporary)?;
+        let contents = fs::read(temporary)?;
+        if contents == original {
+            println!("{}: unchanged", path.display());
+            return Ok(true);
+        }
+        if check(&contents) {
+            write_atomically(path, &contents)?;
+            return Ok(true);
+        }
+        if !ask_edit_again()? {
+            println!("{}: not saved", path.display());
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    use edit::*;
+
+    #[test]
+    fn test_create_temporary_copy() {
+        let target = env::temp_dir().join(format!("crontabcheck-test-target-{}", process::id()));
+        fs::write(&target, "precious").unwrap();
+        let planted = env::temp_dir().join(format!("crontabcheck-{}-0-planted", process::id()));
+        let _ = fs::remove_file(&planted);
+        symlink(&target, &planted).unwrap();
+
+        let copy = create_temporary_copy(Path::new("/etc/cron.d/planted"), b"0 2 * * * root true\n").unwrap();
+        assert_ne!(copy, planted);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "precious");
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "0 2 * * * root true\n");
+        assert_eq!(fs::metadata(&copy).unwrap().permissions().mode() & 0o777, 0o600);
+        for path in &[copy, planted, target] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+}
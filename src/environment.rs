@@ -0,0 +1,272 @@
+// Checks on the values of the environment variables cron itself interprets
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use command::check_executable;
+use files::in_root;
+use suggest::closest_match;
+
+
+pub const ZONEINFO_DIRECTORY: &str = "/usr/share/zoneinfo";
+// vixie cron's MAX_ENVSTR: longer assignments are not read correctly
+pub const DEFAULT_MAX_ENV_VALUE_LENGTH: usize = 1000;
+
+fn mailto_recipient_warning(recipient: &str) -> Option<String> {
+    if recipient.is_empty() {
+        return Some("empty recipient in MAILTO (trailing or doubled comma?)".to_string());
+    }
+    if recipient.contains(char::is_whitespace) {
+        return Some(format!("MAILTO recipient '{}' contains spaces: separate recipients with commas", recipient));
+    }
+    let parts: Vec<&str> = recipient.split('@').collect();
+    match parts.len() {
+        // local user
+        1 if recipient.contains('.') && !recipient.starts_with('.') => Some(format!("MAILTO recipient '{}' looks like a domain name: missing '@'?", recipient)),
+        1 => None,
+        2 if parts[0].is_empty() => Some(format!("MAILTO recipient '{}' has nothing before the '@'", recipient)),
+        2 if parts[1].is_empty() || parts[1].starts_with('.') || parts[1].ends_with('.') || parts[1].contains("..") =>
+            Some(format!("MAILTO recipient '{}' has an invalid domain", recipient)),
+        2 => None,
+        _ => Some(format!("MAILTO recipient '{}' contains several '@'", recipient)),
+    }
+}
+
+// MAILTO is either empty (no mail is sent) or a comma-separated list of addresses or local users
+pub fn mailto_warnings(value: &str) -> Vec<String> {
+    if value.is_empty() {
+        return vec![];
+    }
+    value.split(',')
+        .filter_map(|recipient| mailto_recipient_warning(recipient.trim()))
+        .collect()
+}
+
+// cron passes values verbatim into the environment of every job
+pub fn check_value(name: &str, value: &str, max_length: usize) -> Result<(), String> {
+    if value.len() > max_length {
+        return Err(format!("the value of {} is {} characters long (maximum: {})", name, value.len(), max_length));
+    }
+    if let Some((index, c)) = value.char_indices().find(|&(_, c)| c.is_control() && c != '\t') {
+        return Err(format!("the value of {} contains a control character ({:?}) at position {}", name, c, index + 1));
+    }
+    Ok(())
+}
+
+// The shells listed in the /etc/shells of `root`
+pub fn shells_from_etc_shells(root: &Path) -> Result<Vec<String>, io::Error> {
+    let file = BufReader::new(File::open(in_root(root, "/etc/shells"))?);
+    let mut shells: Vec<String> = vec![];
+    for line in file.lines() {
+        let line = line?;
+        let shell = line.trim();
+        if !shell.is_empty() && !shell.starts_with('#') {
+            shells.push(shell.to_string());
+        }
+    }
+    Ok(shells)
+}
+
+// A broken SHELL makes every job of the crontab fail.
+// `filesystem` is the root of the filesystem to look the shell up in, if it should be checked.
+pub fn check_shell<T: AsRef<str>>(value: &str, filesystem: Option<&Path>, allowed_shells: Option<&[T]>) -> Result<(), String> {
+    if !value.starts_with('/') {
+        return Err(format!("SHELL must be an absolute path, got '{}'", value));
+    }
+    if let Some(root) = filesystem {
+        check_executable(&in_root(root, value)).map_err(|e| format!("invalid SHELL: {}", e))?;
+    }
+    if let Some(allowed_shells) = allowed_shells {
+        if !allowed_shells.iter().any(|shell| shell.as_ref() == value) {
+            return Err(format!("SHELL {} is not listed in /etc/shells", value));
+        }
+    }
+    Ok(())
+}
+
+// The entries searching the current directory: "." and empty ones ("::", or a leading or trailing ':'), which
+// make a job run whatever program of that name is in the directory it runs from
+pub fn path_security_warnings(value: &str) -> Vec<String> {
+    let mut warnings = vec![];
+    if !value.is_empty() && value.split(':').any(str::is_empty) {
+        warnings.push(format!("PATH '{}' has an empty entry, which searches the current directory: \
+                               jobs may run programs from it instead of the system ones", value));
+    }
+    for directory in value.split(':').filter(|directory| *directory == "." || *directory == "./") {
+        warnings.push(format!("PATH entry '{}' searches the current directory: \
+                               jobs may run programs from it instead of the system ones", directory));
+    }
+    warnings
+}
+
+pub fn path_warnings(value: &str, filesystem: Option<&Path>) -> Vec<String> {
+    let mut warnings = path_security_warnings(value);
+    for directory in value.split(':').filter(|directory| !directory.is_empty() && *directory != "." && *directory != "./") {
+        if !directory.starts_with('/') {
+            warnings.push(format!("PATH entry '{}' is not an absolute path", directory));
+        } else if filesystem.map(|root| !in_root(root, directory).is_dir()).unwrap_or(false) {
+            warnings.push(format!("PATH entry '{}' is not an existing directory", directory));
+        }
+    }
+    warnings
+}
+
+// names of the time zones in a zoneinfo directory ("Europe/Paris"...)
+fn zone_names(directory: &Path, prefix: &str, names: &mut Vec<String>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let name = format!("{}{}", prefix, file_name);
+        let path = entry.path();
+        if path.is_dir() {
+            // alternative copies of the whole database
+            if prefix.is_empty() && (file_name == "posix" || file_name == "right") {
+                continue;
+            }
+            zone_names(&path, &format!("{}/", name), names);
+        } else if file_name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            names.push(name);
+        }
+    }
+}
+
+// TZ and CRON_TZ must name a zone of the tz database, or cron silently falls back to UTC
+pub fn check_timezone(name: &str, value: &str, zoneinfo: &Path) -> Result<(), String> {
+    // POSIX allows a leading colon before a zone name
+    let zone = value.trim_start_matches(':');
+    // without a zone database, there is nothing to check against
+    if !zoneinfo.is_dir() {
+        return Ok(());
+    }
+    // POSIX TZ strings describing the zone itself (e.g. "CET-1CEST")
+    if !zone.contains('/') && zone.contains(|c: char| c.is_ascii_digit()) {
+        return Ok(());
+    }
+    if !zone.is_empty() && !zone.starts_with('/') && !zone.split('/').any(|part| part == "..") && zoneinfo.join(zone).is_file() {
+        return Ok(());
+    }
+    let mut zones = vec![];
+    zone_names(zoneinfo, "", &mut zones);
+    match closest_match(zone, &zones) {
+        Some(suggestion) => Err(format!("unknown time zone '{}' in {}, did you mean '{}'?", zone, name, suggestion)),
+        None => Err(format!("unknown time zone '{}' in {}", zone, name)),
+    }
+}
+
+// cronie: maximum number of minutes jobs are randomly delayed by
+pub fn check_random_delay(value: &str) -> Result<(), String> {
+    value.parse::<u32>()
+        .map(|_| ())
+        .map_err(|_| format!("RANDOM_DELAY must be a number of minutes, got '{}'", value))
+}
+
+// cronie/anacron: hours during which jobs may start, e.g. 3-22
+pub fn check_start_hours_range(value: &str) -> Result<(), String> {
+    let invalid = || format!("START_HOURS_RANGE must be a range of hours (e.g. 3-22), got '{}'", value);
+    let bounds: Vec<&str> = value.split('-').collect();
+    if bounds.len() != 2 {
+        return Err(invalid());
+    }
+    let start = bounds[0].parse::<u32>().map_err(|_| invalid())?;
+    let end = bounds[1].parse::<u32>().map_err(|_| invalid())?;
+    if start > 23 || end > 24 {
+        return Err(format!("START_HOURS_RANGE {} is out of bounds (hours go from 0 to 24)", value));
+    }
+    if start >= end {
+        return Err(format!("START_HOURS_RANGE {} is empty: the range must start before it ends", value));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use environment::*;
+
+    #[test]
+    fn test_mailto_warnings() {
+        assert!(mailto_warnings("").is_empty());
+        assert!(mailto_warnings("root").is_empty());
+        assert!(mailto_warnings("ops@example.com, root,dev-team@lists.example.org").is_empty());
+        assert_eq!(mailto_warnings("ops@example.com,").len(), 1);
+        assert_eq!(mailto_warnings("ops.example.com").len(), 1);
+        assert_eq!(mailto_warnings("ops@example.com dev@example.com").len(), 1);
+        assert_eq!(mailto_warnings("@example.com,ops@,ops@@example.com,ops@example..com").len(), 4);
+    }
+
+    #[test]
+    fn test_check_value() {
+        assert!(check_value("MAILTO", "ops@example.com", DEFAULT_MAX_ENV_VALUE_LENGTH).is_ok());
+        assert!(check_value("PATH", "/usr/bin:/bin", 5).is_err());
+        assert!(check_value("GREETING", "hello\tworld", DEFAULT_MAX_ENV_VALUE_LENGTH).is_ok());
+        assert_eq!(
+            check_value("GREETING", "hello\x1b[31m", DEFAULT_MAX_ENV_VALUE_LENGTH),
+            Err("the value of GREETING contains a control character ('\\u{1b}') at position 6".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_shell() {
+        let no_shells: Option<&[&str]> = None;
+        let root = Some(Path::new("/"));
+        assert!(check_shell("/bin/sh", root, no_shells).is_ok());
+        assert!(check_shell("bash", None, no_shells).is_err());
+        assert!(check_shell("/nonexistent/shell", None, no_shells).is_ok());
+        assert!(check_shell("/nonexistent/shell", root, no_shells).is_err());
+        assert!(check_shell("/bin/sh", Some(Path::new("/nonexistent")), no_shells).is_err());
+        assert!(check_shell("/bin/sh", None, Some(&["/bin/sh", "/bin/bash"][..])).is_ok());
+        assert!(check_shell("/bin/zsh", None, Some(&["/bin/sh", "/bin/bash"][..])).is_err());
+    }
+
+    #[test]
+    fn test_check_timezone() {
+        let zoneinfo = Path::new(ZONEINFO_DIRECTORY);
+        if !zoneinfo.is_dir() {
+            return;
+        }
+        assert!(check_timezone("CRON_TZ", "Europe/Paris", zoneinfo).is_ok());
+        assert!(check_timezone("TZ", ":UTC", zoneinfo).is_ok());
+        assert!(check_timezone("TZ", "CET-1CEST", zoneinfo).is_ok());
+        assert_eq!(
+            check_timezone("CRON_TZ", "Eruope/Paris", zoneinfo),
+            Err("unknown time zone 'Eruope/Paris' in CRON_TZ, did you mean 'Europe/Paris'?".to_string())
+        );
+        assert!(check_timezone("TZ", "../../etc/passwd", zoneinfo).is_err());
+        assert!(check_timezone("TZ", "/etc/passwd", zoneinfo).is_err());
+    }
+
+    #[test]
+    fn test_check_random_delay() {
+        assert!(check_random_delay("45").is_ok());
+        assert!(check_random_delay("-5").is_err());
+        assert!(check_random_delay("10m").is_err());
+    }
+
+    #[test]
+    fn test_check_start_hours_range() {
+        assert!(check_start_hours_range("3-22").is_ok());
+        assert!(check_start_hours_range("0-24").is_ok());
+        assert!(check_start_hours_range("3").is_err());
+        assert!(check_start_hours_range("22-3").is_err());
+        assert!(check_start_hours_range("3-25").is_err());
+        assert!(check_start_hours_range("a-b").is_err());
+    }
+
+    #[test]
+    fn test_path_warnings() {
+        let root = Some(Path::new("/"));
+        assert!(path_warnings("/usr/bin:/bin", root).is_empty());
+        assert_eq!(path_warnings("/usr/bin:bin:/nonexistent", None).len(), 1);
+        assert_eq!(path_warnings("/usr/bin:bin:/nonexistent", root).len(), 2);
+        assert_eq!(path_warnings("/usr/bin::/bin", None).len(), 1);
+        assert_eq!(path_warnings(":/usr/bin", None).len(), 1);
+        assert_eq!(path_warnings("/usr/bin:.", None), vec![
+            "PATH entry '.' searches the current directory: jobs may run programs from it instead of the system ones".to_string()
+        ]);
+    }
+
+}
@@ -0,0 +1,150 @@
+// C bindings of the library (see include/crontabcheck.h), for agents written in C or C++ to check crontabs without
+// running the command. The pointers must be valid as described in the header.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+
+use parser::CrontabParserOptions;
+use validate::{ValidationError, validate_crontab, validate_line};
+
+
+pub struct CheckResult {
+    errors: Vec<(usize, usize, CString)>,
+}
+
+// NULL and invalid UTF-8 are None
+unsafe fn c_string<'a>(pointer: *const c_char) -> Option<&'a str> {
+    if pointer.is_null() {
+        None
+    } else {
+        CStr::from_ptr(pointer).to_str().ok()
+    }
+}
+
+fn check_result(errors: Vec<ValidationError>) -> *mut CheckResult {
+    let errors = errors.into_iter().map(|error| {
+        // messages do not contain NUL characters, unless the line does
+        let message = CString::new(error.message.replace('\0', "\\0")).unwrap_or_default();
+        // from 1, so that 0 can mean that the error is not at a place of the line
+        (error.line_number, error.column.map(|column| column + 1).unwrap_or(0), message)
+    }).collect();
+    Box::into_raw(Box::new(CheckResult { errors }))
+}
+
+// a panic must not unwind into C code: it returns NULL, as invalid strings do
+unsafe fn check(text: *const c_char, owner: *const c_char, check_text: &dyn Fn(&str, &CrontabParserOptions<&str>) -> Vec<ValidationError>) -> *mut CheckResult {
+    let text = match c_string(text) {
+        Some(text) => text,
+        None => return ptr::null_mut(),
+    };
+    if !owner.is_null() && c_string(owner).is_none() {
+        return ptr::null_mut();
+    }
+    let options = CrontabParserOptions { crontab_owner: c_string(owner), ..Default::default() };
+    catch_unwind(AssertUnwindSafe(|| check_result(check_text(text, &options)))).unwrap_or(ptr::null_mut())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn crontabcheck_check(contents: *const c_char, owner: *const c_char) -> *mut CheckResult {
+    check(contents, owner, &|contents, options| validate_crontab(contents, options))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn crontabcheck_check_line(line: *const c_char, owner: *const c_char) -> *mut CheckResult {
+    check(line, owner, &|line, options| validate_line(1, line, options).err().unwrap_or_default())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn crontabcheck_result_count(result: *const CheckResult) -> usize {
+    result.as_ref().map(|result| result.errors.len()).unwrap_or(0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn crontabcheck_result_get(result: *const CheckResult, index: usize, line_number: *mut usize, column: *mut usize, message: *mut *const c_char) -> c_int {
+    let &(error_line_number, error_column, ref error_message) = match result.as_ref().and_then(|result| result.errors.get(index)) {
+        Some(error) => error,
+        None => return 0,
+    };
+    if let Some(line_number) = line_number.as_mut() {
+        *line_number = error_line_number;
+    }
+    if let Some(column) = column.as_mut() {
+        *column = error_column;
+    }
+    if let Some(message) = message.as_mut() {
+        *message = error_message.as_ptr();
+    }
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn crontabcheck_result_free(result: *mut CheckResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use ffi::*;
+
+    #[test]
+    fn test_check() {
+        let contents = CString::new("# backups\n0 2 * * wedn root /usr/bin/backup\n").unwrap();
+        unsafe {
+            let result = crontabcheck_check(contents.as_ptr(), ptr::null());
+            assert_eq!(crontabcheck_result_count(result), 1);
+            let (mut line_number, mut column, mut message) = (0usize, 0usize, ptr::null());
+            assert_eq!(crontabcheck_result_get(result, 0, &mut line_number, &mut column, &mut message), 1);
+            assert_eq!((line_number, column), (2, 9));
+            assert!(CStr::from_ptr(message).to_str().unwrap().starts_with("unknown day of the week 'wedn'"));
+            assert_eq!(crontabcheck_result_get(result, 1, &mut line_number, &mut column, &mut message), 0);
+            crontabcheck_result_free(result);
+        }
+    }
+
+    #[test]
+    fn test_check_without_column() {
+        let contents = CString::new("0 2 * * * root /usr/bin/backup").unwrap();
+        let line = CString::new("wedn 2 * * * root /usr/bin/backup").unwrap();
+        unsafe {
+            let result = crontabcheck_check(contents.as_ptr(), ptr::null());
+            let mut column = 1usize;
+            assert_eq!(crontabcheck_result_get(result, 0, ptr::null_mut(), &mut column, ptr::null_mut()), 1);
+            assert_eq!(column, 0);
+            crontabcheck_result_free(result);
+            // an error at the start of the line
+            let result = crontabcheck_check_line(line.as_ptr(), ptr::null());
+            assert_eq!(crontabcheck_result_get(result, 0, ptr::null_mut(), &mut column, ptr::null_mut()), 1);
+            assert_eq!(column, 1);
+            crontabcheck_result_free(result);
+        }
+    }
+
+    #[test]
+    fn test_check_panic() {
+        let contents = CString::new("0 2 * * * root /usr/bin/backup\n").unwrap();
+        unsafe {
+            assert!(check(contents.as_ptr(), ptr::null(), &|_, _| panic!("bug")).is_null());
+        }
+    }
+
+    #[test]
+    fn test_check_line() {
+        let line = CString::new("0 2 * * * /usr/bin/backup").unwrap();
+        let owner = CString::new("alice").unwrap();
+        unsafe {
+            let result = crontabcheck_check_line(line.as_ptr(), owner.as_ptr());
+            assert_eq!(crontabcheck_result_count(result), 0);
+            crontabcheck_result_free(result);
+            assert!(crontabcheck_check_line(ptr::null(), ptr::null()).is_null());
+            crontabcheck_result_free(ptr::null_mut());
+        }
+    }
+
+}
@@ -0,0 +1,331 @@
+// Checks on crontab files themselves, rather than on their contents
+
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Deref;
+use std::os::unix::fs::{MetadataExt, PermissionsExt, chown};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+use memmap2::Mmap;
+
+
+const SYSTEM_CRONTAB: &str = "/etc/crontab";
+const CRON_D_DIRECTORY: &str = "/etc/cron.d";
+// user crontabs: Debian's cron keeps them in a subdirectory, cronie directly in /var/spool/cron
+const USER_SPOOL_DIRECTORIES: &[&str] = &["/var/spool/cron/crontabs", "/var/spool/cron"];
+// files from this size on (e.g. concatenated crontab exports) are mapped in memory rather than read
+const MAPPED_FILE_MIN_SIZE: u64 = 1 << 20;
+
+// A crontab file found on the system
+pub struct CrontabFile {
+    pub path: PathBuf,
+    // the user owning a user crontab (its file name), None for system crontabs
+    pub owner: Option<String>,
+}
+
+// Contents of a file, read or mapped in memory
+pub enum FileContents {
+    Read(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for FileContents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            FileContents::Read(ref contents) => contents,
+            FileContents::Mapped(ref map) => map,
+        }
+    }
+}
+
+
+// cron ignores files in /etc/cron.d that are writable by anyone but root
+pub fn check_permissions(path: &Path) -> Result<(), Vec<String>> {
+    let metadata = fs::metadata(path).map_err(|e| vec![format!("could not stat {}: {}", path.display(), e)])?;
+    let mut errors = vec![];
+    if metadata.uid() != 0 {
+        errors.push(format!("the file is owned by uid {}, not root: cron will ignore it", metadata.uid()));
+    }
+    if metadata.mode() & 0o022 != 0 {
+        errors.push(format!("the file is group or world writable (mode {:o}): cron will ignore it", metadata.mode() & 0o7777));
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+// as Linux does before failing with ELOOP
+const MAX_SYMLINKS: usize = 40;
+
+fn path_components(path: &Path) -> Vec<OsString> {
+    path.components().filter_map(|component| match component {
+        Component::Normal(name) => Some(name.to_os_string()),
+        Component::ParentDir => Some(OsString::from("..")),
+        _ => None,
+    }).collect()
+}
+
+// An absolute path as seen from another root directory (e.g. /bin/sh in an OS image extracted in /mnt/image).
+// Symlinks and '..' are resolved one component at a time under the root, as chroot would: in the image, `bin ->
+// /usr/bin` leads to /mnt/image/usr/bin, not to the /usr/bin of the host.
+pub fn in_root<P: AsRef<Path>>(root: &Path, path: P) -> PathBuf {
+    let path = path.as_ref();
+    if root == Path::new("/") {
+        return root.join(path);
+    }
+    let mut resolved: Vec<OsString> = vec![];
+    let mut pending: VecDeque<OsString> = path_components(path).into_iter().collect();
+    let mut symlinks = 0;
+    while let Some(name) = pending.pop_front() {
+        if name == ".." {
+            resolved.pop();
+            continue;
+        }
+        let candidate = resolved.iter().fold(root.to_path_buf(), |path, name| path.join(name)).join(&name);
+        match fs::read_link(&candidate) {
+            Ok(ref target) if symlinks < MAX_SYMLINKS => {
+                symlinks += 1;
+                if target.is_absolute() {
+                    resolved.clear();
+                }
+                for component in path_components(target).into_iter().rev() {
+                    pending.push_front(component);
+                }
+            },
+            // not a symlink, missing, or a loop
+            _ => resolved.push(name),
+        }
+    }
+    resolved.iter().fold(root.to_path_buf(), |path, name| path.join(name))
+}
+
+// The accounts with uid 0 in /etc/passwd (root and its aliases, such as toor on BSDs)
+pub fn uid0_usernames(root: &Path) -> io::Result<Vec<String>> {
+    let file = BufReader::new(File::open(in_root(root, "/etc/passwd"))?);
+    let mut usernames: Vec<String> = vec![];
+    for line in file.lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() > 2 && fields[2].trim() == "0" {
+            usernames.push(fields[0].trim().to_string());
+        }
+    }
+    Ok(usernames)
+}
+
+// Large regular files are mapped in memory, the others (small files, pipes...) are read
+pub fn read_file(path: &Path) -> io::Result<FileContents> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    if metadata.is_file() && metadata.len() >= MAPPED_FILE_MIN_SIZE {
+        // the file could be truncated while it is checked (reading it then fails with SIGBUS): crontabs are not
+        // rewritten in place, they are replaced (see replace_file)
+        return Ok(FileContents::Mapped(unsafe { Mmap::map(&file)? }));
+    }
+    let mut contents = vec![];
+    file.read_to_end(&mut contents)?;
+    Ok(FileContents::Read(contents))
+}
+
+// mode of installed crontabs: cron ignores files in /etc/cron.d writable by anyone but root
+const INSTALLED_CRONTAB_MODE: u32 = 0o644;
+
+// Replace the file's contents at once (cron never sees a partly written file).
+// The temporary file is hidden: cron ignores it in case it is left behind.
+fn replace_file(path: &Path, contents: &[u8], mode: u32, root_owned: bool) -> io::Result<()> {
+    let name = path.file_name().ok_or(io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let temporary = path.with_file_name(format!(".{}.crontabcheck-{}", name.to_string_lossy(), process::id()));
+    fs::write(&temporary, contents)
+        .and_then(|_| fs::set_permissions(&temporary, fs::Permissions::from_mode(mode)))
+        .and_then(|_| if root_owned { chown(&temporary, Some(0), Some(0)) } else { Ok(()) })
+        .and_then(|_| fs::rename(&temporary, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temporary);
+        })
+}
+
+// Atomically replace the file's contents, keeping its mode
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mode = match fs::metadata(path) {
+        Ok(metadata) => metadata.mode() & 0o7777,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => INSTALLED_CRONTAB_MODE,
+        Err(e) => return Err(e),
+    };
+    replace_file(path, contents, mode, false)
+}
+
+// Atomically install a crontab, owned by root with the mode cron expects
+pub fn install_crontab(path: &Path, contents: &[u8]) -> io::Result<()> {
+    replace_file(path, contents, INSTALLED_CRONTAB_MODE, true)
+}
+
+// The user a crontab belongs to, when the path is in a user crontab spool directory
+pub fn user_crontab_owner(path: &Path, root: &Path) -> Option<String> {
+    match (path.parent(), path.file_name()) {
+        (Some(directory), Some(name)) if USER_SPOOL_DIRECTORIES.iter().any(|spool| in_root(root, spool) == directory) => {
+            Some(name.to_string_lossy().into_owned())
+        },
+        _ => None,
+    }
+}
+
+// The crontab cron reads at this (absolute) path, if any
+pub fn crontab_file(path: &Path) -> Option<CrontabFile> {
+    let hidden = path.file_name().map(|name| name.to_string_lossy().starts_with('.')).unwrap_or(true);
+    if path == Path::new(SYSTEM_CRONTAB) || (!hidden && path.parent() == Some(Path::new(CRON_D_DIRECTORY))) {
+        Some(CrontabFile { path: path.to_path_buf(), owner: None })
+    } else if hidden {
+        None
+    } else {
+        user_crontab_owner(path, Path::new("/")).map(|owner| CrontabFile { path: path.to_path_buf(), owner: Some(owner) })
+    }
+}
+
+// Regular files of a directory, sorted by name (hidden files are left out, as cron does)
+fn directory_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !hidden && entry.file_type()?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// All the crontabs cron reads: /etc/crontab, /etc/cron.d and the user crontabs (which are usually only readable by root),
+// in the filesystem under `root`. Also returns notes about the places which could not be read.
+pub fn system_crontabs(root: &Path) -> (Vec<CrontabFile>, Vec<String>) {
+    let mut crontabs = vec![];
+    let mut notes = vec![];
+    let system_crontab = in_root(root, SYSTEM_CRONTAB);
+    if system_crontab.is_file() {
+        crontabs.push(CrontabFile { path: system_crontab, owner: None });
+    }
+    let cron_d = in_root(root, CRON_D_DIRECTORY);
+    match directory_files(&cron_d) {
+        Ok(files) => crontabs.extend(files.into_iter().map(|path| CrontabFile { path, owner: None })),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => notes.push(format!("could not list {}: {}", cron_d.display(), e)),
+    }
+    if let Some(spool) = USER_SPOOL_DIRECTORIES.iter().map(|spool| in_root(root, spool)).find(|directory| directory.is_dir()) {
+        match directory_files(&spool) {
+            Ok(files) => crontabs.extend(files.into_iter().map(|path| {
+                let owner = user_crontab_owner(&path, root);
+                CrontabFile { path, owner }
+            })),
+            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                notes.push(format!("user crontabs in {} were not checked: permission denied (run as root to check them)", spool.display()))
+            },
+            Err(e) => notes.push(format!("could not list {}: {}", spool.display(), e)),
+        }
+    }
+    (crontabs, notes)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs::{self, File};
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    use files::*;
+
+    #[test]
+    fn test_check_permissions() {
+        let path = env::temp_dir().join(format!("crontabcheck-permissions-{}", ::std::process::id()));
+        File::create(&path).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+        let errors = check_permissions(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(errors.iter().any(|e| e.contains("writable")));
+
+        assert!(check_permissions(Path::new("/nonexistent/crontab")).is_err());
+    }
+
+    #[test]
+    fn test_user_crontab_owner() {
+        let root = Path::new("/");
+        assert_eq!(user_crontab_owner(Path::new("/var/spool/cron/crontabs/alice"), root), Some("alice".to_string()));
+        assert_eq!(user_crontab_owner(Path::new("/var/spool/cron/bob"), root), Some("bob".to_string()));
+        assert_eq!(user_crontab_owner(Path::new("/etc/cron.d/alice"), root), None);
+        assert_eq!(user_crontab_owner(Path::new("/mnt/image/var/spool/cron/bob"), Path::new("/mnt/image")), Some("bob".to_string()));
+    }
+
+    #[test]
+    fn test_crontab_file() {
+        assert!(crontab_file(Path::new("/etc/crontab")).is_some());
+        assert!(crontab_file(Path::new("/etc/cron.d/backup")).is_some());
+        assert!(crontab_file(Path::new("/etc/cron.d/.placeholder")).is_none());
+        assert!(crontab_file(Path::new("/etc/passwd")).is_none());
+        assert_eq!(crontab_file(Path::new("/var/spool/cron/crontabs/alice")).unwrap().owner, Some("alice".to_string()));
+    }
+
+    #[test]
+    fn test_in_root() {
+        assert_eq!(in_root(Path::new("/"), "/bin/sh"), Path::new("/bin/sh"));
+        assert_eq!(in_root(Path::new("/mnt/image"), "/bin/sh"), Path::new("/mnt/image/bin/sh"));
+
+        let root = env::temp_dir().join(format!("crontabcheck-test-root-{}", process::id()));
+        fs::create_dir_all(root.join("usr/bin")).unwrap();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        symlink("/usr/bin", root.join("bin")).unwrap();
+        symlink("../usr/bin", root.join("etc/tools")).unwrap();
+        symlink("loop", root.join("loop")).unwrap();
+        assert_eq!(in_root(&root, "/bin/sh"), root.join("usr/bin/sh"));
+        assert_eq!(in_root(&root, "/etc/tools/sh"), root.join("usr/bin/sh"));
+        assert_eq!(in_root(&root, "/../../etc/passwd"), root.join("etc/passwd"));
+        assert_eq!(in_root(&root, "/bin/../../../etc"), root.join("etc"));
+        assert_eq!(in_root(&root, "/loop/x"), root.join("loop/x"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_read_file() {
+        let path = env::temp_dir().join(format!("crontabcheck-read-{}", ::std::process::id()));
+        fs::write(&path, "0 2 * * * root /usr/bin/backup\n").unwrap();
+        let contents = read_file(&path).unwrap();
+        assert!(matches!(contents, FileContents::Read(_)));
+        assert_eq!(&contents[..], b"0 2 * * * root /usr/bin/backup\n");
+
+        let line = "0 2 * * * root /usr/bin/backup\n".repeat(40000);
+        fs::write(&path, &line).unwrap();
+        let contents = read_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(contents, FileContents::Mapped(_)));
+        assert_eq!(&contents[..], line.as_bytes());
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let path = env::temp_dir().join(format!("crontabcheck-write-{}", ::std::process::id()));
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        write_atomically(&path, b"new").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(contents, "new");
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_directory_files() {
+        let directory = env::temp_dir().join(format!("crontabcheck-directory-{}", ::std::process::id()));
+        fs::create_dir(&directory).unwrap();
+        for name in &["b", "a", ".hidden"] {
+            File::create(directory.join(name)).unwrap();
+        }
+        fs::create_dir(directory.join("subdirectory")).unwrap();
+        let files = directory_files(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(files.unwrap(), vec![directory.join("a"), directory.join("b")]);
+    }
+
+}
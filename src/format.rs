@@ -0,0 +1,191 @@
+// Canonical formatting of crontabs: fields of jobs separated by single spaces (or aligned in columns), lowercase
+// month and day names, 0 for Sunday. Comments, variables and lines which cannot be parsed are left as they are.
+// Also the safe automatic fixes of --fix, which leave the rest of the file alone.
+
+use parser::{CrontabLine, CrontabParserOptions, parse_crontab};
+use schedule::{DAY_NAMES, MONTH_NAMES};
+
+
+// "JAN-Mar" -> "jan-mar" (other words, e.g. Jenkins' H, are left alone)
+fn lowercase_names(field: &str, names: &[&str]) -> String {
+    let mut formatted = String::new();
+    let mut word = String::new();
+    for c in field.chars().chain(Some(',')) {
+        if c.is_ascii_alphabetic() {
+            word.push(c);
+            continue;
+        }
+        let lowercase = word.to_lowercase();
+        formatted.push_str(if names.contains(&&lowercase[..]) { &lowercase } else { &word });
+        word.clear();
+        formatted.push(c);
+    }
+    formatted.pop();
+    formatted
+}
+
+// Sunday is both 0 and 7: "1,7" -> "1,0" (ranges such as "5-7" are left alone)
+fn sunday_as_zero(field: &str) -> String {
+    field.split(',').map(|item| if item == "7" { "0" } else { item }).collect::<Vec<&str>>().join(",")
+}
+
+// Lowercase names and 0 for Sunday in the time and date fields at the start of the line, keeping its separators.
+// Also returns what was changed.
+fn normalize_names(line: &str) -> Option<(String, Vec<&'static str>)> {
+    // byte ranges of the five fields
+    let mut spans = vec![];
+    let mut position = 0;
+    for _ in 0..5 {
+        let start = position + line[position..].find(|c: char| !c.is_whitespace())?;
+        let end = start + line[start..].find(char::is_whitespace).unwrap_or(line.len() - start);
+        spans.push((start, end));
+        position = end;
+    }
+    let (month, day_of_week) = (&line[spans[3].0..spans[3].1], &line[spans[4].0..spans[4].1]);
+    let lowercase_month = lowercase_names(month, MONTH_NAMES);
+    let lowercase_day_of_week = lowercase_names(day_of_week, DAY_NAMES);
+    let normalized_day_of_week = sunday_as_zero(&lowercase_day_of_week);
+    let mut changes = vec![];
+    if lowercase_month != month || lowercase_day_of_week != day_of_week {
+        changes.push("month and day names in lowercase");
+    }
+    if normalized_day_of_week != lowercase_day_of_week {
+        changes.push("0 for Sunday instead of 7");
+    }
+    let normalized = format!("{}{}{}{}{}", &line[..spans[3].0], lowercase_month, &line[spans[3].1..spans[4].0], normalized_day_of_week, &line[spans[4].1..]);
+    Some((normalized, changes))
+}
+
+// The fields of a job line (the five time and date fields, normalized, and the user of system crontabs), and its
+// command
+type JobFields = (Vec<String>, String);
+
+// The parser only knows lowercase names: they are normalized first
+fn job_fields(line: &str, crontab_owner: Option<&str>) -> Option<JobFields> {
+    let (normalized, _) = normalize_names(line)?;
+    let options = CrontabParserOptions::<&str> { crontab_owner, ..Default::default() };
+    match parse_crontab(&normalized, &options) {
+        Ok(CrontabLine::Job { schedule, user, command }) => {
+            let mut fields: Vec<String> = schedule.split_whitespace().map(str::to_string).collect();
+            if crontab_owner.is_none() {
+                fields.push(user.to_string());
+            }
+            Some((fields, command.to_string()))
+        },
+        _ => None,
+    }
+}
+
+// Format a crontab (`crontab_owner` is the owner of a user crontab, whose lines have no user field)
+pub fn format_crontab(contents: &str, crontab_owner: Option<&str>, align: bool) -> String {
+    let lines: Vec<(&str, Option<JobFields>)> = contents.lines().map(|line| (line, job_fields(line, crontab_owner))).collect();
+    let mut widths: Vec<usize> = vec![];
+    if align {
+        for fields in lines.iter().filter_map(|(_, job)| job.as_ref().map(|(fields, _)| fields)) {
+            widths.resize(fields.len(), 0);
+            for (width, field) in widths.iter_mut().zip(fields) {
+                *width = (*width).max(field.len());
+            }
+        }
+    }
+    let mut formatted = String::new();
+    for (line, job) in lines {
+        match job {
+            Some((fields, command)) => {
+                for (index, field) in fields.iter().enumerate() {
+                    let width = widths.get(index).cloned().unwrap_or(0);
+                    formatted.push_str(&format!("{:width$} ", field, width = width));
+                }
+                formatted.push_str(&command);
+            },
+            None => formatted.push_str(line),
+        }
+        formatted.push('\n');
+    }
+    // cron ignores a last line without newline: the checks report it, formatting does not hide it
+    if !contents.ends_with('\n') {
+        formatted.pop();
+    }
+    formatted
+}
+
+// Apply the fixes which cannot change what cron runs: CRLF line endings, trailing whitespace, case of month and day
+// names, 7 for Sunday and the missing final newline. Returns the fixed crontab, and what was fixed.
+pub fn fix_crontab(contents: &str, crontab_owner: Option<&str>) -> (String, Vec<String>) {
+    let mut fixed = String::new();
+    let mut changes = vec![];
+    if contents.is_empty() {
+        return (fixed, changes);
+    }
+    // not lines(), which would hide the carriage returns
+    let mut lines: Vec<&str> = contents.split('\n').collect();
+    let missing_newline = !contents.ends_with('\n');
+    if !missing_newline {
+        lines.pop();
+    }
+    let line_count = lines.len();
+    for (index, line) in lines.into_iter().enumerate() {
+        let line_number = index + 1;
+        let mut line = line.to_string();
+        if line.ends_with('\r') {
+            line.pop();
+            changes.push(format!("line {}: removed the carriage return (CRLF line ending)", line_number));
+        }
+        let trimmed_length = line.trim_end_matches([' ', '\t']).len();
+        // an escaped space belongs to the command
+        if trimmed_length < line.len() && !line[..trimmed_length].ends_with('\\') {
+            line.truncate(trimmed_length);
+            changes.push(format!("line {}: removed trailing whitespace", line_number));
+        }
+        if let (Some(_), Some((fixed_line, line_changes))) = (job_fields(&line, crontab_owner), normalize_names(&line)) {
+            changes.extend(line_changes.into_iter().map(|change| format!("line {}: {}", line_number, change)));
+            line = fixed_line;
+        }
+        fixed.push_str(&line);
+        fixed.push('\n');
+    }
+    if missing_newline {
+        changes.push(format!("line {}: added the final newline (cron ignores a last line without one)", line_count));
+    }
+    (fixed, changes)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use format::*;
+
+    #[test]
+    fn test_format_crontab() {
+        let crontab = "# backups\nMAILTO=ops@example.com\n0  2\t* JAN-Mar 7  root   /usr/bin/backup  --full\n*/5 * * * MON,7 www-data /usr/bin/poll\n";
+        assert_eq!(format_crontab(crontab, None, false),
+                   "# backups\nMAILTO=ops@example.com\n0 2 * jan-mar 0 root /usr/bin/backup  --full\n*/5 * * * mon,0 www-data /usr/bin/poll\n");
+        assert_eq!(format_crontab(crontab, None, true),
+                   "# backups\nMAILTO=ops@example.com\n0   2 * jan-mar 0     root     /usr/bin/backup  --full\n*/5 * * *       mon,0 www-data /usr/bin/poll\n");
+        assert_eq!(format_crontab("0 2 * * 5-7\t/usr/bin/backup", Some("alice"), false), "0 2 * * 5-7 /usr/bin/backup");
+        assert_eq!(format_crontab("not a job\n", None, false), "not a job\n");
+    }
+
+    #[test]
+    fn test_fix_crontab() {
+        let (fixed, changes) = fix_crontab("MAILTO=ops \r\n0  2 * JAN 7\troot echo a\\ \n0 3 * * * root x", None);
+        assert_eq!(fixed, "MAILTO=ops\n0  2 * jan 0\troot echo a\\ \n0 3 * * * root x\n");
+        assert_eq!(changes, vec![
+            "line 1: removed the carriage return (CRLF line ending)",
+            "line 1: removed trailing whitespace",
+            "line 2: month and day names in lowercase",
+            "line 2: 0 for Sunday instead of 7",
+            "line 3: added the final newline (cron ignores a last line without one)",
+        ]);
+        assert_eq!(fix_crontab("# nothing to fix\n", None), ("# nothing to fix\n".to_string(), vec![]));
+        assert_eq!(fix_crontab("", None).0, "");
+    }
+
+    #[test]
+    fn test_lowercase_names() {
+        assert_eq!(lowercase_names("MON-FRI", DAY_NAMES), "mon-fri");
+        assert_eq!(lowercase_names("H(1-5)", DAY_NAMES), "H(1-5)");
+        assert_eq!(lowercase_names("*/2", MONTH_NAMES), "*/2");
+    }
+
+}
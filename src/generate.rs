@@ -0,0 +1,186 @@
+// Crontab files generated from job definitions in TOML or YAML, so that crontabs can be built (and checked)
+// like any other artifact
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde_yaml;
+use toml;
+
+use schedule::nickname_schedule;
+
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrontabSpec {
+    // variables set at the top of the file (MAILTO, PATH...)
+    pub environment: BTreeMap<String, String>,
+    pub jobs: Vec<JobSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobSpec {
+    pub name: String,
+    // the five time and date fields, e.g. "0 2 * * *"
+    pub schedule: String,
+    pub user: String,
+    pub command: String,
+    pub comment: Option<String>,
+}
+
+// Job definitions from a .yaml / .yml file, or else a TOML file
+pub fn parse_spec(contents: &str, file_name: &str) -> Result<CrontabSpec, String> {
+    if file_name.ends_with(".yaml") || file_name.ends_with(".yml") {
+        serde_yaml::from_str(contents).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+}
+
+// A field is emitted verbatim: a line break (or any other control character) would add lines to the crontab
+fn check_no_control_characters(what: &str, value: &str) -> Result<(), String> {
+    match value.chars().find(|&c| c.is_control() && c != '\t') {
+        Some(c) => Err(format!("{} contains the control character {:?}", what, c)),
+        None => Ok(()),
+    }
+}
+
+fn check_variable_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(|c: char| c == '=' || c.is_whitespace() || c.is_control()) {
+        return Err(format!("invalid variable name {:?}", name));
+    }
+    Ok(())
+}
+
+// The five time and date fields, or a shortcut such as @daily
+fn check_schedule_shape(schedule: &str) -> Result<(), String> {
+    let fields = schedule.split_whitespace().count();
+    let nickname = fields == 1 && (schedule.trim() == "@reboot" || nickname_schedule(schedule.trim()).is_some());
+    if fields != 5 && !nickname {
+        return Err(format!("the schedule '{}' is neither five fields nor a known @ shortcut", schedule));
+    }
+    Ok(())
+}
+
+fn check_job(job: &JobSpec) -> Result<(), String> {
+    check_no_control_characters("the name", &job.name)?;
+    check_schedule_shape(&job.schedule)?;
+    check_no_control_characters("the user", &job.user)?;
+    if job.user.is_empty() || job.user.contains(char::is_whitespace) {
+        return Err(format!("invalid user '{}'", job.user));
+    }
+    if job.command.contains('\n') {
+        return Err("the command must be a single line".to_string());
+    }
+    check_no_control_characters("the command", &job.command)?;
+    if let Some(ref comment) = job.comment {
+        for line in comment.lines() {
+            check_no_control_characters("the comment", line)?;
+        }
+    }
+    Ok(())
+}
+
+// The crontab file of the jobs, in a canonical form (the caller checks it like any other crontab)
+pub fn generate_crontab(spec: &CrontabSpec, source: &str) -> Result<String, String> {
+    let mut crontab = format!("# Generated by crontabcheck from {}: edit that file instead\n", source);
+    if !spec.environment.is_empty() {
+        crontab.push('\n');
+    }
+    for (name, value) in &spec.environment {
+        check_variable_name(name)?;
+        check_no_control_characters(&format!("the value of {}", name), value)?;
+        crontab.push_str(&format!("{}={}\n", name, value));
+    }
+    let mut names = HashSet::new();
+    for job in &spec.jobs {
+        if job.name.trim().is_empty() {
+            return Err("a job has no name".to_string());
+        }
+        if !names.insert(&job.name) {
+            return Err(format!("there are several jobs named '{}'", job.name));
+        }
+        check_job(job).map_err(|e| format!("job '{}': {}", job.name.escape_debug(), e))?;
+        let fields: Vec<&str> = job.schedule.split_whitespace().collect();
+        crontab.push_str(&format!("\n# {}\n", job.name));
+        if let Some(ref comment) = job.comment {
+            for line in comment.trim_end().lines().map(str::trim_end) {
+                if line.is_empty() {
+                    crontab.push_str("#\n");
+                } else {
+                    crontab.push_str(&format!("# {}\n", line));
+                }
+            }
+        }
+        crontab.push_str(&format!("{} {} {}\n", fields.join(" "), job.user, job.command.trim()));
+    }
+    Ok(crontab)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use generate::*;
+
+    #[test]
+    fn test_generate_crontab() {
+        let spec = parse_spec(r#"
+            [environment]
+            MAILTO = "ops@example.com"
+
+            [[jobs]]
+            name = "backup"
+            schedule = "0  2 * * *"
+            user = "root"
+            command = "/usr/bin/backup"
+            comment = "nightly backup\n\nkeeps 7 days"
+        "#, "jobs.toml").unwrap();
+        assert_eq!(generate_crontab(&spec, "jobs.toml").unwrap(), "# Generated by crontabcheck from jobs.toml: edit that file instead\n\
+                                                                   \n\
+                                                                   MAILTO=ops@example.com\n\
+                                                                   \n\
+                                                                   # backup\n\
+                                                                   # nightly backup\n\
+                                                                   #\n\
+                                                                   # keeps 7 days\n\
+                                                                   0 2 * * * root /usr/bin/backup\n");
+    }
+
+    #[test]
+    fn test_parse_yaml_spec() {
+        let spec = parse_spec("jobs:\n  - name: poll\n    schedule: '*/5 * * * *'\n    user: www-data\n    command: /usr/bin/poll\n", "jobs.yml").unwrap();
+        assert_eq!(spec.jobs.len(), 1);
+        assert_eq!(spec.jobs[0].user, "www-data");
+        assert!(parse_spec("[[jobs]]\nname = 'poll'\n", "jobs.toml").is_err());
+    }
+
+    #[test]
+    fn test_invalid_spec() {
+        let spec = parse_spec("[[jobs]]\nname='a'\nschedule='* * * * *'\nuser='root'\ncommand='x'\n\
+                               [[jobs]]\nname='a'\nschedule='* * * * *'\nuser='root'\ncommand='y'\n", "jobs.toml").unwrap();
+        assert!(generate_crontab(&spec, "jobs.toml").unwrap_err().contains("several jobs"));
+    }
+
+    #[test]
+    fn test_injected_lines() {
+        let generate = |edit: &dyn Fn(&mut CrontabSpec)| {
+            let mut spec = CrontabSpec::default();
+            spec.jobs.push(JobSpec { name: "a".to_string(), schedule: "0 2 * * *".to_string(), user: "root".to_string(),
+                                     command: "/usr/bin/backup".to_string(), comment: None });
+            edit(&mut spec);
+            generate_crontab(&spec, "jobs.toml")
+        };
+        assert!(generate(&|_| ()).is_ok());
+        assert!(generate(&|spec| spec.jobs[0].name = "a\n* * * * * root /tmp/x".to_string()).is_err());
+        assert!(generate(&|spec| spec.jobs[0].user = "root /tmp/x\n* * * * * root".to_string()).is_err());
+        assert!(generate(&|spec| spec.jobs[0].user = "root /tmp/x".to_string()).is_err());
+        assert!(generate(&|spec| spec.jobs[0].command = "y\r* * * * * root /tmp/x".to_string()).is_err());
+        assert!(generate(&|spec| spec.jobs[0].comment = Some("a\r* * * * * root /tmp/x".to_string())).is_err());
+        assert!(generate(&|spec| spec.jobs[0].schedule = "0 2 * *".to_string()).is_err());
+        assert!(generate(&|spec| spec.jobs[0].schedule = "@sometimes".to_string()).is_err());
+        assert!(generate(&|spec| { spec.environment.insert("MAILTO".to_string(), "x\n* * * * * root /tmp/x".to_string()); }).is_err());
+        assert!(generate(&|spec| { spec.environment.insert("A=B".to_string(), "x".to_string()); }).is_err());
+        assert!(generate(&|spec| { spec.environment.insert("A B".to_string(), "x".to_string()); }).is_err());
+        assert!(generate(&|spec| spec.jobs[0].schedule = "@daily".to_string()).unwrap().ends_with("\n@daily root /usr/bin/backup\n"));
+    }
+}
@@ -0,0 +1,260 @@
+// A small HTTP API, for web UIs and bots to check crontabs without running crontabcheck themselves:
+// POST /check with the crontab as the body (add ?path=... for user crontabs) returns the JSON response of the daemon,
+// GET /metrics the Prometheus metrics of the checks. One request per connection, answered one at a time.
+
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use checker::CheckerOptions;
+use daemon::{check_response, error_response};
+use metrics::Metrics;
+use parser::CrontabParserOptions;
+
+
+// crontabs are small: anything bigger is a mistake, or an abuse
+const MAX_BODY_LENGTH: usize = 1024 * 1024;
+// the request line and the headers together
+const MAX_HEADER_LENGTH: u64 = 16 * 1024;
+// requests are answered one at a time: a client which sends slowly (or stops) must not block the others
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+const JSON: &str = "application/json";
+// the text exposition format of Prometheus
+const METRICS_TEXT: &str = "text/plain; version=0.0.4";
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+fn error(status: u16, message: &str) -> Response {
+    Response { status, content_type: JSON, body: error_response(message) }
+}
+
+// "a%20b+c" -> "a b+c"
+pub fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3)
+            .and_then(|hex| ::std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => { decoded.push(byte); index += 3; },
+            (byte, _) => { decoded.push(byte); index += 1; },
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+// The value of a parameter of a query string ("path=%2Fetc%2Fcrontab&x=1")
+fn query_parameter(query: &str, name: &str) -> Option<String> {
+    query.split('&')
+        .filter_map(|parameter| {
+            let mut parts = parameter.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if key == name => percent_decode(&value.replace('+', " ")),
+                _ => None,
+            }
+        })
+        .next()
+}
+
+// A connection which fails the reads once the whole request took too long, however often the client sends a byte
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "the request took too long"));
+        }
+        self.stream.set_read_timeout(Some(self.deadline - now))?;
+        self.stream.read(buffer)
+    }
+}
+
+fn header_too_large() -> Response {
+    error(431, &format!("request lines and headers larger than {} bytes are not accepted", MAX_HEADER_LENGTH))
+}
+
+// The response to a request
+fn handle_request<T: AsRef<str>>(input: &mut dyn BufRead, metrics: &mut Metrics, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> io::Result<Response> {
+    let mut head = input.take(MAX_HEADER_LENGTH);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    if !request_line.ends_with('\n') && head.limit() == 0 {
+        return Ok(header_too_large());
+    }
+    let parts: Vec<&str> = request_line.split_whitespace().collect();
+    let (method, target) = match parts[..] {
+        [method, target, _] => (method, target),
+        _ => return Ok(error(400, "invalid request line")),
+    };
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if head.read_line(&mut header)? == 0 {
+            return Ok(if head.limit() == 0 { header_too_large() } else { error(400, "incomplete headers") });
+        }
+        if !header.ends_with('\n') && head.limit() == 0 {
+            return Ok(header_too_large());
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut target_parts = target.splitn(2, '?');
+    let (path, query) = (target_parts.next().unwrap_or(""), target_parts.next().unwrap_or(""));
+    match (method, path) {
+        ("GET", "/metrics") => return Ok(Response { status: 200, content_type: METRICS_TEXT, body: metrics.prometheus_text(true) }),
+        ("POST", "/check") => (),
+        (_, "/check") | (_, "/metrics") => return Ok(error(405, "method not allowed (POST crontabs to /check, GET /metrics)")),
+        _ => return Ok(error(404, "not found (POST crontabs to /check, GET /metrics)")),
+    }
+    let length = match content_length {
+        Some(length) if length <= MAX_BODY_LENGTH => length,
+        Some(_) => return Ok(error(413, &format!("crontabs larger than {} bytes are not accepted", MAX_BODY_LENGTH))),
+        None => return Ok(error(411, "the Content-Length header is required")),
+    };
+    let mut body = vec![0; length];
+    head.into_inner().read_exact(&mut body)?;
+    let contents = match String::from_utf8(body) {
+        Ok(contents) => contents,
+        Err(e) => return Ok(error(400, &format!("the crontab is not valid UTF-8: {}", e))),
+    };
+    let crontab_path = query_parameter(query, "path");
+    let (response, diagnostics) = check_response(&contents, crontab_path.as_ref().map(|path| &path[..]), parser_options, checker_options);
+    metrics.record(&diagnostics);
+    Ok(Response { status: 200, content_type: JSON, body: response })
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    }
+}
+
+fn serve_connection<T: AsRef<str>>(stream: TcpStream, metrics: &mut Metrics, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> io::Result<()> {
+    let timeout = Duration::from_secs(REQUEST_TIMEOUT_SECONDS);
+    stream.set_write_timeout(Some(timeout))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(DeadlineStream { stream, deadline: Instant::now() + timeout });
+    let response = handle_request(&mut reader, metrics, parser_options, checker_options)?;
+    write!(writer, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           response.status, reason_phrase(response.status), response.content_type, response.body.len(), response.body)?;
+    writer.flush()
+}
+
+// Listen on an address such as 127.0.0.1:8080. Only returns if it cannot be listened on.
+pub fn run_http_server<T: AsRef<str>>(address: &str, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let mut metrics = Metrics::default();
+    for stream in listener.incoming() {
+        if let Err(e) = stream.and_then(|stream| serve_connection(stream, &mut metrics, parser_options, checker_options)) {
+            eprintln!("{}: {}", address, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+
+    use config::Config;
+    use http::*;
+    use serde_json::{self, Value};
+
+    fn respond(request: &str, metrics: &mut Metrics) -> Response {
+        let config = Config::default();
+        handle_request(&mut Cursor::new(request.as_bytes()), metrics, &CrontabParserOptions::<&str>::default(), &CheckerOptions::test_defaults(&config)).unwrap()
+    }
+
+    fn request(request: &str) -> (u16, Value) {
+        let response = respond(request, &mut Metrics::default());
+        (response.status, serde_json::from_str(&response.body).unwrap())
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let crontab = "0 2 * * * /usr/bin/backup\n";
+        let (status, response) = request(&format!("POST /check?path=%2Fvar%2Fspool%2Fcron%2Fcrontabs%2Falice HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", crontab.len(), crontab));
+        assert_eq!(status, 200);
+        assert_eq!(response["valid"], true);
+
+        let crontab = "0 2 * * mon,wedn root /usr/bin/backup\n";
+        let (status, response) = request(&format!("POST /check HTTP/1.1\r\ncontent-length: {}\r\n\r\n{}", crontab.len(), crontab));
+        assert_eq!(status, 200);
+        assert_eq!(response["valid"], false);
+        assert_eq!(response["diagnostics"][0]["line"], 1);
+
+        assert_eq!(request("GET /check HTTP/1.1\r\n\r\n").0, 405);
+        assert_eq!(request("POST / HTTP/1.1\r\n\r\n").0, 404);
+        assert_eq!(request("POST /check HTTP/1.1\r\n\r\n").0, 411);
+        assert_eq!(request("POST /check HTTP/1.1\r\nContent-Length: 100000000\r\n\r\n").0, 413);
+        assert_eq!(request("hello\r\n").0, 400);
+        let long_header = format!("GET /metrics HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_HEADER_LENGTH as usize));
+        assert_eq!(request(&long_header).0, 431);
+        let many_headers = format!("GET /metrics HTTP/1.1\r\n{}\r\n", "X-Padding: a\r\n".repeat(MAX_HEADER_LENGTH as usize / 10));
+        assert_eq!(request(&many_headers).0, 431);
+        assert_eq!(request(&format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEADER_LENGTH as usize))).0, 431);
+    }
+
+    #[test]
+    fn test_deadline_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        client.write_all(b"GET").unwrap();
+        let mut stream = DeadlineStream { stream, deadline: Instant::now() + Duration::from_secs(5) };
+        let mut buffer = [0; 3];
+        stream.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"GET");
+        // the client sends nothing more
+        stream.deadline = Instant::now() + Duration::from_millis(50);
+        assert!(stream.read(&mut buffer).is_err());
+        client.write_all(b" /").unwrap();
+        assert_eq!(stream.read(&mut buffer).unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut metrics = Metrics::default();
+        let crontab = "0 2 * * * root /usr/bin/backup\n";
+        respond(&format!("POST /check HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", crontab.len(), crontab), &mut metrics);
+        let response = respond("GET /metrics HTTP/1.1\r\n\r\n", &mut metrics);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, METRICS_TEXT);
+        assert!(response.body.contains("\ncrontabcheck_files_checked_total 1\n"));
+        assert_eq!(respond("POST /metrics HTTP/1.1\r\n\r\n", &mut metrics).status, 405);
+    }
+
+    #[test]
+    fn test_query_parameter() {
+        assert_eq!(query_parameter("x=1&path=%2Fetc%2Fcron.d%2Fmy+job", "path"), Some("/etc/cron.d/my job".to_string()));
+        assert_eq!(query_parameter("", "path"), None);
+    }
+
+}
@@ -0,0 +1,192 @@
+// Translations of the messages printed for operators (--lang, or the locale of the environment). The catalog maps
+// English messages, with {} for the values they contain, to their translations; messages which are not in it are
+// printed in English. Rule names and the keys of machine formats are never translated.
+
+use std::env;
+
+use checker::Diagnostic;
+
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Lang {
+    English,
+    French,
+    Spanish,
+}
+
+pub const LANG_NAMES: &[&str] = &["en", "fr", "es"];
+
+// (English, French, Spanish), the values in the same order. Messages starting like others come first.
+const CATALOG: &[(&str, &str, &str)] = &[
+    // how diagnostics are printed
+    ("Invalid line", "Ligne invalide", "Línea no válida"),
+    ("Invalid file", "Fichier invalide", "Archivo no válido"),
+    ("Warning on line {}", "Avertissement à la ligne {}", "Advertencia en la línea {}"),
+    ("Note on line {}", "Remarque à la ligne {}", "Nota en la línea {}"),
+    ("Warning", "Avertissement", "Advertencia"),
+    ("Note", "Remarque", "Nota"),
+    ("{} files checked ({} lines), {} invalid: {} errors, {} warnings (most found: {})",
+     "{} fichiers vérifiés ({} lignes), {} invalides : {} erreurs, {} avertissements (les plus fréquents : {})",
+     "{} archivos comprobados ({} líneas), {} no válidos: {} errores, {} advertencias (más frecuentes: {})"),
+    ("{} files checked ({} lines), {} invalid: {} errors, {} warnings",
+     "{} fichiers vérifiés ({} lignes), {} invalides : {} erreurs, {} avertissements",
+     "{} archivos comprobados ({} líneas), {} no válidos: {} errores, {} advertencias"),
+    // syntax errors
+    ("Caused by: {}", "Cause : {}", "Causa: {}"),
+    ("{} (at '{}')", "{} (à '{}')", "{} (en '{}')"),
+    ("value {} out of bounds (accepted: {} to {})", "valeur {} hors limites (acceptées : {} à {})", "valor {} fuera de rango (aceptados: {} a {})"),
+    ("could not parse the field", "impossible d'analyser le champ", "no se pudo analizar el campo"),
+    ("invalid numeric value", "valeur numérique invalide", "valor numérico no válido"),
+    ("expected a field separator (space or tab)", "séparateur de champs attendu (espace ou tabulation)", "se esperaba un separador de campos (espacio o tabulación)"),
+    ("invalid username", "nom d'utilisateur invalide", "nombre de usuario no válido"),
+    ("unknown user '{}', did you mean '{}'?", "utilisateur '{}' inconnu, vouliez-vous dire '{}' ?", "usuario '{}' desconocido, ¿quiso decir '{}'?"),
+    ("unknown user '{}'", "utilisateur '{}' inconnu", "usuario '{}' desconocido"),
+    ("unterminated quoted value", "valeur entre guillemets non terminée", "valor entre comillas sin cerrar"),
+    ("the line is too long ({} bytes, maximum: {})", "la ligne est trop longue ({} octets, maximum : {})", "la línea es demasiado larga ({} bytes, máximo: {})"),
+    // checks of the files
+    ("invalid UTF-8 at byte offset {} (use --lossy to check the rest of the file anyway)",
+     "UTF-8 invalide à l'octet {} (utilisez --lossy pour vérifier tout de même le reste du fichier)",
+     "UTF-8 no válido en el byte {} (use --lossy para comprobar igualmente el resto del archivo)"),
+    ("the file starts with a UTF-8 byte order mark (BOM): remove it, cron does not expect it",
+     "le fichier commence par une marque d'ordre des octets UTF-8 (BOM) : supprimez-la, cron ne l'attend pas",
+     "el archivo empieza con una marca de orden de bytes UTF-8 (BOM): elimínela, cron no la espera"),
+    ("the file does not end with a newline: cron ignores this last line",
+     "le fichier ne se termine pas par un saut de ligne : cron ignore cette dernière ligne",
+     "el archivo no termina con un salto de línea: cron ignora esta última línea"),
+    ("the file does not contain any job", "le fichier ne contient aucune tâche", "el archivo no contiene ninguna tarea"),
+    ("the file contains {} jobs (maximum: {}): split it into smaller files",
+     "le fichier contient {} tâches (maximum : {}) : découpez-le en fichiers plus petits",
+     "el archivo contiene {} tareas (máximo: {}): divídalo en archivos más pequeños"),
+    ("the line ends with a carriage return (CRLF line ending): cron will pass it to the command",
+     "la ligne se termine par un retour chariot (fin de ligne CRLF) : cron le passera à la commande",
+     "la línea termina con un retorno de carro (fin de línea CRLF): cron lo pasará al comando"),
+    ("unresolved merge conflict marker", "marqueur de conflit de fusion non résolu", "marcador de conflicto de fusión sin resolver"),
+    // checks of the lines
+    ("trailing whitespace at the end of the line", "espaces en fin de ligne", "espacios al final de la línea"),
+    ("against policy: {}", "contraire à la politique : {}", "contrario a la política: {}"),
+    ("command '{}' not found in PATH ({})", "commande '{}' introuvable dans le PATH ({})", "comando '{}' no encontrado en el PATH ({})"),
+    ("command '{}' is not an absolute path: cron resolves it with its own PATH, from the user's home directory",
+     "la commande '{}' n'est pas un chemin absolu : cron la résout avec son propre PATH, depuis le répertoire personnel de l'utilisateur",
+     "el comando '{}' no es una ruta absoluta: cron lo resuelve con su propio PATH, desde el directorio personal del usuario"),
+    ("argument '{}' is a relative path: cron runs jobs from the user's home directory",
+     "l'argument '{}' est un chemin relatif : cron lance les tâches depuis le répertoire personnel de l'utilisateur",
+     "el argumento '{}' es una ruta relativa: cron ejecuta las tareas desde el directorio personal del usuario"),
+    ("the job downloads and executes code: pin a local, reviewed copy of the script instead",
+     "la tâche télécharge et exécute du code : utilisez plutôt une copie locale et relue du script",
+     "la tarea descarga y ejecuta código: use en su lugar una copia local y revisada del script"),
+    ("shell syntax error: {}", "erreur de syntaxe du shell : {}", "error de sintaxis del shell: {}"),
+    // environment variables
+    ("invalid SHELL: {}", "SHELL invalide : {}", "SHELL no válido: {}"),
+    ("unknown time zone '{}' in {}, did you mean '{}'?", "fuseau horaire '{}' inconnu dans {}, vouliez-vous dire '{}' ?", "zona horaria '{}' desconocida en {}, ¿quiso decir '{}'?"),
+    ("unknown time zone '{}' in {}", "fuseau horaire '{}' inconnu dans {}", "zona horaria '{}' desconocida en {}"),
+    ("environment variable {} is not allowed (allowed: {})", "la variable d'environnement {} n'est pas autorisée (autorisées : {})",
+     "la variable de entorno {} no está permitida (permitidas: {})"),
+];
+
+impl Lang {
+    // "fr", "fr_FR.UTF-8", "es_ES"... None for other languages
+    pub fn from_locale(locale: &str) -> Option<Lang> {
+        match locale.split(&['_', '.', '-'][..]).next() {
+            Some("en") | Some("C") | Some("POSIX") => Some(Lang::English),
+            Some("fr") => Some(Lang::French),
+            Some("es") => Some(Lang::Spanish),
+            _ => None,
+        }
+    }
+
+    // --lang, or the first locale variable set, as gettext looks them up; English for other languages
+    pub fn detect(lang: Option<&str>) -> Lang {
+        let locale = lang.map(|lang| lang.to_string()).or_else(|| {
+            ["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|name| env::var(name).ok()).find(|value| !value.is_empty())
+        });
+        locale.and_then(|locale| Lang::from_locale(&locale)).unwrap_or(Lang::English)
+    }
+}
+
+// The values of `message` if it is `template` with {} replaced by them
+fn template_values<'m>(template: &str, message: &'m str) -> Option<Vec<&'m str>> {
+    let parts: Vec<&str> = template.split("{}").collect();
+    if !message.starts_with(parts[0]) {
+        return None;
+    }
+    let mut rest = &message[parts[0].len()..];
+    let mut values = vec![];
+    for (index, part) in parts.iter().enumerate().skip(1) {
+        if index == parts.len() - 1 {
+            if !rest.ends_with(part) {
+                return None;
+            }
+            values.push(&rest[..rest.len() - part.len()]);
+            rest = "";
+        } else {
+            let end = rest.find(part)?;
+            values.push(&rest[..end]);
+            rest = &rest[end + part.len()..];
+        }
+    }
+    if rest.is_empty() { Some(values) } else { None }
+}
+
+fn translate_line(lang: Lang, line: &str) -> String {
+    for &(english, french, spanish) in CATALOG {
+        if let Some(values) = template_values(english, line) {
+            let translation = match lang {
+                Lang::English => english,
+                Lang::French => french,
+                Lang::Spanish => spanish,
+            };
+            // values may be messages themselves ("Caused by: ...")
+            let mut parts = translation.split("{}");
+            let mut translated = parts.next().unwrap_or("").to_string();
+            for (value, part) in values.iter().zip(parts) {
+                translated.push_str(&translate_line(lang, value));
+                translated.push_str(part);
+            }
+            return translated;
+        }
+    }
+    line.to_string()
+}
+
+// `message` in `lang`, line by line
+pub fn translate(lang: Lang, message: &str) -> String {
+    if lang == Lang::English {
+        return message.to_string();
+    }
+    message.split('\n').map(|line| translate_line(lang, line)).collect::<Vec<_>>().join("\n")
+}
+
+// The diagnostics with their messages in `lang`
+pub fn translate_diagnostics(lang: Lang, diagnostics: &[Diagnostic]) -> Vec<Diagnostic> {
+    diagnostics.iter().map(|diagnostic| Diagnostic { message: translate(lang, &diagnostic.message), ..diagnostic.clone() }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use i18n::*;
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(translate(Lang::French, "unknown user 'bob', did you mean 'rob'?"), "utilisateur 'bob' inconnu, vouliez-vous dire 'rob' ?");
+        assert_eq!(translate(Lang::Spanish, "unknown user 'bob'"), "usuario 'bob' desconocido");
+        assert_eq!(translate(Lang::French, "could not parse the field (at '61 * * * * ro')\nCaused by: invalid numeric value (at '61')"),
+                   "impossible d'analyser le champ (à '61 * * * * ro')\nCause : valeur numérique invalide (à '61')");
+        assert_eq!(translate(Lang::Spanish, "Warning on line 3"), "Advertencia en la línea 3");
+        assert_eq!(translate(Lang::French, "2 files checked (15 lines), 1 invalid: 1 errors, 2 warnings (most found: syntax 1)"),
+                   "2 fichiers vérifiés (15 lignes), 1 invalides : 1 erreurs, 2 avertissements (les plus fréquents : syntax 1)");
+        // not in the catalog
+        assert_eq!(translate(Lang::French, "something else"), "something else");
+        assert_eq!(translate(Lang::English, "unknown user 'bob'"), "unknown user 'bob'");
+    }
+
+    #[test]
+    fn test_from_locale() {
+        assert_eq!(Lang::from_locale("fr_FR.UTF-8"), Some(Lang::French));
+        assert_eq!(Lang::from_locale("es"), Some(Lang::Spanish));
+        assert_eq!(Lang::from_locale("C.UTF-8"), Some(Lang::English));
+        assert_eq!(Lang::from_locale("de_DE"), None);
+        assert_eq!(Lang::detect(Some("es_MX")), Lang::Spanish);
+    }
+
+}
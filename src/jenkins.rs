@@ -0,0 +1,72 @@
+// Jenkins build triggers: either the "Build periodically" specification itself (one schedule per line),
+// or a Jenkinsfile, whose `cron('...')` triggers are checked
+
+use std::path::Path;
+
+use checker::{Diagnostic, Severity};
+use environment::check_timezone;
+use parser::check_jenkins_schedule;
+
+
+const SCHEDULE_MACROS: &[&str] = &["@yearly", "@annually", "@monthly", "@weekly", "@daily", "@midnight", "@hourly"];
+
+fn check_spec_line(line: &str, zoneinfo: &Path) -> Result<(), String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        Ok(())
+    } else if let Some(timezone) = line.strip_prefix("TZ=") {
+        check_timezone("TZ", timezone, zoneinfo)
+    } else if line.starts_with('@') {
+        if SCHEDULE_MACROS.contains(&line) { Ok(()) } else { Err(format!("unknown schedule macro '{}'", line)) }
+    } else {
+        check_jenkins_schedule(line)
+    }
+}
+
+// The argument of a `cron('...')` (or `cron("...")`) call
+fn cron_trigger(line: &str) -> Option<&str> {
+    let start = line.find("cron(")? + "cron(".len();
+    let argument = line[start..].trim_start();
+    let quote = argument.chars().next().filter(|&c| c == '\'' || c == '"')?;
+    argument[1..].find(quote).map(|end| &argument[1..end + 1])
+}
+
+pub fn check_triggers(contents: &str, zoneinfo: &Path) -> Vec<Diagnostic> {
+    let jenkinsfile = contents.contains("cron(");
+    let mut diagnostics = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let result = if jenkinsfile {
+            // a specification may hold several lines ("H * * * *\nH/5 * * * *")
+            match cron_trigger(line) {
+                Some(spec) => spec.split("\\n").try_for_each(|spec_line| check_spec_line(spec_line, zoneinfo)),
+                None => continue,
+            }
+        } else {
+            check_spec_line(line, zoneinfo)
+        };
+        if let Err(message) = result {
+            diagnostics.push(Diagnostic { line_number: index + 1, line: line.to_string(), severity: Severity::Error, rule: "jenkins-schedule", message });
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use jenkins::*;
+
+    #[test]
+    fn test_check_triggers() {
+        let zoneinfo = Path::new("/nonexistent");
+        assert!(check_triggers("# nightly\nH H(0-7) * * *\n@midnight\n", zoneinfo).is_empty());
+        let jenkinsfile = "pipeline {\n  triggers {\n    cron('H H(0-7) * * *\\nH 25 * * *')\n  }\n}\n";
+        let diagnostics = check_triggers(jenkinsfile, zoneinfo);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 3);
+        assert_eq!(cron_trigger("cron(\"H/15 * * * *\")"), Some("H/15 * * * *"));
+    }
+
+}
@@ -0,0 +1,108 @@
+// Kubernetes CronJob manifests: the schedule (and time zone) of each CronJob, found line by line in the YAML
+// documents so that diagnostics point to the right line
+
+use std::path::Path;
+
+use checker::{Diagnostic, Severity};
+use environment::check_timezone;
+use parser::check_schedule;
+
+
+// macros accepted by the CronJob controller
+const SCHEDULE_MACROS: &[&str] = &["@yearly", "@annually", "@monthly", "@weekly", "@daily", "@midnight", "@hourly"];
+
+// The value of a `key: value` line, without quotes and trailing comment
+fn yaml_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let line = line.trim_start().trim_start_matches("- ");
+    if !line.starts_with(key) || !line[key.len()..].starts_with(':') {
+        return None;
+    }
+    let value = line[key.len() + 1..].trim();
+    for quote in &['"', '\''] {
+        if value.starts_with(*quote) {
+            return value[1..].find(*quote).map(|end| &value[1..end + 1]);
+        }
+    }
+    Some(value.split(" #").next().unwrap_or("").trim())
+}
+
+fn check_cronjob_schedule(schedule: &str) -> Result<(), String> {
+    if schedule.starts_with("TZ=") || schedule.starts_with("CRON_TZ=") {
+        return Err("time zones in the schedule are not supported: use spec.timeZone".to_string());
+    }
+    if schedule.starts_with('@') {
+        return if SCHEDULE_MACROS.contains(&schedule) { Ok(()) } else { Err(format!("unknown schedule macro '{}'", schedule)) };
+    }
+    check_schedule(schedule).map_err(|e| format!("invalid schedule: {}", e))
+}
+
+// Check the CronJobs of a (possibly multi-document) manifest
+pub fn check_manifest(contents: &str, zoneinfo: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start + 1..lines.len()).find(|&index| lines[index].starts_with("---")).unwrap_or(lines.len());
+        let document = start..end;
+        start = end + 1;
+        let is_cronjob = document.clone().any(|index| yaml_value(lines[index], "kind") == Some("CronJob") && !lines[index].starts_with(' '));
+        if !is_cronjob {
+            continue;
+        }
+        for index in document {
+            let line = lines[index];
+            let result = if let Some(schedule) = yaml_value(line, "schedule") {
+                check_cronjob_schedule(schedule)
+            } else if let Some(zone) = yaml_value(line, "timeZone") {
+                check_timezone("timeZone", zone, zoneinfo)
+            } else {
+                continue;
+            };
+            if let Err(message) = result {
+                diagnostics.push(Diagnostic { line_number: index + 1, line: line.to_string(), severity: Severity::Error, rule: "cronjob-schedule", message });
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use kubernetes::*;
+
+    #[test]
+    fn test_yaml_value() {
+        assert_eq!(yaml_value("  schedule: \"*/5 * * * *\"  # every 5 minutes", "schedule"), Some("*/5 * * * *"));
+        assert_eq!(yaml_value("  schedule: 0 1 * * * # nightly", "schedule"), Some("0 1 * * *"));
+        assert_eq!(yaml_value("  schedules: []", "schedule"), None);
+    }
+
+    #[test]
+    fn test_check_manifest() {
+        let manifest = "\
+apiVersion: v1
+kind: ConfigMap
+data:
+  schedule: not a schedule
+---
+apiVersion: batch/v1
+kind: CronJob
+spec:
+  schedule: '0 25 * * *'
+  jobTemplate: {}
+---
+kind: CronJob
+spec:
+  schedule: \"@daily\"
+";
+        let diagnostics = check_manifest(manifest, Path::new("/nonexistent"));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 9);
+        assert!(check_cronjob_schedule("CRON_TZ=UTC 0 1 * * *").is_err());
+        assert!(check_cronjob_schedule("0 24 * * *").is_err());
+    }
+
+}
@@ -0,0 +1,24 @@
+// The parser of crontabcheck as a library, for the APIs embedding it: a C API (include/crontabcheck.h) for agents
+// written in C or C++, WebAssembly bindings for web UIs (with the wasm feature). The checks which need the system
+// (users, commands, files...) are left to the crontabcheck command.
+
+extern crate nom;
+extern crate nom_locate;
+extern crate chrono;
+extern crate serde;
+#[cfg(feature = "wasm")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "wasm")]
+extern crate serde_json;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+pub mod dialect;
+pub mod ffi;
+pub mod parser;
+pub mod schedule;
+pub mod suggest;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,519 @@
+// Lints: constructs cron accepts but which probably do not do what the author expects.
+// Unless configured otherwise, they are reported as warnings and do not make the check fail.
+
+use command::program_name;
+use parser::ALLOW_ROOT_MARKER;
+
+
+pub fn relative_program_warning(command: &str) -> Option<String> {
+    program_name(command).and_then(|program|
+        if program.starts_with('/') {
+            None
+        } else {
+            Some(format!("command '{}' is not an absolute path: cron resolves it with its own PATH, from the user's home directory", program))
+        }
+    )
+}
+
+fn is_relative_path(word: &str) -> bool {
+    // redirections: `>logs/out.log`, `2>>logs/err.log`
+    let word = if word.contains('>') || word.contains('<') {
+        word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '>' || c == '<' || c == '&')
+    } else {
+        word
+    };
+    word.contains('/')
+        && !word.contains(':')  // URLs, remote paths (host:path)
+        && !word.contains('=')
+        && !word.starts_with(['/', '~', '$', '-', '"', '\''])
+}
+
+pub fn relative_argument_warnings(command: &str) -> Vec<String> {
+    command.split_whitespace()
+        .skip(1)
+        .filter(|word| is_relative_path(word))
+        .map(|word| format!("argument '{}' is a relative path: cron runs jobs from the user's home directory", word))
+        .collect()
+}
+
+// A word of a command line, with its quoted and escaped characters masked (as '_') in `unquoted`, which keeps the
+// byte offsets of `text`: the operators are looked for in `unquoted`
+struct Word {
+    text: String,
+    unquoted: String,
+}
+
+fn mask(word: &mut Word, c: char) {
+    word.text.push(c);
+    word.unquoted.extend((0..c.len_utf8()).map(|_| '_'));
+}
+
+// The words of each command of a command line, split at pipes, ';', '&&', '||' and '&' out of quotes
+fn command_stages(command: &str) -> Vec<Vec<Word>> {
+    let mut stages: Vec<Vec<Word>> = vec![vec![]];
+    let mut word = Word { text: String::new(), unquoted: String::new() };
+    let (mut quote, mut escaped) = (None, false);
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        let (end_word, end_stage) = match (quote, c) {
+            _ if escaped => { escaped = false; mask(&mut word, c); (false, false) },
+            (Some(q), _) if q == c => { quote = None; mask(&mut word, c); (false, false) },
+            (Some(_), _) => { mask(&mut word, c); (false, false) },
+            (None, '\\') => { escaped = true; mask(&mut word, c); (false, false) },
+            (None, '"') | (None, '\'') => { quote = Some(c); mask(&mut word, c); (false, false) },
+            (None, '|') | (None, ';') => (true, true),
+            // not `2>&1` nor `&>file`
+            (None, '&') if !word.unquoted.ends_with('>') && chars.peek() != Some(&'>') => (true, true),
+            (None, c) if c.is_whitespace() => (true, false),
+            (None, c) => { word.text.push(c); word.unquoted.push(c); (false, false) },
+        };
+        if end_word && !word.text.is_empty() {
+            stages.last_mut().unwrap().push(word);
+            word = Word { text: String::new(), unquoted: String::new() };
+        }
+        if end_stage && !stages.last().unwrap().is_empty() {
+            stages.push(vec![]);
+        }
+    }
+    if !word.text.is_empty() {
+        stages.last_mut().unwrap().push(word);
+    }
+    stages
+}
+
+// An output redirection: `>file`, `2>> file`, `&>file`, `2>&1`...
+struct Redirection {
+    // as written: "2>&1", ">>/var/log/x", ">"
+    text: String,
+    // "1" (also when omitted), "2"... or "&" (stdout and stderr)
+    descriptor: String,
+    append: bool,
+    // the file, or the descriptor after '&' ("1" in `2>&1`)
+    target: String,
+    // `2>&1`: to another descriptor rather than a file
+    duplicate: bool,
+}
+
+impl Redirection {
+    // where cron would not read the output of the job anymore
+    fn redirects_stdout_to_file(&self) -> bool {
+        (self.descriptor == "1" || self.descriptor == "&") && !self.duplicate
+    }
+}
+
+// The output redirections of the words of a command
+fn redirections(words: &[Word]) -> Vec<Redirection> {
+    let mut redirections = vec![];
+    for (index, word) in words.iter().enumerate() {
+        let operator = match word.unquoted.find('>') {
+            Some(operator) => operator,
+            None => continue,
+        };
+        // `2>`, `&>`, or a word followed by `>` (`echo done>file`)
+        let prefix = &word.unquoted[..operator];
+        let (start, descriptor) = if prefix == "&" || (!prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_digit())) {
+            (0, prefix.to_string())
+        } else {
+            (operator, "1".to_string())
+        };
+        let append = word.unquoted[operator + 1..].starts_with('>');
+        let rest = operator + if append { 2 } else { 1 };
+        let duplicate = word.unquoted[rest..].starts_with('&');
+        let target = match &word.text[rest + if duplicate { 1 } else { 0 }..] {
+            // `> file`
+            "" => words.get(index + 1).map(|next| next.text.clone()).unwrap_or_default(),
+            target => target.to_string(),
+        };
+        redirections.push(Redirection { text: word.text[start..].to_string(), descriptor, append, target, duplicate });
+    }
+    redirections
+}
+
+// Whether the output of the command is redirected somewhere (a file or the system log),
+// instead of being sent by mail by cron
+pub fn has_output_redirection(command: &str) -> bool {
+    let stages = command_stages(command);
+    stages.iter().any(|words| redirections(words).iter().any(Redirection::redirects_stdout_to_file))
+        || stages.iter().skip(1).any(|words| {
+            let program = words.first().map(|word| &word.text[..]).unwrap_or("");
+            program.ends_with("logger") || program.ends_with("systemd-cat")
+        })
+}
+
+fn basename(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+const DOWNLOADERS: &[&str] = &["curl", "wget", "fetch"];
+const INTERPRETERS: &[&str] = &["sh", "bash", "dash", "zsh", "ksh", "python", "python3", "perl", "ruby"];
+
+// Code downloaded and executed straight away: `curl ... | sh`, `bash <(wget -O- ...)`
+pub fn pipe_to_shell_warning(command: &str) -> Option<String> {
+    let stages: Vec<&str> = command.split("||").flat_map(|part| part.split('|')).collect();
+    let piped = stages.windows(2).any(|pair| {
+        let from = program_name(pair[0]).map(basename).unwrap_or("");
+        let to = program_name(pair[1].trim_start_matches(|c: char| c == '&' || c.is_whitespace()))
+            .map(basename)
+            .unwrap_or("");
+        DOWNLOADERS.contains(&from) && INTERPRETERS.contains(&to)
+    });
+    let substituted = DOWNLOADERS.iter().any(|downloader| command.contains(&format!("<({}", downloader)[..]));
+    if piped || substituted {
+        Some("the job downloads and executes code: pin a local, reviewed copy of the script instead".to_string())
+    } else {
+        None
+    }
+}
+
+// Downloads over plain HTTP (`curl http://...`), which anyone on the network path can tamper with. Local URLs, such
+// as health checks of services on the same host, are fine.
+pub fn plain_http_warning(command: &str) -> Option<String> {
+    command.split(['|', ';', '&', '(', '`'])
+        .filter(|stage| program_name(stage).map(basename).map(|program| DOWNLOADERS.contains(&program)).unwrap_or(false))
+        .flat_map(|stage| stage.split_whitespace())
+        .map(|word| word.trim_matches(['"', '\'']))
+        .find(|word| {
+            let host = match word.get(..7) {
+                Some(scheme) if scheme.eq_ignore_ascii_case("http://") => word[7..].split(['/', ':']).next().unwrap_or(""),
+                _ => return false,
+            };
+            host != "localhost" && host != "127.0.0.1" && !word[7..].starts_with("[::1]")
+        })
+        .map(|url| format!("the job downloads {} over plain HTTP, which can be tampered with on the way: \
+                            use HTTPS, or check the checksum of what is downloaded", url))
+}
+
+// Variables set in every job's environment by cron or by the shell itself
+const CRON_ENVIRONMENT: &[&str] = &[
+    "HOME", "LOGNAME", "USER", "SHELL", "PATH", "PWD", "OLDPWD", "IFS", "PPID", "RANDOM", "LINENO", "OPTARG", "OPTIND",
+];
+
+// Names of the variables expanded by the shell in a command line (`$NAME`, `${NAME}`, `${NAME:-default}`),
+// except in single quotes and escaped dollar signs (a single quote in double quotes is a plain character)
+fn referenced_variables(command: &str) -> Vec<&str> {
+    let mut variables = vec![];
+    let mut in_single_quotes = false;
+    let mut in_double_quotes = false;
+    let mut escaped = false;
+    for (index, c) in command.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if !in_single_quotes => escaped = true,
+            '\'' if !in_double_quotes => in_single_quotes = !in_single_quotes,
+            '"' if !in_single_quotes => in_double_quotes = !in_double_quotes,
+            '$' if !in_single_quotes => {
+                let rest = &command[index + 1..];
+                let rest = rest.strip_prefix('{').unwrap_or(rest);
+                let length = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                let name = &rest[..length];
+                // skip positional and special parameters ($1, $?...), command substitutions...
+                if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                    variables.push(name);
+                }
+            },
+            _ => (),
+        }
+    }
+    variables
+}
+
+fn is_assigned_in_command(command: &str, name: &str) -> bool {
+    let words: Vec<&str> = command.split(|c: char| c.is_whitespace() || c == ';' || c == '&' || c == '|' || c == '(').collect();
+    words.windows(2).any(|pair| (pair[0] == "for" || pair[0] == "read" || pair[0] == "export") && pair[1] == name)
+        || words.iter().any(|word| word.starts_with(name) && word[name.len()..].starts_with('='))
+}
+
+// Variables the shell will expand to an empty string, because neither cron nor the crontab sets them
+pub fn unset_variable_warnings<T: AsRef<str>>(command: &str, crontab_variables: &[T]) -> Vec<String> {
+    let mut warnings: Vec<String> = vec![];
+    for name in referenced_variables(command) {
+        let defined = CRON_ENVIRONMENT.contains(&name)
+            || crontab_variables.iter().any(|variable| variable.as_ref() == name)
+            || is_assigned_in_command(command, name);
+        let warning = format!("'${}' is not set in cron's environment (only HOME, LOGNAME, SHELL, PATH and the variables \
+                               assigned in the crontab are): the shell will expand it to an empty string", name);
+        if !defined && !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+    warnings
+}
+
+// cron uses environment assignments verbatim: `PATH=$PATH:/opt/bin` is not expanded
+pub fn environment_expansion_warning(name: &str, value: &str) -> Option<String> {
+    if value.contains('$') {
+        Some(format!("cron does not expand variables in environment assignments: {} will literally be set to '{}'", name, value))
+    } else {
+        None
+    }
+}
+
+// `cmd 2>&1 >file` sends stderr to where stdout pointed *before* the file redirection
+// (the redirections of each command of a pipeline apply to that command only: `cmd 2>&1 | tee log >/dev/null` is fine)
+pub fn redirection_order_warning(command: &str) -> Option<String> {
+    command_stages(command).iter().filter_map(|words| {
+        let redirections = redirections(words);
+        let duplication = redirections.iter().position(|redirection| {
+            redirection.descriptor == "2" && redirection.duplicate && redirection.target == "1"
+        })?;
+        let redirection = redirections[duplication + 1..].iter().find(|redirection| {
+            redirection.descriptor == "1" && !redirection.duplicate
+        })?;
+        let operator = if redirection.append { ">>" } else { ">" };
+        Some(format!("'2>&1' before '{}' still sends stderr to the original stdout (usually cron's mail): \
+                      write '{} {} 2>&1' instead", redirection.text, operator, redirection.target))
+    }).next()
+}
+
+pub fn line_continuation_warning(command: &str) -> Option<String> {
+    let trailing_backslashes = command.trim_end().chars().rev().take_while(|&c| c == '\\').count();
+    // an even number of backslashes is a sequence of escaped backslashes
+    if trailing_backslashes % 2 == 1 {
+        Some("the command ends with a backslash, but cron does not support line continuations: \
+              the next line is parsed as a new crontab entry".to_string())
+    } else {
+        None
+    }
+}
+
+// "cmd # cleanup": cron has no comments at the end of job lines, the '#' (out of quotes, starting a word) and what
+// follows are part of the command. Only the shell ignores them.
+pub fn trailing_comment_warning(command: &str) -> Option<String> {
+    let (mut quote, mut escaped, mut previous) = (None, false, ' ');
+    for (index, c) in command.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => (),
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '#') if previous.is_whitespace() => {
+                let comment = command[index..].trim_end();
+                if comment.trim_start_matches('#').trim() == ALLOW_ROOT_MARKER {
+                    return None;
+                }
+                return Some(format!("'{}' is not a comment for cron: it is passed to the shell as part of the command \
+                                     (put comments on their own line)", comment));
+            },
+            _ => (),
+        }
+        previous = c;
+    }
+    None
+}
+
+// Style: whitespace is invisible in review and can hide characters
+pub fn trailing_whitespace_warning(line: &str) -> Option<String> {
+    if line.trim_end().len() < line.len() {
+        Some("trailing whitespace at the end of the line".to_string())
+    } else {
+        None
+    }
+}
+
+// Style: cron skips the whitespace before the fields of jobs, other tools reading crontabs may not
+pub fn indentation_warning(line: &str) -> Option<String> {
+    if line.starts_with([' ', '\t']) {
+        Some("the job is indented: remove the whitespace before its minute field".to_string())
+    } else {
+        None
+    }
+}
+
+// Characters which render as nothing, or reorder the text around them (as in "Trojan Source" attacks)
+fn hidden_character_description(c: char) -> Option<&'static str> {
+    match c {
+        '\u{200b}'..='\u{200d}' | '\u{2060}'..='\u{2064}' | '\u{feff}' | '\u{00ad}' | '\u{180e}' => Some("an invisible character"),
+        '\u{200e}' | '\u{200f}' | '\u{061c}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => Some("a bidirectional control character"),
+        _ => None,
+    }
+}
+
+// Letters which look like Latin ones
+fn lookalike_script(c: char) -> Option<&'static str> {
+    match c {
+        '\u{0370}'..='\u{03ff}' => Some("Greek"),
+        '\u{0400}'..='\u{04ff}' => Some("Cyrillic"),
+        '\u{ff01}'..='\u{ff5e}' => Some("fullwidth"),
+        _ => None,
+    }
+}
+
+// Characters which can make a command look different in review from what runs: invisible and bidirectional control
+// characters, and Greek or Cyrillic letters among Latin ones ("/usr/bin/b\u{0430}ckup")
+pub fn hidden_character_warnings(command: &str) -> Vec<String> {
+    let mut warnings = vec![];
+    for (index, c) in command.chars().enumerate() {
+        if let Some(description) = hidden_character_description(c) {
+            warnings.push(format!("the command contains {} (U+{:04X}, character {}), which can hide what it runs", description, c as u32, index + 1));
+        }
+    }
+    for word in command.split_whitespace().filter(|word| word.chars().any(|c| c.is_ascii_alphabetic())) {
+        if let Some((c, script)) = word.chars().filter_map(|c| lookalike_script(c).map(|script| (c, script))).next() {
+            warnings.push(format!("'{}' mixes Latin letters with {} ones (U+{:04X}), which look alike: the command may not run what it seems to",
+                                  word, script, c as u32));
+        }
+    }
+    warnings
+}
+
+// Whitespace separating the fields of job lines
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FieldSeparator {
+    Spaces,
+    Tabs,
+    Mixed,
+}
+
+impl FieldSeparator {
+    pub fn description(&self) -> &'static str {
+        match *self {
+            FieldSeparator::Spaces => "spaces",
+            FieldSeparator::Tabs => "tabs",
+            FieldSeparator::Mixed => "both tabs and spaces",
+        }
+    }
+}
+
+// The separator used between the fields (schedule, user) which come before the command
+pub fn field_separator(fields: &str) -> Option<FieldSeparator> {
+    let fields = fields.trim_start();
+    match (fields.contains(' '), fields.contains('\t')) {
+        (true, false) => Some(FieldSeparator::Spaces),
+        (false, true) => Some(FieldSeparator::Tabs),
+        (true, true) => Some(FieldSeparator::Mixed),
+        (false, false) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use lints::*;
+
+    #[test]
+    fn test_relative_program_warning() {
+        assert_eq!(relative_program_warning("/usr/local/bin/backup.sh --full"), None);
+        assert!(relative_program_warning("backup.sh --full").is_some());
+        assert!(relative_program_warning("scripts/backup.sh").is_some());
+        assert_eq!(relative_program_warning("cd /srv && make"), None);
+    }
+
+    #[test]
+    fn test_line_continuation_warning() {
+        assert!(line_continuation_warning("/usr/bin/find /tmp -mtime +7 \\").is_some());
+        assert!(line_continuation_warning("/bin/echo done \\ ").is_some());
+        assert_eq!(line_continuation_warning("/bin/echo \\\\"), None);
+        assert_eq!(line_continuation_warning("/bin/echo a\\ b"), None);
+    }
+
+    #[test]
+    fn test_hidden_character_warnings() {
+        assert_eq!(hidden_character_warnings("/usr/bin/backup\u{200b} --full"),
+                   vec!["the command contains an invisible character (U+200B, character 16), which can hide what it runs"]);
+        assert_eq!(hidden_character_warnings("/bin/echo \u{202e}txt.exe").len(), 1);
+        assert_eq!(hidden_character_warnings("/usr/bin/b\u{0430}ckup"),
+                   vec!["'/usr/bin/b\u{0430}ckup' mixes Latin letters with Cyrillic ones (U+0430), which look alike: the command may not run what it seems to"]);
+        assert!(hidden_character_warnings("/usr/bin/notify '\u{043f}\u{0440}\u{0438}\u{0432}\u{0435}\u{0442}' caf\u{e9}").is_empty());
+    }
+
+    #[test]
+    fn test_trailing_comment_warning() {
+        assert_eq!(trailing_comment_warning("/usr/bin/find /tmp -delete  # cleanup"),
+                   Some("'# cleanup' is not a comment for cron: it is passed to the shell as part of the command \
+                         (put comments on their own line)".to_string()));
+        assert!(trailing_comment_warning("/usr/bin/backup # crontabcheck: allow-root").is_none());
+        assert!(trailing_comment_warning("/bin/echo '# not a comment' \"#neither\" \\# nor this issue#12").is_none());
+    }
+
+    #[test]
+    fn test_whitespace_style() {
+        assert!(trailing_whitespace_warning("* * * * * root /bin/true \t").is_some());
+        assert_eq!(trailing_whitespace_warning("* * * * * root /bin/true"), None);
+        assert!(indentation_warning("\t* * * * * root /bin/true").is_some());
+        assert_eq!(indentation_warning("* * * * * root /bin/true"), None);
+        assert_eq!(field_separator("0 5 * * * root "), Some(FieldSeparator::Spaces));
+        assert_eq!(field_separator("  @daily\troot\t"), Some(FieldSeparator::Tabs));
+        assert_eq!(field_separator("0 5\t* * *\troot "), Some(FieldSeparator::Mixed));
+    }
+
+    #[test]
+    fn test_relative_argument_warnings() {
+        assert!(relative_argument_warnings("/usr/bin/rsync -a /srv/ backup:/srv/ --log-file=/var/log/x").is_empty());
+        assert!(relative_argument_warnings("/usr/bin/curl -s https://example.com/ping").is_empty());
+        assert_eq!(relative_argument_warnings("/bin/sh scripts/run.sh >logs/out.log 2>&1").len(), 2);
+    }
+
+    #[test]
+    fn test_redirection_order_warning() {
+        assert_eq!(redirection_order_warning("/usr/bin/backup > /var/log/x 2>&1"), None);
+        assert_eq!(redirection_order_warning("/usr/bin/backup 2>&1 | logger"), None);
+        assert_eq!(
+            redirection_order_warning("/usr/bin/backup 2>&1 > /var/log/x"),
+            Some("'2>&1' before '>' still sends stderr to the original stdout (usually cron's mail): write '> /var/log/x 2>&1' instead".to_string())
+        );
+        assert!(redirection_order_warning("/usr/bin/backup 2>&1 >>/var/log/x").unwrap().ends_with("write '>> /var/log/x 2>&1' instead"));
+        assert_eq!(redirection_order_warning("/usr/bin/backup 2>&1 | tee /var/log/x > /dev/null"), None);
+        assert_eq!(redirection_order_warning("/usr/bin/backup 2>&1; /bin/date > /var/log/x"), None);
+        assert!(redirection_order_warning("/usr/bin/backup | gzip 2>&1 1>/srv/x.gz").is_some());
+    }
+
+    #[test]
+    fn test_plain_http_warning() {
+        assert_eq!(plain_http_warning("/usr/bin/curl -sO http://example.com/data.tar.gz && tar xf data.tar.gz"),
+                   Some("the job downloads http://example.com/data.tar.gz over plain HTTP, which can be tampered with on the way: \
+                         use HTTPS, or check the checksum of what is downloaded".to_string()));
+        assert!(plain_http_warning("cd /srv; wget -q 'HTTP://example.com/x'").is_some());
+        assert!(plain_http_warning("/usr/bin/curl -s https://example.com/x").is_none());
+        assert!(plain_http_warning("/usr/bin/curl -s http://localhost:8080/health").is_none());
+        assert!(plain_http_warning("/usr/bin/notify http://example.com/x").is_none());
+    }
+
+    #[test]
+    fn test_pipe_to_shell_warning() {
+        assert!(pipe_to_shell_warning("/usr/bin/curl -fsSL https://example.com/install.sh | sh").is_some());
+        assert!(pipe_to_shell_warning("wget -qO- https://example.com/x | /bin/bash -s -- --yes").is_some());
+        assert!(pipe_to_shell_warning("/bin/bash <(curl -s https://example.com/x)").is_some());
+        assert!(pipe_to_shell_warning("curl -s https://example.com/health || echo down").is_none());
+        assert!(pipe_to_shell_warning("curl -s https://example.com/data | gzip > /tmp/data.gz").is_none());
+    }
+
+    #[test]
+    fn test_unset_variable_warnings() {
+        let no_variables: &[&str] = &[];
+        assert!(unset_variable_warnings("/usr/bin/backup $HOME/data ${LOGNAME}", no_variables).is_empty());
+        assert!(unset_variable_warnings("/usr/bin/backup '$HOSTNAME' \\$HOSTNAME $1 $(date)", no_variables).is_empty());
+        assert!(unset_variable_warnings("for f in /srv/*; do /usr/bin/backup $f; done", no_variables).is_empty());
+        assert!(unset_variable_warnings("DEST=/srv /usr/bin/backup $DEST", no_variables).is_empty());
+        assert!(unset_variable_warnings("/usr/bin/backup $DEST", &["DEST"]).is_empty());
+        assert_eq!(unset_variable_warnings("/bin/echo $HOSTNAME ${HOSTNAME:-x} \"$DEST\"", no_variables).len(), 2);
+        assert_eq!(referenced_variables("/bin/echo \"it's $HOSTNAME\" '$DEST' \"$DEST\""), vec!["HOSTNAME", "DEST"]);
+    }
+
+    #[test]
+    fn test_environment_expansion_warning() {
+        assert_eq!(environment_expansion_warning("PATH", "/usr/bin:/bin"), None);
+        assert!(environment_expansion_warning("PATH", "$PATH:/opt/bin").is_some());
+    }
+
+    #[test]
+    fn test_has_output_redirection() {
+        assert!(has_output_redirection("/usr/bin/backup > /var/log/backup.log 2>&1"));
+        assert!(has_output_redirection("/usr/bin/backup 2>&1 | /usr/bin/logger -t backup"));
+        assert!(!has_output_redirection("/usr/bin/backup | gzip"));
+        assert!(!has_output_redirection("/usr/bin/backup"));
+        assert!(has_output_redirection("/usr/bin/backup &>/var/log/backup.log"));
+        assert!(has_output_redirection("[ -x /usr/bin/backup ] && /usr/bin/backup 1>>/var/log/backup.log"));
+        assert!(has_output_redirection("/bin/echo done>/tmp/done"));
+        // stderr only, or '>' as an argument
+        assert!(!has_output_redirection("/usr/bin/backup 2>/dev/null"));
+        assert!(!has_output_redirection("/usr/bin/backup 2>&1"));
+        assert!(!has_output_redirection("/usr/bin/backup >&2"));
+        assert!(!has_output_redirection("/bin/echo 'a > b' \\> \"c>d\""));
+    }
+
+}
@@ -0,0 +1,341 @@
+// Language Server Protocol over stdio: editors send the crontabs being edited, and get their diagnostics back
+// as the user types (see https://microsoft.github.io/language-server-protocol/)
+
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use serde_json::{self, Value};
+
+use checker::{CheckerOptions, Diagnostic, ParseCache, Severity, check_crontab_cached};
+use codes::rule_code;
+use files::user_crontab_owner;
+use http::percent_decode;
+use parser::{CrontabParserOptions, error_offset};
+
+
+// JSON-RPC error code
+const METHOD_NOT_FOUND: i64 = -32601;
+// the client sends the changed ranges of the document
+const INCREMENTAL_TEXT_DOCUMENT_SYNC: u8 = 2;
+
+#[derive(Serialize, PartialEq)]
+struct Position {
+    line: usize,
+    // in UTF-16 code units
+    character: usize,
+}
+
+#[derive(Serialize, PartialEq)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+#[derive(Serialize, PartialEq)]
+struct LspDiagnostic {
+    range: Range,
+    severity: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    source: &'static str,
+    message: String,
+}
+
+// An open document: only its new and modified lines are parsed after a change
+#[derive(Default)]
+struct Document {
+    text: String,
+    cache: ParseCache,
+    // the diagnostics the editor shows
+    published: Option<Vec<LspDiagnostic>>,
+}
+
+// A message, or None at the end of the input
+fn read_message(input: &mut dyn BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "message without a valid Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut dyn Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+// "file:///etc/cron.d/my%20job" -> "/etc/cron.d/my job"
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    if !uri.starts_with("file://") {
+        return None;
+    }
+    percent_decode(&uri["file://".len()..]).map(PathBuf::from)
+}
+
+// Byte offset in the text of a position from the client
+fn byte_offset(text: &str, position: &Value) -> Option<usize> {
+    let line_index = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+    let mut start = 0;
+    for _ in 0..line_index {
+        start += text[start..].find('\n')? + 1;
+    }
+    let line = text[start..].split('\n').next().unwrap_or("");
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character {
+            return Some(start + offset);
+        }
+        units += c.len_utf16();
+    }
+    Some(start + line.len())
+}
+
+// Apply a change to a document: a range replaced with some text, or the whole text
+fn apply_change(text: &mut String, change: &Value) {
+    let new_text = change["text"].as_str().unwrap_or("");
+    let range = &change["range"];
+    if range.is_null() {
+        *text = new_text.to_string();
+    } else if let (Some(start), Some(end)) = (byte_offset(text, &range["start"]), byte_offset(text, &range["end"])) {
+        if start <= end {
+            text.replace_range(start..end, new_text);
+        }
+    }
+}
+
+fn position(line: &str, line_index: usize, offset: usize) -> Position {
+    Position { line: line_index, character: line[..offset].encode_utf16().count() }
+}
+
+// The range of a diagnostic: its syntax error when the parser knows where it is, else the whole line
+fn diagnostic_range<T: AsRef<str>>(diagnostic: &Diagnostic, lines: &[&str], parser_options: &CrontabParserOptions<T>) -> Range {
+    if diagnostic.line_number == 0 {
+        return Range { start: Position { line: 0, character: 0 }, end: Position { line: 0, character: 0 } };
+    }
+    let line_index = diagnostic.line_number - 1;
+    let line = lines.get(line_index).map(|line| line.trim_end_matches('\r')).unwrap_or("");
+    let (start, end) = match error_offset(line, parser_options) {
+        Some(offset) => match line[offset..].find(char::is_whitespace) {
+            Some(length) if length > 0 => (offset, offset + length),
+            _ => (offset, line.len()),
+        },
+        None => (line.len() - line.trim_start().len(), line.len()),
+    };
+    Range { start: position(line, line_index, start), end: position(line, line_index, end) }
+}
+
+fn document_diagnostics<T: AsRef<str>>(uri: &str, document: &mut Document, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> Vec<LspDiagnostic> {
+    let owner = uri_path(uri).and_then(|path| user_crontab_owner(&path, checker_options.sysroot));
+    let parser_options = CrontabParserOptions { crontab_owner: owner.as_ref().map(|owner| &owner[..]), ..*parser_options };
+    document.cache.update(&document.text, &parser_options);
+    let text = &document.text;
+    let lines: Vec<&str> = text.split('\n').collect();
+    check_crontab_cached(text, &parser_options, checker_options, &document.cache).iter().map(|diagnostic| LspDiagnostic {
+        range: diagnostic_range(diagnostic, &lines, &parser_options),
+        severity: match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+            Severity::Info => 3,
+        },
+        code: rule_code(diagnostic.rule),
+        source: "crontabcheck",
+        message: diagnostic.message.clone()
+    }).collect()
+}
+
+fn publish_diagnostics(output: &mut dyn Write, uri: &str, diagnostics: &[LspDiagnostic]) -> io::Result<()> {
+    write_message(output, &json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics }
+    }))
+}
+
+// Check the document, and publish its diagnostics if they changed
+fn update_diagnostics<T: AsRef<str>>(output: &mut dyn Write, uri: &str, document: &mut Document, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> io::Result<()> {
+    let diagnostics = document_diagnostics(uri, document, parser_options, checker_options);
+    if document.published.as_ref() != Some(&diagnostics) {
+        publish_diagnostics(output, uri, &diagnostics)?;
+        document.published = Some(diagnostics);
+    }
+    Ok(())
+}
+
+fn respond(output: &mut dyn Write, id: &Value, result: Value) -> io::Result<()> {
+    write_message(output, &json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+// Answer the client until it asks to exit. Returns whether it asked to shut down first, as it should.
+pub fn serve_lsp<T: AsRef<str>>(input: &mut dyn BufRead, output: &mut dyn Write, parser_options: &CrontabParserOptions<T>, checker_options: &CheckerOptions) -> io::Result<bool> {
+    let mut shut_down = false;
+    let mut documents: HashMap<String, Document> = HashMap::new();
+    while let Some(message) = read_message(input)? {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+        match method {
+            "initialize" => respond(output, &message["id"], json!({
+                "capabilities": { "textDocumentSync": INCREMENTAL_TEXT_DOCUMENT_SYNC },
+                "serverInfo": { "name": "crontabcheck" }
+            }))?,
+            "shutdown" => {
+                shut_down = true;
+                respond(output, &message["id"], Value::Null)?;
+            },
+            "exit" => return Ok(shut_down),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                let document = documents.entry(uri.to_string()).or_default();
+                document.text = text.to_string();
+                update_diagnostics(output, uri, document, parser_options, checker_options)?;
+            },
+            "textDocument/didChange" => {
+                if let Some(document) = documents.get_mut(uri) {
+                    // the changes apply one after the other
+                    for change in params["contentChanges"].as_array().map(|changes| &changes[..]).unwrap_or(&[]) {
+                        apply_change(&mut document.text, change);
+                    }
+                    update_diagnostics(output, uri, document, parser_options, checker_options)?;
+                }
+            },
+            // the editor no longer shows the diagnostics of closed files
+            "textDocument/didClose" => {
+                documents.remove(uri);
+                publish_diagnostics(output, uri, &[])?;
+            },
+            // other notifications (initialized, didSave...) and responses to the server need no answer
+            _ if message.get("id").is_none() || message.get("method").is_none() => (),
+            _ => write_message(output, &json!({
+                "jsonrpc": "2.0",
+                "id": message["id"],
+                "error": { "code": METHOD_NOT_FOUND, "message": format!("unsupported method '{}'", method) }
+            }))?,
+        }
+    }
+    Ok(shut_down)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+
+    use config::Config;
+    use lsp::*;
+
+    fn frame(message: Value) -> String {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    // the messages written by the server
+    fn responses(output: &[u8]) -> Vec<Value> {
+        let mut input = Cursor::new(output);
+        let mut messages = vec![];
+        while let Some(message) = read_message(&mut input).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[test]
+    fn test_serve() {
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": "file:///etc/cron.d/backup", "languageId": "crontab", "version": 1,
+                                          "text": "MAILTO=ops@example.com\n0 2 * * wedn root /usr/bin/backup\n" } }
+        });
+        let input = [
+            frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })),
+            frame(open),
+            frame(json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" })),
+            frame(json!({ "jsonrpc": "2.0", "method": "exit" })),
+        ].concat();
+        let config = Config::default();
+        let mut output = vec![];
+        let shut_down = serve_lsp(&mut Cursor::new(input.as_bytes()), &mut output, &CrontabParserOptions::<&str>::default(), &CheckerOptions::test_defaults(&config));
+        assert!(shut_down.unwrap());
+
+        let messages = responses(&output);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["id"], 1);
+        assert_eq!(messages[0]["result"]["capabilities"]["textDocumentSync"], 2);
+        assert_eq!(messages[1]["method"], "textDocument/publishDiagnostics");
+        let diagnostics = messages[1]["params"]["diagnostics"].as_array().unwrap();
+        let diagnostic = diagnostics.iter().find(|diagnostic| diagnostic["severity"] == 1).unwrap();
+        assert_eq!(diagnostic["range"]["start"], json!({ "line": 1, "character": 8 }));
+        assert_eq!(diagnostic["range"]["end"], json!({ "line": 1, "character": 12 }));
+        assert!(diagnostic["message"].as_str().unwrap().contains("did you mean 'wed'?"));
+        assert_eq!(messages[2]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(messages[3]["id"], 3);
+    }
+
+    #[test]
+    fn test_incremental_changes() {
+        let uri = "file:///etc/cron.d/backup";
+        let change = |line, start, end, text| frame(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": { "textDocument": { "uri": uri, "version": 2 }, "contentChanges": [
+                { "range": { "start": { "line": line, "character": start }, "end": { "line": line, "character": end } }, "text": text }
+            ] }
+        }));
+        let input = [
+            frame(json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+                          "params": { "textDocument": { "uri": uri, "text": "MAILTO=ops@example.com\n0 2 * * wedn root /usr/bin/backup\n" } } })),
+            // "wedn" -> "wed"
+            change(1, 11, 12, ""),
+            // a change which does not change the diagnostics is not published
+            change(1, 25, 25, " "),
+            frame(json!({ "jsonrpc": "2.0", "method": "exit" })),
+        ].concat();
+        let config = Config::default();
+        let mut output = vec![];
+        serve_lsp(&mut Cursor::new(input.as_bytes()), &mut output, &CrontabParserOptions::<&str>::default(), &CheckerOptions::test_defaults(&config)).unwrap();
+
+        let messages = responses(&output);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["params"]["diagnostics"][0]["severity"], 1);
+        assert_eq!(messages[1]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_apply_change() {
+        let mut text = "# café\n0 2 * * * root /bin/true\n".to_string();
+        let range = |start: (u64, u64), end: (u64, u64)| json!({ "start": { "line": start.0, "character": start.1 }, "end": { "line": end.0, "character": end.1 } });
+        apply_change(&mut text, &json!({ "range": range((0, 6), (1, 1)), "text": "\n5" }));
+        assert_eq!(text, "# café\n5 2 * * * root /bin/true\n");
+        apply_change(&mut text, &json!({ "text": "" }));
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn test_uri_path() {
+        assert_eq!(uri_path("file:///etc/cron.d/my%20job"), Some(PathBuf::from("/etc/cron.d/my job")));
+        assert_eq!(uri_path("untitled:Untitled-1"), None);
+    }
+
+}
@@ -1,36 +1,542 @@
-#[macro_use]
 extern crate nom;
+extern crate nom_locate;
+#[macro_use]
 extern crate clap;
+extern crate chrono;
+extern crate flate2;
+extern crate memmap2;
+extern crate rayon;
+extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate tar;
+extern crate toml;
 
+mod archive;
+mod ast;
+mod audit;
+mod checker;
+mod codes;
+mod command;
+mod config;
+mod convert;
+mod daemon;
+mod dialect;
+mod diff;
+mod edit;
+mod environment;
+mod files;
+mod format;
+mod generate;
+mod http;
+mod i18n;
+mod jenkins;
+mod kubernetes;
+mod lints;
+mod lsp;
+mod metrics;
 mod parser;
+mod policy;
+mod progress;
+mod quartz;
+mod repl;
+mod report;
+mod schedule;
+mod secrets;
+mod suggest;
+mod summary;
+mod syslog;
+mod systemd;
 
-use std::fs::File;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
 use std::io;
-use std::io::{BufReader, BufRead};
+use std::io::{BufReader, BufRead, Read};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::slice;
 
-use clap::{Arg, App, ArgMatches};
-use nom::IResult::{Error, Done, Incomplete};
+use archive::crontabs_in_archive;
+use ast::{ast_json, ast_text};
+use chrono::Local;
+use clap::{Arg, App, ArgMatches, ErrorKind, SubCommand};
+use checker::{CheckerOptions, CrontabChecker, Diagnostic, Severity, check_crontab_bytes};
+use codes::{explain_code, rule_code};
+use config::{Config, load_config};
+use audit::{Auditor, Finding, Risk, audit_json, audit_text, sort_findings};
+use convert::{to_crontab_lines, to_systemd_units};
+use daemon::run_daemon;
+use dialect::{DIALECT_NAMES, Dialect};
+use diff::diff_crontabs;
+use edit::edit_crontab;
+use environment::{ZONEINFO_DIRECTORY, shells_from_etc_shells};
+use files::{CrontabFile, check_permissions, in_root, install_crontab, read_file, system_crontabs, uid0_usernames, user_crontab_owner,
+            write_atomically};
+use format::{fix_crontab, format_crontab};
+use generate::{generate_crontab, parse_spec};
+use http::run_http_server;
+use i18n::{LANG_NAMES, Lang, translate, translate_diagnostics};
+use jenkins::check_triggers;
+use kubernetes::check_manifest;
+use lsp::serve_lsp;
+use metrics::Metrics;
+use rayon::ThreadPoolBuilder;
+use rayon::prelude::*;
+use parser::{CrontabParserOptions, DEBIAN_MAX_COMMAND_LENGTH};
+use progress::Progress;
+use quartz::{EXPRESSION_FORMAT_NAMES, ExpressionFormat, convert_expression};
+use repl::run_repl;
+use report::{crontab_job_users, crontab_runs, ics_calendar, parse_duration, timeline, upcoming_json, upcoming_text, user_stats, users_text};
+use summary::{Summary, check_json, file_json, line_count};
+use syslog::{SYSLOG_SOCKET, Syslog};
+use systemd::{check_calendar, check_timer_unit, unit_settings};
 
-use parser::{CrontabParserOptions, parse_crontab, walk_errors};
 
-
-fn parse_args<'a>() -> ArgMatches<'a> {
+fn app<'a, 'b>() -> App<'a, 'b> {
     App::new("crontabcheck")
-        .about("Check a crontab file (read from stdin)")
+        .about("Check crontab files (`crontabcheck [FILE]...` is short for `crontabcheck check [FILE]...`)")
+        // options of all the subcommands, which may also come before them
         .arg(
             Arg::with_name("allowed-usernames")
                 .short("u")
                 .takes_value(true)
                 .use_delimiter(true)
-                .default_value("")
+                .global(true)
                 .help("Comma-separated list of valid usernames (may be specified multiple times).")
         )
         .arg(
             Arg::with_name("passwd-usernames")
                 .short("p")
+                .global(true)
                 .help("Read valid usernames from /etc/passwd")
-        ).get_matches()
+        )
+        .arg(
+            Arg::with_name("forbid-root")
+                .long("forbid-root")
+                .global(true)
+                .help("Reject jobs running as root (or any uid 0 user listed in /etc/passwd)")
+        )
+        .arg(
+            Arg::with_name("check-commands")
+                .long("check-commands")
+                .global(true)
+                .help("Check that the program run by each job, SHELL and PATH exist, and that the files run by root jobs are not writable by others")
+        )
+        .arg(
+            Arg::with_name("check-etc-shells")
+                .long("check-etc-shells")
+                .global(true)
+                .help("Check that SHELL is listed in /etc/shells")
+        )
+        .arg(
+            Arg::with_name("check-shell-syntax")
+                .long("check-shell-syntax")
+                .global(true)
+                .help("Check the syntax of each job's command line with `$SHELL -n` (SHELL as set in the crontab, if listed in /etc/shells)")
+        )
+        .arg(
+            Arg::with_name("warn-relative-arguments")
+                .long("warn-relative-arguments")
+                .global(true)
+                .help("Also warn about relative paths in the arguments of commands (not only the program)")
+        )
+        .arg(
+            Arg::with_name("dialect")
+                .long("dialect")
+                .takes_value(true)
+                .possible_values(DIALECT_NAMES)
+                .global(true)
+                .help("cron implementation the crontab is written for (vixie by default): openbsd accepts random values (0~30), freebsd the @ shortcuts, including @every_second and @<seconds>, posix rejects names, steps and @ shortcuts, extended accepts the Quartz-style '?', 'L' and '#' in the day fields")
+        )
+        .arg(
+            Arg::with_name("allowed-env-vars")
+                .long("allowed-env-vars")
+                .takes_value(true)
+                .use_delimiter(true)
+                .value_name("NAMES")
+                .global(true)
+                .help("Comma-separated list of the only environment variables the crontab may set")
+        )
+        .arg(
+            Arg::with_name("max-command-length")
+                .long("max-command-length")
+                .takes_value(true)
+                .value_name("LENGTH")
+                .global(true)
+                .help("Maximum length of the command part of job lines (defaults to Debian cron's limit, 999)")
+        )
+        .arg(
+            Arg::with_name("lossy")
+                .long("lossy")
+                .global(true)
+                .help("Replace invalid UTF-8 sequences and go on checking (by default, files with invalid UTF-8 are rejected)")
+        )
+        .arg(
+            Arg::with_name("sysroot")
+                .long("sysroot")
+                .alias("root")
+                .takes_value(true)
+                .value_name("DIRECTORY")
+                .global(true)
+                .help("Root of the filesystem the crontabs run in (e.g. an OS image, / by default): users, shells, commands and --system files are looked up there")
+        )
+        .arg(
+            Arg::with_name("metrics-file")
+                .long("metrics-file")
+                .takes_value(true)
+                .value_name("FILE")
+                .global(true)
+                .help("Write Prometheus metrics (files checked, invalid lines...) to this file, for the textfile collector \
+                       of the node exporter: after a --system scan, or after each check of the daemon")
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .value_name("FILE")
+                .global(true)
+                .help("Write the report (or the findings, in the chosen --format) to this file instead of stdout, e.g. as a CI \
+                       artifact: the diagnostics of the checks are then printed on stderr")
+        )
+        .arg(
+            Arg::with_name("lang")
+                .long("lang")
+                .takes_value(true)
+                .possible_values(LANG_NAMES)
+                .global(true)
+                .help("Language of the diagnostics (by default, that of LC_ALL, LC_MESSAGES or LANG; English if it is another)")
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .global(true)
+                .help("Do not show the progress of long scans on stderr (it is only shown on terminals)")
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .value_name("FILE")
+                .global(true)
+                .help("Read additional checks (e.g. denied command patterns) from a TOML configuration file")
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Check crontab files (read from stdin if no file is given), or report when their jobs run")
+                .arg(
+                    Arg::with_name("files")
+                        .value_name("FILE")
+                        .multiple(true)
+                        .help("Crontab files to check")
+                )
+                .arg(
+                    Arg::with_name("system")
+                        .long("system")
+                        .conflicts_with("files")
+                        .help("Check all the crontabs of this system: /etc/crontab, /etc/cron.d and user crontabs (when run as root)")
+                )
+                .arg(
+                    Arg::with_name("k8s")
+                        .long("k8s")
+                        .conflicts_with_all(&["system", "edit", "install", "archive"])
+                        .help("The files (or stdin) are Kubernetes manifests: check the schedule and time zone of their CronJobs")
+                )
+                .arg(
+                    Arg::with_name("systemd")
+                        .long("systemd")
+                        .conflicts_with_all(&["system", "edit", "install", "archive", "k8s"])
+                        .help("The files (or stdin) are systemd timer units: check their OnCalendar= settings")
+                )
+                .arg(
+                    Arg::with_name("oncalendar")
+                        .long("oncalendar")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("EXPRESSION")
+                        .conflicts_with_all(&["files", "system", "edit", "install", "archive", "k8s", "systemd"])
+                        .help("Check a systemd calendar event expression (as in OnCalendar=), e.g. \"Mon..Fri *-*-* 02:00\"")
+                )
+                .arg(
+                    Arg::with_name("convert-to")
+                        .long("convert-to")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&["systemd", "crontab"])
+                        .conflicts_with_all(&["system", "edit", "install", "archive", "k8s", "systemd", "oncalendar"])
+                        .help("Convert the (single, valid) crontab to a timer and a service unit per job for systemd, or the given systemd timers (and their services) to crontab lines")
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&["upcoming", "timeline", "ics", "users"])
+                        .conflicts_with_all(&["edit", "install", "archive", "k8s", "systemd", "oncalendar", "convert-to"])
+                        .help("Instead of checking the crontabs (or the --system ones), print when their jobs run until --until: as a list (upcoming), a grid of the number of runs at each minute of the day (timeline) or an iCalendar file (ics), or the number of jobs and runs of each user (users)")
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .help("Format of the findings of the checks (with their summary), and of the upcoming and users reports")
+                )
+                .arg(
+                    Arg::with_name("until")
+                        .long("until")
+                        .takes_value(true)
+                        .value_name("DURATION")
+                        .default_value("24h")
+                        .help("End of the --report period, from now (e.g. 30m, 24h, 7d, 2w)")
+                )
+                .arg(
+                    Arg::with_name("fix")
+                        .long("fix")
+                        .conflicts_with_all(&["system", "edit", "install", "archive", "k8s", "systemd", "oncalendar", "convert-to", "report"])
+                        .help("Fix CRLF line endings, trailing whitespace, uppercase names, 7 for Sunday and the missing final newline in the files, then check them")
+                )
+                .arg(
+                    Arg::with_name("stdout")
+                        .long("stdout")
+                        .requires("fix")
+                        .help("Print the fixed crontabs instead of changing the files (the fixes are listed on stderr)")
+                )
+                .arg(
+                    Arg::with_name("units-directory")
+                        .long("units-directory")
+                        .takes_value(true)
+                        .value_name("DIRECTORY")
+                        .requires("convert-to")
+                        .help("Write the converted units to this directory (by default, they are printed)")
+                )
+                .arg(
+                    Arg::with_name("archive")
+                        .long("archive")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .conflicts_with_all(&["files", "system"])
+                        .help("Check the crontabs of a filesystem tarball, `docker save` image or OCI image layout (tar or tar.gz)")
+                )
+                .arg(
+                    Arg::with_name("edit")
+                        .long("edit")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .conflicts_with_all(&["files", "system", "archive"])
+                        .help("Edit the file with $VISUAL or $EDITOR, and only save it once it is valid (as visudo does)")
+                )
+                .arg(
+                    Arg::with_name("install")
+                        .long("install")
+                        .takes_value(true)
+                        .value_name("DESTINATION")
+                        .requires("files")
+                        .conflicts_with_all(&["system", "edit", "archive"])
+                        .help("Install the (single) crontab file given as DESTINATION, owned by root with mode 644, if it is valid")
+                )
+                .arg(
+                    Arg::with_name("print-ast")
+                        .long("print-ast")
+                        .conflicts_with_all(&["system", "edit", "install", "archive", "k8s", "systemd", "oncalendar", "convert-to", "report", "fix"])
+                        .help("Instead of checking the crontabs, print what the parser makes of each of their lines: its Debug form, or JSON with --format json")
+                )
+                .arg(
+                    Arg::with_name("check-permissions")
+                        .long("check-permissions")
+                        .help("Check that crontab files are owned by root and not writable by others, as cron requires for /etc/cron.d")
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .short("j")
+                        .long("jobs")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Number of files checked at the same time (defaults to the number of CPUs)")
+                )
+                .arg(
+                    Arg::with_name("log")
+                        .long("log")
+                        .takes_value(true)
+                        .value_name("DESTINATION")
+                        .possible_values(&["stdout", "syslog"])
+                        .conflicts_with_all(&["edit", "install", "report", "convert-to", "fix"])
+                        .help("Where the findings of the checks go (stdout by default): syslog (or the journal) records them with their priority, e.g. for scheduled audits")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("audit")
+                .about("Report the security findings of all the crontabs of this system (or of the given files), highest risks first: \
+                        files run by root which others can modify, credentials, downloads run without checks...")
+                .arg(
+                    Arg::with_name("files")
+                        .value_name("FILE")
+                        .multiple(true)
+                        .help("Crontab files to audit (by default, those of --system)")
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .help("Format of the report")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("explain-code")
+                .about("Explain a diagnostic code (e.g. CTC014, printed with the diagnostics): what the rule looks for, why, and examples")
+                .arg(
+                    Arg::with_name("code")
+                        .value_name("CODE")
+                        .required(true)
+                        .help("Code, or name of the rule (e.g. pipe-to-shell)")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("repl")
+                .about("Type schedules at a prompt to see whether they are valid, what they mean and when they run next")
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Print the changes between two crontabs: jobs added, removed or run at other times (in English), \
+                        environment variables changed. Whitespace and comments are ignored. Exits with status 1 if they differ.")
+                .arg(
+                    Arg::with_name("old")
+                        .value_name("OLD")
+                        .required(true)
+                        .help("Crontab file before the change")
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .value_name("NEW")
+                        .required(true)
+                        .help("Crontab file after the change")
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .help("Format of the changes")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("Convert schedule expressions between cron formats, reporting what has no equivalent")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(EXPRESSION_FORMAT_NAMES)
+                        .help("Format of the expressions")
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(EXPRESSION_FORMAT_NAMES)
+                        .help("Format to convert them to")
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Convert the expressions of a file, one per line (blank lines and # comments are kept)")
+                )
+                .arg(
+                    Arg::with_name("expression")
+                        .value_name("EXPRESSION")
+                        .required_unless("file")
+                        .conflicts_with("file")
+                        .help("Expression to convert, e.g. \"0 0/15 8-17 ? * MON-FRI\"")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Format crontabs: fields separated by single spaces, lowercase names, 0 for Sunday (stdin is formatted to stdout)")
+                .arg(
+                    Arg::with_name("files")
+                        .value_name("FILE")
+                        .multiple(true)
+                        .help("Crontab files to format in place")
+                )
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("Do not change the files, exit with status 1 if some are not formatted (e.g. in CI)")
+                )
+                .arg(
+                    Arg::with_name("align")
+                        .long("align")
+                        .help("Align the fields of the jobs in columns")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("daemon")
+                .about("Keep running, checking the crontabs sent on a Unix socket (one JSON request per line, one JSON response per line)")
+                .arg(
+                    Arg::with_name("socket")
+                        .long("socket")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .required(true)
+                        .help("Path of the socket to listen on")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Run an HTTP server checking the crontabs POSTed to /check, answering with JSON diagnostics")
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .takes_value(true)
+                        .value_name("ADDRESS")
+                        .default_value("127.0.0.1:8080")
+                        .help("Address and port to listen on")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("lsp")
+                .about("Run a Language Server Protocol server on stdin and stdout, for editors to show diagnostics as crontabs are edited")
+        )
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Print the crontab file of the jobs defined in a TOML or YAML file, if it is valid")
+                .arg(
+                    Arg::with_name("spec")
+                        .value_name("SPEC")
+                        .required(true)
+                        .help("Job definitions: [[jobs]] with a name, schedule, user, command and optional comment, and an [environment] table")
+                )
+        )
+}
+
+// The arguments of the command: without a subcommand, it checks crontabs (`crontabcheck -p /etc/crontab`)
+fn parse_args<'a>() -> ArgMatches<'a> {
+    let args: Vec<OsString> = env::args_os().collect();
+    match app().get_matches_from_safe(&args) {
+        Ok(matches) => if matches.subcommand_name().is_some() {
+            return matches;
+        },
+        Err(ref e) if e.kind == ErrorKind::UnknownArgument || e.kind == ErrorKind::UnrecognizedSubcommand => (),
+        Err(e) => e.exit(),
+    }
+    let mut check_args = args.clone();
+    check_args.insert(1, OsString::from("check"));
+    app().get_matches_from(check_args)
 }
 
 fn main() {
@@ -39,41 +545,893 @@ fn main() {
 
 fn run() -> i32 {
     let matches = parse_args();
-    let mut allowed_usernames: Vec<String> = matches.values_of("allowed-usernames").unwrap().map(|s| s.to_string()).collect();
+    let (subcommand, matches) = match matches.subcommand() {
+        (name, Some(matches)) => (name, matches),
+        _ => unreachable!(),
+    };
+    let config = match matches.value_of("config") {
+        Some(path) => match load_config(path) {
+            Ok(config) => config,
+            Err(e) => { println!("{}", e); return 2; }
+        },
+        None => Config::default()
+    };
+    let sysroot = Path::new(matches.value_of("sysroot").unwrap_or("/"));
+    let mut allowed_usernames: Vec<String> = matches.values_of("allowed-usernames")
+        .map(|names| names.map(|s| s.to_string()).collect())
+        .unwrap_or_default();
     if matches.is_present("passwd-usernames") {
-         match usernames_from_etc_passwd() {
+         match usernames_from_etc_passwd(sysroot) {
             Ok(more_usernames) => allowed_usernames.extend(more_usernames),
-            Err(e) => { println!("could not read usernames from /etc/passwd: {}", e); return 2; }
+            Err(e) => { println!("could not read usernames from {}: {}", in_root(sysroot, "/etc/passwd").display(), e); return 2; }
          }
     }
+    let mut forbidden_usernames: Vec<String> = vec![];
+    if matches.is_present("forbid-root") {
+        forbidden_usernames.push("root".to_string());
+        // /etc/passwd may not be readable (e.g. when checking on a build machine): root is still forbidden
+        if let Ok(more_usernames) = uid0_usernames(sysroot) {
+            forbidden_usernames.extend(more_usernames);
+        }
+    }
+    let max_command_length = match matches.value_of("max-command-length").map(|value| value.parse::<usize>()) {
+        Some(Ok(length)) => length,
+        Some(Err(e)) => { println!("invalid maximum command length: {}", e); return 2; },
+        None => DEBIAN_MAX_COMMAND_LENGTH
+    };
+    let dialect = value_t!(matches, "dialect", Dialect).unwrap_or(Dialect::Vixie);
     let options = CrontabParserOptions {
-        allowed_usernames: Some(&allowed_usernames[..])
+        allowed_usernames: Some(&allowed_usernames[..]),
+        forbidden_usernames: Some(&forbidden_usernames[..]),
+        max_command_length,
+        crontab_owner: None,
+        dialect,
+    };
+    let allowed_shells = if matches.is_present("check-etc-shells") {
+        match shells_from_etc_shells(sysroot) {
+            Ok(shells) => Some(shells),
+            Err(e) => { println!("could not read shells from {}: {}", in_root(sysroot, "/etc/shells").display(), e); return 2; }
+        }
+    } else {
+        None
+    };
+    let checker_options = CheckerOptions {
+        config: &config,
+        dialect,
+        check_commands: matches.is_present("check-commands"),
+        sysroot,
+        allowed_shells,
+        // the command line takes precedence over the configuration file
+        allowed_variables: matches.values_of("allowed-env-vars")
+            .map(|names| names.map(|name| name.to_string()).collect())
+            .or(config.allowed_env_vars.clone()),
+        check_shell_syntax: matches.is_present("check-shell-syntax"),
+        warn_relative_arguments: matches.is_present("warn-relative-arguments"),
+        lossy: matches.is_present("lossy"),
+    };
+    let lang = Lang::detect(matches.value_of("lang"));
+    match subcommand {
+        "audit" => audit_crontabs(matches, &config, sysroot),
+        "convert" => convert_expressions(matches),
+        "diff" => diff_files(matches, sysroot),
+        "explain-code" => {
+            let code = matches.value_of("code").unwrap();
+            match explain_code(code) {
+                Some(explanation) => { print!("{}", explanation); 0 },
+                None => { println!("unknown code: {}", code); 2 },
+            }
+        },
+        "fmt" => format_files(matches, sysroot),
+        "repl" => {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            match run_repl(&mut stdin.lock(), &mut stdout.lock(), || Local::now().naive_local()) {
+                Ok(()) => 0,
+                Err(e) => { eprintln!("repl: {}", e); 2 }
+            }
+        },
+        "generate" => generate_file(matches.value_of("spec").unwrap(), &options, &checker_options, lang),
+        "daemon" => {
+            let socket = matches.value_of("socket").unwrap();
+            if let Err(e) = run_daemon(Path::new(socket), matches.value_of("metrics-file").map(Path::new), &options, &checker_options) {
+                println!("could not listen on {}: {}", socket, e);
+            }
+            2
+        },
+        "serve" => {
+            let address = matches.value_of("listen").unwrap();
+            if let Err(e) = run_http_server(address, &options, &checker_options) {
+                println!("could not listen on {}: {}", address, e);
+            }
+            2
+        },
+        "lsp" => {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            // stdout belongs to the protocol: errors go to stderr
+            match serve_lsp(&mut stdin.lock(), &mut stdout.lock(), &options, &checker_options) {
+                Ok(true) => 0,
+                Ok(false) => 1,
+                Err(e) => { eprintln!("language server: {}", e); 2 }
+            }
+        },
+        _ => check_crontabs(matches, &options, &checker_options, lang),
+    }
+}
+
+// The audit subcommand: exits with status 1 if there are high risk findings
+fn audit_crontabs(matches: &ArgMatches, config: &Config, sysroot: &Path) -> i32 {
+    let (crontabs, system) = match matches.values_of("files") {
+        Some(paths) => (paths.map(|path| CrontabFile { path: PathBuf::from(path), owner: user_crontab_owner(Path::new(path), sysroot) }).collect(), false),
+        None => {
+            let (crontabs, notes) = system_crontabs(sysroot);
+            // stdout belongs to the report
+            for note in notes {
+                eprintln!("Note: {}", note);
+            }
+            (crontabs, true)
+        },
+    };
+    let auditor = Auditor::new(config, sysroot);
+    let progress = Progress::new(crontabs.len(), matches.is_present("quiet"));
+    let results: Vec<io::Result<Vec<Finding>>> = crontabs.par_iter().map(|crontab| {
+        let contents = read_file(&crontab.path)?;
+        let source = crontab.path.to_string_lossy();
+        let mut findings = if system && crontab.owner.is_none() { auditor.audit_file_permissions(&crontab.path) } else { vec![] };
+        findings.extend(auditor.audit_crontab(&String::from_utf8_lossy(&contents), &source, crontab.owner.as_ref().map(|owner| &owner[..])));
+        Ok(findings)
+    }).inspect(|_| progress.tick()).collect();
+    progress.finish();
+    let mut findings = vec![];
+    let mut unreadable = false;
+    for (crontab, result) in crontabs.iter().zip(results) {
+        match result {
+            Ok(file_findings) => findings.extend(file_findings),
+            Err(e) => { eprintln!("could not read {}: {}", crontab.path.display(), e); unreadable = true; },
+        }
+    }
+    sort_findings(&mut findings);
+    let report = match matches.value_of("format") {
+        Some("json") => format!("{}\n", audit_json(&findings)),
+        _ => audit_text(&findings),
+    };
+    if !write_output(matches.value_of("output"), &report) || unreadable {
+        2
+    } else if findings.iter().any(|finding| finding.risk == Risk::High) {
+        1
+    } else {
+        0
+    }
+}
+
+// The check subcommand: check crontabs (or other files holding schedules), or report when their jobs run
+fn check_crontabs(matches: &ArgMatches, options: &CrontabParserOptions<String>, checker_options: &CheckerOptions, lang: Lang) -> i32 {
+    let sysroot = checker_options.sysroot;
+    let syslog = match matches.value_of("log") {
+        Some("syslog") => match Syslog::connect() {
+            Ok(syslog) => Some(syslog),
+            Err(e) => { println!("could not connect to syslog ({}): {}", SYSLOG_SOCKET, e); return 2; }
+        },
+        _ => None,
+    };
+    let syslog = syslog.as_ref();
+    let quiet = matches.is_present("quiet");
+    let json = matches.value_of("format") == Some("json");
+    let output = matches.value_of("output");
+    // files are checked concurrently, their diagnostics are reported in order
+    if let Some(jobs) = matches.value_of("jobs") {
+        let jobs = match jobs.parse::<usize>() {
+            Ok(jobs) if jobs > 0 => jobs,
+            _ => { println!("invalid number of jobs: {}", jobs); return 2; }
+        };
+        if let Err(e) = ThreadPoolBuilder::new().num_threads(jobs).build_global() {
+            println!("could not start the threads: {}", e);
+            return 2;
+        }
+    }
+    // --report reads the system crontabs itself
+    if matches.is_present("system") && !matches.is_present("report") {
+        let (crontabs, notes) = system_crontabs(sysroot);
+        let mut reports = FileReports::new(syslog, json, output, lang);
+        for note in notes {
+            reports.note(&format!("Note: {}", note));
+        }
+        let mut metrics = Metrics::default();
+        let progress = Progress::new(crontabs.len(), quiet);
+        let results: Vec<io::Result<(usize, Vec<Diagnostic>)>> = crontabs.par_iter().map(|crontab| {
+            let options = CrontabParserOptions { crontab_owner: crontab.owner.as_ref().map(|owner| &owner[..]), ..*options };
+            // user crontabs belong to their user, not to root
+            let check_file_permissions = matches.is_present("check-permissions") && crontab.owner.is_none();
+            file_diagnostics(&crontab.path, &options, checker_options, check_file_permissions)
+        }).inspect(|_| progress.tick()).collect();
+        progress.finish();
+        for (crontab, result) in crontabs.iter().zip(results) {
+            let (lines, diagnostics) = result.unwrap_or_else(|e| {
+                (0, vec![Diagnostic { line_number: 0, line: String::new(), severity: Severity::Error, rule: "unreadable", message: format!("could not read the file: {}", e) }])
+            });
+            metrics.record(&diagnostics);
+            reports.report(&crontab.path.to_string_lossy(), lines, &diagnostics);
+        }
+        let invalid_count = reports.summary.invalid_files;
+        if !reports.finish() {
+            return 2;
+        }
+        if let Some(path) = matches.value_of("metrics-file") {
+            if let Err(e) = write_atomically(Path::new(path), metrics.prometheus_text(false).as_bytes()) {
+                println!("could not write {}: {}", path, e);
+                return 2;
+            }
+        }
+        return if invalid_count == 0 { 0 } else { 1 };
+    }
+    if let Some(archive) = matches.value_of("archive") {
+        let crontabs = match crontabs_in_archive(Path::new(archive)) {
+            Ok(crontabs) => crontabs,
+            Err(e) => { println!("could not read {}: {}", archive, e); return 2; }
+        };
+        let mut reports = FileReports::new(syslog, json, output, lang);
+        let progress = Progress::new(crontabs.len(), quiet);
+        let results: Vec<(String, usize, Vec<Diagnostic>)> = crontabs.into_par_iter().map(|crontab| {
+            let options = CrontabParserOptions { crontab_owner: crontab.owner.as_ref().map(|owner| &owner[..]), ..*options };
+            let diagnostics = check_crontab_bytes(&crontab.contents, true, &options, checker_options);
+            (crontab.path, line_count(&crontab.contents), diagnostics)
+        }).inspect(|_| progress.tick()).collect();
+        progress.finish();
+        for (path, lines, diagnostics) in results {
+            reports.report(&format!("{}:{}", archive, path), lines, &diagnostics);
+        }
+        let invalid_count = reports.summary.invalid_files;
+        if !reports.finish() {
+            return 2;
+        }
+        return if invalid_count == 0 { 0 } else { 1 };
+    }
+    if let Some(path) = matches.value_of("edit") {
+        let owner = user_crontab_owner(Path::new(path), sysroot);
+        let options = CrontabParserOptions { crontab_owner: owner.as_ref().map(|owner| &owner[..]), ..*options };
+        let check = |contents: &[u8]| print_diagnostics(&check_crontab_bytes(contents, true, &options, checker_options), Some(path), lang);
+        return match edit_crontab(Path::new(path), check) {
+            Ok(true) => 0,
+            Ok(false) => 1,
+            Err(e) => { println!("could not edit {}: {}", path, e); 2 }
+        };
+    }
+    if let Some(destination) = matches.value_of("install") {
+        let sources: Vec<&str> = matches.values_of("files").unwrap().collect();
+        if sources.len() != 1 {
+            println!("--install takes a single crontab file");
+            return 2;
+        }
+        let mut contents = vec![];
+        if let Err(e) = File::open(sources[0]).and_then(|mut file| file.read_to_end(&mut contents)) {
+            println!("could not read {}: {}", sources[0], e);
+            return 2;
+        }
+        if !print_diagnostics(&check_crontab_bytes(&contents, true, options, checker_options), Some(sources[0]), lang) {
+            println!("{} was not installed", destination);
+            return 1;
+        }
+        return match install_crontab(Path::new(destination), &contents) {
+            Ok(()) => 0,
+            Err(e) => { println!("could not install {}: {}", destination, e); 2 }
+        };
+    }
+    if matches.is_present("print-ast") {
+        return print_ast(matches, options, sysroot, json, output);
+    }
+    if let Some(format) = matches.value_of("report") {
+        let until = match parse_duration(matches.value_of("until").unwrap()) {
+            Ok(until) => until,
+            Err(e) => { println!("{}", e); return 2; }
+        };
+        let start = Local::now().naive_local();
+        let crontabs = if matches.is_present("system") {
+            system_crontabs(sysroot).0
+        } else {
+            match matches.values_of("files") {
+                Some(paths) => paths.map(|path| CrontabFile { path: PathBuf::from(path), owner: user_crontab_owner(Path::new(path), sysroot) }).collect(),
+                None => vec![CrontabFile { path: PathBuf::from("-"), owner: None }],
+            }
+        };
+        let (mut runs, mut job_users) = (vec![], vec![]);
+        for crontab in &crontabs {
+            let from_stdin = crontab.path == Path::new("-");
+            let mut contents = vec![];
+            let read = if from_stdin {
+                io::stdin().read_to_end(&mut contents)
+            } else {
+                File::open(&crontab.path).and_then(|mut file| file.read_to_end(&mut contents))
+            };
+            if let Err(e) = read {
+                println!("could not read {}: {}", crontab.path.display(), e);
+                return 2;
+            }
+            let source = crontab.path.to_string_lossy().into_owned();
+            let options = CrontabParserOptions { crontab_owner: crontab.owner.as_ref().map(|owner| &owner[..]), ..*options };
+            // the report goes to stdout (or --output): warnings are left out, and there is no report if a crontab is invalid
+            let diagnostics = check_crontab_bytes(&contents, !from_stdin, &options, checker_options);
+            if diagnostics.iter().any(|diagnostic| matches!(diagnostic.severity, Severity::Error)) {
+                match output {
+                    Some(_) => eprint!("{}", diagnostics_text(&diagnostics, Some(&source), lang)),
+                    None => { print_diagnostics(&diagnostics, Some(&source), lang); },
+                }
+                return 1;
+            }
+            let contents = String::from_utf8_lossy(&contents);
+            match crontab_runs(&contents, &source, &options, start, start + until) {
+                Ok(crontab_runs) => runs.extend(crontab_runs),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                },
+            }
+            job_users.extend(crontab_job_users(&contents, &options));
+        }
+        runs.sort_by_key(|run| run.time);
+        let report = match format {
+            "upcoming" if json => format!("{}\n", upcoming_json(&runs)),
+            "upcoming" => upcoming_text(&runs),
+            "timeline" => timeline(&runs),
+            "ics" => ics_calendar(&runs),
+            "users" if json => format!("{}\n", serde_json::to_string_pretty(&user_stats(&job_users, &runs, until)).unwrap_or_default()),
+            "users" => users_text(&user_stats(&job_users, &runs, until)),
+            _ => unreachable!(),
+        };
+        return if write_output(output, &report) { 0 } else { 2 };
+    }
+    if matches.value_of("convert-to") == Some("crontab") {
+        return match matches.values_of("files") {
+            Some(paths) => convert_timers(&paths.map(Path::new).collect::<Vec<&Path>>()),
+            None => { println!("--convert-to crontab needs the .timer files to convert"); 2 },
+        };
+    }
+    if matches.is_present("convert-to") {
+        let path = match matches.values_of("files").map(|paths| paths.collect::<Vec<&str>>()) {
+            Some(ref paths) if paths.len() > 1 => { println!("--convert-to takes a single crontab file"); return 2; },
+            Some(paths) => Some(paths[0]),
+            None => None,
+        };
+        let mut contents = vec![];
+        let read = match path {
+            Some(path) => File::open(path).and_then(|mut file| file.read_to_end(&mut contents)),
+            None => io::stdin().read_to_end(&mut contents),
+        };
+        if let Err(e) = read {
+            println!("could not read {}: {}", path.unwrap_or("stdin"), e);
+            return 2;
+        }
+        let owner = path.and_then(|path| user_crontab_owner(Path::new(path), sysroot));
+        let options = CrontabParserOptions { crontab_owner: owner.as_ref().map(|owner| &owner[..]), ..*options };
+        // only valid crontabs are converted
+        if !print_diagnostics(&check_crontab_bytes(&contents, path.is_some(), &options, checker_options), path, lang) {
+            return 1;
+        }
+        let contents = String::from_utf8_lossy(&contents);
+        let name = path.and_then(|path| Path::new(path).file_name()).map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| "crontab".to_string());
+        let units = match to_systemd_units(&contents, &name, &options) {
+            Ok(units) => units,
+            Err(e) => { println!("could not convert {}: {}", name, e); return 1; }
+        };
+        for unit in units {
+            match matches.value_of("units-directory") {
+                Some(directory) => if let Err(e) = fs::write(Path::new(directory).join(&unit.name), &unit.contents) {
+                    println!("could not write {}: {}", unit.name, e);
+                    return 2;
+                },
+                None => println!("# {}\n{}", unit.name, unit.contents),
+            }
+        }
+        return 0;
+    }
+    let mut valid = true;
+    if let Some(expressions) = matches.values_of("oncalendar") {
+        let zoneinfo = in_root(sysroot, ZONEINFO_DIRECTORY);
+        for expression in expressions {
+            if let Err(e) = check_calendar(expression, &zoneinfo) {
+                println!("Invalid calendar expression: {}\n{}", expression, e);
+                valid = false;
+            }
+        }
+        return if valid { 0 } else { 1 };
+    }
+    // files which are not crontabs, but hold schedules
+    let schedule_files = if matches.is_present("k8s") {
+        Some(check_manifest as fn(&str, &Path) -> Vec<Diagnostic>)
+    } else if matches.is_present("systemd") {
+        Some(check_timer_unit as fn(&str, &Path) -> Vec<Diagnostic>)
+    } else if checker_options.dialect == Dialect::Jenkins {
+        Some(check_triggers as fn(&str, &Path) -> Vec<Diagnostic>)
+    } else {
+        None
+    };
+    if let Some(check) = schedule_files {
+        let zoneinfo = in_root(sysroot, ZONEINFO_DIRECTORY);
+        let paths: Vec<&str> = match matches.values_of("files") {
+            Some(paths) => paths.collect(),
+            None => vec!["-"],
+        };
+        for path in paths {
+            let mut contents = String::new();
+            let read = if path == "-" {
+                io::stdin().read_to_string(&mut contents)
+            } else {
+                File::open(path).and_then(|mut file| file.read_to_string(&mut contents))
+            };
+            if let Err(e) = read {
+                println!("could not read {}: {}", path, e);
+                return 2;
+            }
+            valid &= print_diagnostics(&check(&contents, &zoneinfo), if path == "-" { None } else { Some(path) }, lang);
+        }
+        return if valid { 0 } else { 1 };
+    }
+    match matches.values_of("files") {
+        Some(paths) => {
+            let paths: Vec<&str> = paths.collect();
+            if matches.is_present("fix") {
+                for path in &paths {
+                    let owner = user_crontab_owner(Path::new(path), sysroot);
+                    if let Err(e) = fix_file(Path::new(path), owner.as_ref().map(|owner| &owner[..]), matches.is_present("stdout")) {
+                        println!("could not fix {}: {}", path, e);
+                        return 2;
+                    }
+                }
+                if matches.is_present("stdout") {
+                    return 0;
+                }
+            }
+            let progress = Progress::new(paths.len(), quiet);
+            let results: Vec<io::Result<(usize, Vec<Diagnostic>)>> = paths.par_iter().map(|path| {
+                // files in the user crontab spools have no user field
+                let owner = user_crontab_owner(Path::new(path), sysroot);
+                let options = CrontabParserOptions { crontab_owner: owner.as_ref().map(|owner| &owner[..]), ..*options };
+                let check_file_permissions = matches.is_present("check-permissions") && owner.is_none();
+                file_diagnostics(Path::new(path), &options, checker_options, check_file_permissions)
+            }).inspect(|_| progress.tick()).collect();
+            progress.finish();
+            let mut reports = FileReports::new(syslog, json, output, lang);
+            for (path, result) in paths.iter().zip(results) {
+                match result {
+                    Ok((lines, diagnostics)) => valid &= reports.report(path, lines, &diagnostics),
+                    Err(e) => { println!("could not read {}: {}", path, e); return 2; }
+                }
+            }
+            if !reports.finish() {
+                return 2;
+            }
+        },
+        None if matches.is_present("fix") => {
+            let mut contents = vec![];
+            if let Err(what) = io::stdin().read_to_end(&mut contents) {
+                println!("could no read from stdin: {:?}", what);
+                return 2;
+            }
+            let (fixed, changes) = fix_crontab(&String::from_utf8_lossy(&contents), None);
+            for change in changes {
+                eprintln!("{}", change);
+            }
+            print!("{}", fixed);
+            return 0;
+        },
+        None => {
+            // the lines are checked as they are read, e.g. when crontabcheck is in a pipeline
+            let stdin = io::stdin();
+            let mut reports = FileReports::new(syslog, json, output, lang);
+            let mut stream = CrontabChecker::new(options, checker_options).check_stream(stdin.lock());
+            for diagnostic in &mut stream {
+                match diagnostic {
+                    Ok(diagnostic) => valid &= reports.report_streamed(diagnostic),
+                    Err(what) => { println!("could no read from stdin: {:?}", what); return 2; }
+                }
+            }
+            valid = reports.finish_stream(stream.line_count(), valid);
+            if !reports.finish() {
+                return 2;
+            }
+        }
+    }
+    if valid { 0 } else { 1 }
+}
+
+// The diff subcommand: exits with status 1 if the crontabs differ
+fn diff_files(matches: &ArgMatches, sysroot: &Path) -> i32 {
+    let (old, new) = (matches.value_of("old").unwrap(), matches.value_of("new").unwrap());
+    let mut contents = vec![];
+    for path in &[old, new] {
+        match read_file(Path::new(path)) {
+            Ok(file_contents) => contents.push(String::from_utf8_lossy(&file_contents).into_owned()),
+            Err(e) => { println!("could not read {}: {}", path, e); return 2; }
+        }
+    }
+    // the jobs of user crontabs run as their owner
+    let owner = user_crontab_owner(Path::new(new), sysroot);
+    let changes = diff_crontabs(&contents[0], &contents[1], owner.as_ref().map(|owner| &owner[..]));
+    let report = match matches.value_of("format") {
+        Some("json") => format!("{}\n", serde_json::to_string_pretty(&changes).unwrap_or_default()),
+        _ => changes.iter().map(|change| format!("{}\n", change.text())).collect(),
+    };
+    if !write_output(matches.value_of("output"), &report) {
+        2
+    } else if changes.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+// Print the expression (or the expressions of the file) in another format
+fn convert_expressions(matches: &ArgMatches) -> i32 {
+    let from = value_t!(matches, "from", ExpressionFormat).unwrap_or_else(|e| e.exit());
+    let to = value_t!(matches, "to", ExpressionFormat).unwrap_or_else(|e| e.exit());
+    let path = match matches.value_of("expression") {
+        Some(expression) => return match convert_expression(expression, from, to) {
+            Ok(converted) => { println!("{}", converted); 0 },
+            Err(problems) => { println!("could not convert '{}':\n{}", expression, problems.join("\n")); 1 },
+        },
+        None => matches.value_of("file").unwrap(),
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => { println!("could not read {}: {}", path, e); return 2; }
+    };
+    let mut converted = vec![];
+    let mut problems = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            converted.push(line.to_string());
+            continue;
+        }
+        match convert_expression(line, from, to) {
+            Ok(expression) => converted.push(expression),
+            Err(reasons) => problems.extend(reasons.into_iter().map(|reason| format!("{}: line {}: {}", path, index + 1, reason))),
+        }
+    }
+    // a partly converted file is of no use
+    if !problems.is_empty() {
+        println!("{}", problems.join("\n"));
+        return 1;
+    }
+    for line in converted {
+        println!("{}", line);
+    }
+    0
+}
+
+// --print-ast: exits with status 1 if some lines are invalid
+fn print_ast(matches: &ArgMatches, options: &CrontabParserOptions<String>, sysroot: &Path, json: bool, output: Option<&str>) -> i32 {
+    let paths: Vec<&str> = matches.values_of("files").map(|paths| paths.collect()).unwrap_or_else(|| vec!["-"]);
+    let (mut text, mut documents, mut all_valid) = (String::new(), vec![], true);
+    for path in paths {
+        let mut contents = vec![];
+        let read = if path == "-" {
+            io::stdin().read_to_end(&mut contents)
+        } else {
+            File::open(path).and_then(|mut file| file.read_to_end(&mut contents))
+        };
+        if let Err(e) = read {
+            println!("could not read {}: {}", path, e);
+            return 2;
+        }
+        let contents = String::from_utf8_lossy(&contents);
+        let owner = if path == "-" { None } else { user_crontab_owner(Path::new(path), sysroot) };
+        let options = CrontabParserOptions { crontab_owner: owner.as_ref().map(|owner| &owner[..]), ..*options };
+        let valid = if json {
+            let (document, valid) = ast_json(&contents, path, &options);
+            documents.push(document);
+            valid
+        } else {
+            let (lines, valid) = ast_text(&contents, path, &options);
+            text.push_str(&lines);
+            valid
+        };
+        all_valid = all_valid && valid;
+    }
+    if json {
+        text = format!("{}\n", serde_json::to_string_pretty(&documents).unwrap_or_default());
+    }
+    if !write_output(output, &text) {
+        return 2;
+    }
+    if all_valid { 0 } else { 1 }
+}
+
+// Format the files in place (stdin to stdout), or only tell which ones are not formatted with --check
+fn format_files(matches: &ArgMatches, sysroot: &Path) -> i32 {
+    let (check, align) = (matches.is_present("check"), matches.is_present("align"));
+    let paths = match matches.values_of("files") {
+        Some(paths) => paths,
+        None => {
+            let mut contents = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut contents) {
+                println!("could not read stdin: {}", e);
+                return 2;
+            }
+            let formatted = format_crontab(&contents, None, align);
+            if check {
+                return if formatted == contents { 0 } else { println!("stdin is not formatted"); 1 };
+            }
+            print!("{}", formatted);
+            return 0;
+        },
     };
-    let stdin = io::stdin();
-    for input in stdin.lock().lines() {
-        let line = match input {
-            Ok(line) => line,
-            Err(what) => { println!("could no read from stdin: {:?}", what); return 2; }
+    let mut formatted_count = 0;
+    for path in paths {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => { println!("could not read {}: {}", path, e); return 2; }
         };
-        let out = parse_crontab(line.as_bytes(), &options);
-        match out {
-            Done(..) => (),
-            Incomplete(_) => { println!("Invalid line: {} (incomplete crontab)", line); return 1; },
-            Error(err) => { println!("Invalid line: {}\n{}", line, walk_errors(&[err])); return 1; }
+        let owner = user_crontab_owner(Path::new(path), sysroot);
+        let formatted = format_crontab(&contents, owner.as_ref().map(|owner| &owner[..]), align);
+        if formatted == contents {
+            continue;
+        }
+        formatted_count += 1;
+        if check {
+            println!("{} is not formatted", path);
+        } else if let Err(e) = write_atomically(Path::new(path), formatted.as_bytes()) {
+            println!("could not write {}: {}", path, e);
+            return 2;
         }
     }
+    if check && formatted_count > 0 { 1 } else { 0 }
+}
+
+// Print the crontab generated from job definitions, once checked
+fn generate_file<T: AsRef<str>>(path: &str, options: &CrontabParserOptions<T>, checker_options: &CheckerOptions, lang: Lang) -> i32 {
+    let spec = match fs::read_to_string(path) {
+        Ok(contents) => parse_spec(&contents, path),
+        Err(e) => { println!("could not read {}: {}", path, e); return 2; }
+    };
+    let crontab = match spec.and_then(|spec| generate_crontab(&spec, path)) {
+        Ok(crontab) => crontab,
+        Err(e) => { println!("invalid job definitions in {}: {}", path, e); return 1; }
+    };
+    let diagnostics = check_crontab_bytes(crontab.as_bytes(), true, options, checker_options);
+    if diagnostics.iter().any(|diagnostic| matches!(diagnostic.severity, Severity::Error)) {
+        print_diagnostics(&diagnostics, Some(&format!("crontab generated from {}", path)), lang);
+        return 1;
+    }
+    print!("{}", crontab);
     0
 }
 
+// Print the crontab lines equivalent to systemd timers, and what could not be converted
+fn convert_timers(paths: &[&Path]) -> i32 {
+    let mut converted = true;
+    for path in paths {
+        let timer = match fs::read_to_string(path) {
+            Ok(timer) => timer,
+            Err(e) => { println!("could not read {}: {}", path.display(), e); return 2; }
+        };
+        // the service has the timer's name, unless Unit= says otherwise
+        let service_name = unit_settings(&timer).into_iter()
+            .rfind(|setting| setting.section == "[Timer]" && setting.key == "Unit")
+            .map(|setting| setting.value.to_string())
+            .unwrap_or_else(|| path.with_extension("service").file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default());
+        let service_path = path.with_file_name(&service_name);
+        let service = match fs::read_to_string(&service_path) {
+            Ok(service) => service,
+            Err(e) => { println!("could not read {}: {}", service_path.display(), e); return 2; }
+        };
+        let (lines, problems) = to_crontab_lines(&path.to_string_lossy(), &timer, &service);
+        println!("# {}", path.display());
+        for line in lines {
+            println!("{}", line);
+        }
+        for problem in &problems {
+            println!("# not converted: {}", problem);
+        }
+        converted &= problems.is_empty();
+    }
+    if converted { 0 } else { 1 }
+}
+
+// Apply the safe fixes to the file (or print the fixed file), listing what was fixed
+fn fix_file(path: &Path, owner: Option<&str>, to_stdout: bool) -> io::Result<()> {
+    let (fixed, changes) = fix_crontab(&fs::read_to_string(path)?, owner);
+    if to_stdout {
+        print!("{}", fixed);
+        for change in changes {
+            eprintln!("{}: {}", path.display(), change);
+        }
+        return Ok(());
+    }
+    if !changes.is_empty() {
+        write_atomically(path, fixed.as_bytes())?;
+    }
+    for change in changes {
+        println!("Fixed: {}: {}", path.display(), change);
+    }
+    Ok(())
+}
+
+// The number of lines of a crontab file, and its diagnostics
+fn file_diagnostics<T: AsRef<str>>(path: &Path, options: &CrontabParserOptions<T>, checker_options: &CheckerOptions, check_file_permissions: bool) -> io::Result<(usize, Vec<Diagnostic>)> {
+    let contents = read_file(path)?;
+    let mut diagnostics = vec![];
+    if check_file_permissions {
+        if let Err(errors) = check_permissions(path) {
+            diagnostics.extend(errors.into_iter().map(|message| Diagnostic {
+                line_number: 0,
+                line: String::new(),
+                severity: Severity::Error,
+                rule: "file-permissions",
+                message,
+            }));
+        }
+    }
+    diagnostics.extend(check_crontab_bytes(&contents, true, options, checker_options));
+    Ok((line_count(&contents), diagnostics))
+}
+
+// The results of the checks of files: their diagnostics are printed (or logged) in order, or gathered in the JSON
+// document of --format json, and counted in the summary which ends the run. With --output, the findings (in either
+// format) go to the file, and the diagnostics are printed on stderr.
+struct FileReports<'a> {
+    syslog: Option<&'a Syslog>,
+    json: bool,
+    output: Option<&'a str>,
+    lang: Lang,
+    files: Vec<serde_json::Value>,
+    // the diagnostics for --output, in text
+    text: String,
+    // the diagnostics of stdin so far, for the JSON document
+    streamed: Vec<Diagnostic>,
+    summary: Summary,
+}
+
+impl<'a> FileReports<'a> {
+    fn new(syslog: Option<&'a Syslog>, json: bool, output: Option<&'a str>, lang: Lang) -> Self {
+        FileReports { syslog, json, output, lang, files: vec![], text: String::new(), streamed: vec![], summary: Summary::default() }
+    }
+
+    // Whether the diagnostics are shown to humans (stdout has the JSON document otherwise)
+    fn shows_diagnostics(&self) -> bool {
+        !self.json || self.output.is_some()
+    }
+
+    // A message about the run rather than a file, which is not part of the JSON document
+    fn note(&self, message: &str) {
+        match (self.syslog, self.shows_diagnostics() && self.output.is_none()) {
+            (Some(syslog), _) => syslog.log(Severity::Info, message),
+            (None, true) => println!("{}", message),
+            (None, false) => eprintln!("{}", message),
+        }
+    }
+
+    // Returns false if the file is invalid
+    fn report(&mut self, path: &str, lines: usize, diagnostics: &[Diagnostic]) -> bool {
+        if self.json {
+            self.files.push(file_json(path, &translate_diagnostics(self.lang, diagnostics)));
+        } else if self.output.is_some() {
+            self.text.push_str(&diagnostics_text(diagnostics, Some(path), self.lang));
+        }
+        self.show(Some(path), diagnostics);
+        self.summary.record(lines, diagnostics)
+    }
+
+    // A diagnostic of stdin, reported as soon as its line is checked (see finish_stream). Returns false if it is an
+    // error.
+    fn report_streamed(&mut self, diagnostic: Diagnostic) -> bool {
+        self.show(None, slice::from_ref(&diagnostic));
+        let error = self.summary.count(&diagnostic);
+        if self.json {
+            self.streamed.push(diagnostic);
+        } else if self.output.is_some() {
+            self.text.push_str(&diagnostics_text(slice::from_ref(&diagnostic), None, self.lang));
+        }
+        !error
+    }
+
+    // Once stdin has been read, whether it is `valid`
+    fn finish_stream(&mut self, lines: usize, valid: bool) -> bool {
+        if self.json {
+            let diagnostics = translate_diagnostics(self.lang, &self.streamed);
+            self.files.push(file_json("stdin", &diagnostics));
+        }
+        self.summary.record_file(lines, valid)
+    }
 
-fn usernames_from_etc_passwd() -> Result<Vec<String>, io::Error> {
-    let file = BufReader::new(File::open("/etc/passwd")?);
+    fn show(&self, path: Option<&str>, diagnostics: &[Diagnostic]) {
+        if self.shows_diagnostics() {
+            match (self.syslog, self.output) {
+                (Some(syslog), _) => { syslog.log_diagnostics(&translate_diagnostics(self.lang, diagnostics), path); },
+                (None, Some(_)) => eprint!("{}", diagnostics_text(diagnostics, path, self.lang)),
+                (None, None) => { print_diagnostics(diagnostics, path, self.lang); },
+            }
+        }
+    }
+
+    // Returns false if the file of --output could not be written
+    fn finish(self) -> bool {
+        let summary = translate(self.lang, &self.summary.text());
+        if self.shows_diagnostics() {
+            match (self.syslog, self.output) {
+                (Some(syslog), _) => syslog.log(Severity::Info, &summary),
+                (None, Some(_)) => eprintln!("{}", summary),
+                (None, None) => println!("{}", summary),
+            }
+        }
+        match (self.json, self.output) {
+            (true, output) => write_output(output, &format!("{}\n", check_json(self.files, &self.summary))),
+            (false, Some(output)) => write_output(Some(output), &format!("{}{}\n", self.text, summary)),
+            (false, None) => true,
+        }
+    }
+}
+
+// Print diagnostics (prefixed with the file they are about, if any), returns false if one of them is an error
+fn print_diagnostics(diagnostics: &[Diagnostic], path: Option<&str>, lang: Lang) -> bool {
+    print!("{}", diagnostics_text(diagnostics, path, lang));
+    !diagnostics.iter().any(|diagnostic| matches!(diagnostic.severity, Severity::Error))
+}
+
+// The diagnostics as printed, in `lang`
+fn diagnostics_text(diagnostics: &[Diagnostic], path: Option<&str>, lang: Lang) -> String {
+    let prefix = path.map(|path| format!("{}: ", path)).unwrap_or_default();
+    let mut text = String::new();
+    for diagnostic in diagnostics {
+        let message = translate(lang, &diagnostic.message);
+        let label = match (diagnostic.severity, diagnostic.line_number) {
+            (Severity::Error, 0) => "Invalid file".to_string(),
+            (Severity::Warning, 0) => "Warning".to_string(),
+            (Severity::Info, 0) => "Note".to_string(),
+            (Severity::Error, _) => "Invalid line".to_string(),
+            (Severity::Warning, line_number) => format!("Warning on line {}", line_number),
+            (Severity::Info, line_number) => format!("Note on line {}", line_number),
+        };
+        // "Warning on line 3 [CTC025]", see explain-code
+        let label = match rule_code(diagnostic.rule) {
+            Some(code) => format!("{} [{}]", translate(lang, &label), code),
+            None => translate(lang, &label),
+        };
+        if diagnostic.line_number == 0 {
+            text.push_str(&format!("{}{}: {}\n", prefix, label, message));
+        } else {
+            text.push_str(&format!("{}{}: {}\n{}\n", prefix, label, diagnostic.line, message));
+        }
+    }
+    text
+}
+
+// Print a report, or write it to the file of --output. Returns false if it could not be written.
+fn write_output(output: Option<&str>, report: &str) -> bool {
+    match output {
+        Some(path) => match fs::write(path, report) {
+            Ok(()) => true,
+            Err(e) => { eprintln!("could not write {}: {}", path, e); false },
+        },
+        None => { print!("{}", report); true },
+    }
+}
+
+
+fn usernames_from_etc_passwd(root: &Path) -> Result<Vec<String>, io::Error> {
+    let file = BufReader::new(File::open(in_root(root, "/etc/passwd"))?);
     let mut usernames: Vec<String> = vec![];
     for line in file.lines() {
         usernames.push(
-            line?.split(':').nth(0)
+            line?.split(':').next()
             .ok_or(io::Error::new(io::ErrorKind::InvalidData, "invalid /etc/passwd format"))?
             .trim().to_string());
     }
     Ok(usernames)
 }
+
+#[cfg(test)]
+mod tests {
+
+    use clap::ErrorKind;
+
+    use app;
+
+    #[test]
+    fn test_log_conflicts() {
+        let parse = |args: &[&str]| app().get_matches_from_safe(args).map(|_| ()).map_err(|e| e.kind);
+        assert_eq!(parse(&["crontabcheck", "check", "--fix", "crontab"]), Ok(()));
+        assert_eq!(parse(&["crontabcheck", "check", "--report", "upcoming", "crontab"]), Ok(()));
+        assert_eq!(parse(&["crontabcheck", "check", "--log", "syslog", "crontab"]), Ok(()));
+        assert_eq!(parse(&["crontabcheck", "check", "--log", "syslog", "--fix", "crontab"]), Err(ErrorKind::ArgumentConflict));
+    }
+
+}
@@ -0,0 +1,81 @@
+// Prometheus metrics of the checks: served on /metrics by the HTTP server, or written for the textfile collector of
+// the node exporter after a system scan, so that the health of the crontabs of a fleet can be graphed and alerted on
+
+use std::collections::HashSet;
+
+use checker::{Diagnostic, Severity};
+
+
+#[derive(Default)]
+pub struct Metrics {
+    pub files_checked: u64,
+    pub invalid_files: u64,
+    // lines with at least an error
+    pub invalid_lines: u64,
+    pub warnings: u64,
+}
+
+impl Metrics {
+    // Count a checked file
+    pub fn record(&mut self, diagnostics: &[Diagnostic]) {
+        let mut invalid_lines = HashSet::new();
+        let mut invalid = false;
+        for diagnostic in diagnostics {
+            match diagnostic.severity {
+                Severity::Error => {
+                    invalid = true;
+                    if diagnostic.line_number > 0 {
+                        invalid_lines.insert(diagnostic.line_number);
+                    }
+                },
+                Severity::Warning => self.warnings += 1,
+                Severity::Info => (),
+            }
+        }
+        self.files_checked += 1;
+        if invalid {
+            self.invalid_files += 1;
+        }
+        self.invalid_lines += invalid_lines.len() as u64;
+    }
+
+    // The text exposition format: counters since the start of a server (`cumulative`), else gauges of a scan
+    pub fn prometheus_text(&self, cumulative: bool) -> String {
+        let (kind, suffix) = if cumulative { ("counter", "_total") } else { ("gauge", "") };
+        let metrics = [
+            ("files_checked", "Crontab files checked", self.files_checked),
+            ("invalid_files", "Crontab files with errors", self.invalid_files),
+            ("invalid_lines", "Crontab lines with errors", self.invalid_lines),
+            ("warnings", "Warnings on crontab files", self.warnings),
+        ];
+        let mut text = String::new();
+        for &(name, help, value) in &metrics {
+            let name = format!("crontabcheck_{}{}", name, suffix);
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use metrics::*;
+
+    fn diagnostic(line_number: usize, severity: Severity) -> Diagnostic {
+        Diagnostic { line_number, line: String::new(), severity, rule: "syntax", message: String::new() }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut metrics = Metrics::default();
+        metrics.record(&[diagnostic(2, Severity::Error), diagnostic(2, Severity::Error), diagnostic(0, Severity::Error), diagnostic(3, Severity::Warning)]);
+        metrics.record(&[diagnostic(0, Severity::Info)]);
+        assert_eq!((metrics.files_checked, metrics.invalid_files, metrics.invalid_lines, metrics.warnings), (2, 1, 1, 1));
+
+        let text = metrics.prometheus_text(false);
+        assert!(text.contains("# TYPE crontabcheck_invalid_lines gauge\ncrontabcheck_invalid_lines 1\n"));
+        assert!(metrics.prometheus_text(true).contains("\ncrontabcheck_files_checked_total 2\n"));
+    }
+
+}
@@ -1,10 +1,109 @@
-use std::str::from_utf8;
 use std::fmt;
 
 use nom;
-use nom::{IResult, ErrorKind, digit, space, alphanumeric, is_space};
-use nom::IResult::{Error, Done, Incomplete};
+use nom::{IResult, Slice};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_till, take_while1};
+use nom::character::complete::{digit1, space1};
+use nom::combinator::rest;
+use nom::error::{ErrorKind, ParseError};
+use nom_locate::LocatedSpan;
 
+use dialect::Dialect;
+use schedule::{DAY_NAMES, MONTH_NAMES, parse_schedule};
+use suggest::closest_match;
+
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum CrontabLine<'a> {
+    Empty,
+    Comment,
+    EnvironmentVariable { name: &'a str, value: &'a str },
+    // the five time and date fields ("0 2 * * *"), the user and the command are slices of the line
+    Job { schedule: &'a str, user: &'a str, command: &'a str },
+}
+
+// A parsed line owning its text, to keep it once the crontab it comes from is dropped
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum OwnedCrontabLine {
+    Empty,
+    Comment,
+    EnvironmentVariable { name: String, value: String },
+    Job { schedule: String, user: String, command: String },
+}
+
+impl<'a> CrontabLine<'a> {
+    pub fn to_owned_line(&self) -> OwnedCrontabLine {
+        match *self {
+            CrontabLine::Empty => OwnedCrontabLine::Empty,
+            CrontabLine::Comment => OwnedCrontabLine::Comment,
+            CrontabLine::EnvironmentVariable { name, value } => {
+                OwnedCrontabLine::EnvironmentVariable { name: name.to_string(), value: value.to_string() }
+            },
+            CrontabLine::Job { schedule, user, command } => {
+                OwnedCrontabLine::Job { schedule: schedule.to_string(), user: user.to_string(), command: command.to_string() }
+            },
+        }
+    }
+}
+
+impl OwnedCrontabLine {
+    pub fn as_line(&self) -> CrontabLine<'_> {
+        match *self {
+            OwnedCrontabLine::Empty => CrontabLine::Empty,
+            OwnedCrontabLine::Comment => CrontabLine::Comment,
+            OwnedCrontabLine::EnvironmentVariable { ref name, ref value } => CrontabLine::EnvironmentVariable { name, value },
+            OwnedCrontabLine::Job { ref schedule, ref user, ref command } => CrontabLine::Job { schedule, user, command },
+        }
+    }
+}
+
+impl<'a> CrontabLine<'a> {
+    // The line as crontab text, in a canonical form: schedules as Schedule displays them (@ shortcuts and schedules
+    // it cannot read, such as Jenkins' H, with single spaces between their fields), single spaces between the fields,
+    // values of variables quoted when they would not read back the same. Comments are not kept by the parser: they
+    // are rendered as "#". Jobs of a user crontab (`crontab_owner`) have no user field.
+    pub fn to_crontab_string(&self, crontab_owner: Option<&str>) -> String {
+        match *self {
+            CrontabLine::Empty => String::new(),
+            CrontabLine::Comment => "#".to_string(),
+            CrontabLine::EnvironmentVariable { name, value } => {
+                if value.trim() != value || value.starts_with('"') || value.starts_with('\'') {
+                    format!("{}=\"{}\"", name, value)
+                } else {
+                    format!("{}={}", name, value)
+                }
+            },
+            CrontabLine::Job { schedule, user, command } => {
+                let schedule = match parse_schedule(schedule) {
+                    Ok(parsed) => parsed.to_string(),
+                    Err(_) => schedule.split_whitespace().collect::<Vec<&str>>().join(" "),
+                };
+                match crontab_owner {
+                    Some(_) => format!("{} {}", schedule, command),
+                    None => format!("{} {} {}", schedule, user, command),
+                }
+            },
+        }
+    }
+}
+
+// A line of a system crontab
+impl<'a> fmt::Display for CrontabLine<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_crontab_string(None))
+    }
+}
+
+impl fmt::Display for OwnedCrontabLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_line())
+    }
+}
 
 #[derive(PartialEq)]
 #[derive(Debug)]
@@ -14,9 +113,30 @@ pub enum CrontabSyntaxError {
     ValueOutOfBounds { value: i32, min: i32, max: i32 },
     InvalidNumericValue,
     InvalidPeriodField,
+    InvalidHashField,
     InvalidFieldSeparator,
     InvalidUsername,
+    UnknownUsername { username: String, suggestion: Option<String> },
+    UnknownName { field: &'static str, name: String, suggestion: Option<String> },
+    ForbiddenUsername { username: String },
     InvalidCommandLine { reason: String },
+    UnterminatedQuote,
+    InvalidEnvName { name: String, character: char },
+    LineTooLong { length: usize, max: usize },
+    // a Vixie cron extension ("names of months and days"...), with --dialect posix
+    NotPosix { construct: &'static str },
+    // a word in the minute, hour or day of the month field
+    NameInNumericField { field: &'static str, name: String },
+    // "mon-5", with the range in numbers ("1-5")
+    MixedRange { range: String, numeric: String },
+    // '?', which extended dialects only accept as a whole day field
+    QuestionMark { extended: bool },
+    // 'L' (extended dialect) elsewhere than alone in the day of the month field or after a day of the week
+    InvalidLastDay,
+    // '#' (extended dialect) elsewhere than between a day of the week and its occurrence in the month ("mon#2")
+    InvalidNthDay,
+    // an error of a nom parser, which the parsers above should have turned into one of ours
+    Parse(ErrorKind),
 }
 
 impl fmt::Display for CrontabSyntaxError {
@@ -25,407 +145,1127 @@ impl fmt::Display for CrontabSyntaxError {
             CrontabSyntaxError::ValueOutOfBounds { value, min, max } => write!(f, "value {} out of bounds (accepted: {} to {})", value, min, max),
             CrontabSyntaxError::InvalidEnumField | CrontabSyntaxError::InvalidPeriodField => write!(f, "could not parse the field"),
             CrontabSyntaxError::InvalidNumericValue => write!(f, "invalid numeric value"),
+            CrontabSyntaxError::InvalidHashField => write!(f, "could not parse the field (expected H, H(min-max) or H/step)"),
             CrontabSyntaxError::InvalidFieldSeparator => write!(f, "expected a field separator (space or tab)"),
             CrontabSyntaxError::InvalidUsername => write!(f, "invalid username"),
+            CrontabSyntaxError::UnknownUsername { ref username, suggestion: Some(ref suggestion) } => write!(f, "unknown user '{}', did you mean '{}'?", username, suggestion),
+            CrontabSyntaxError::UnknownUsername { ref username, suggestion: None } => write!(f, "unknown user '{}'", username),
+            CrontabSyntaxError::UnknownName { field, ref name, suggestion: Some(ref suggestion) } => write!(f, "unknown {} '{}', did you mean '{}'?", field, name, suggestion),
+            CrontabSyntaxError::UnknownName { field, ref name, suggestion: None } => write!(f, "unknown {} '{}'", field, name),
+            CrontabSyntaxError::ForbiddenUsername { ref username } => write!(f, "jobs must not run as '{}' (append '# {}' to the line to allow it)", username, ALLOW_ROOT_MARKER),
             CrontabSyntaxError::InvalidCommandLine { ref reason } => write!(f, "invalid command line: {}", reason),
+            CrontabSyntaxError::UnterminatedQuote => write!(f, "unterminated quoted value"),
+            CrontabSyntaxError::InvalidEnvName { ref name, character } => write!(f, "invalid environment variable name '{}': unexpected '{}' (names must match [A-Za-z_][A-Za-z0-9_]*)", name, character),
+            CrontabSyntaxError::LineTooLong { length, max } => write!(f, "the line is too long ({} bytes, maximum: {})", length, max),
+            CrontabSyntaxError::NotPosix { construct } => write!(f, "{} are an extension of Vixie cron, not POSIX", construct),
+            CrontabSyntaxError::NameInNumericField { field, ref name } => {
+                let lowercase = name.to_lowercase();
+                if MONTH_NAMES.contains(&&lowercase[..]) || DAY_NAMES.contains(&&lowercase[..]) {
+                    write!(f, "month and day names are not valid in the {} field ('{}')", field, name)
+                } else {
+                    write!(f, "'{}' is not a number, which the {} field takes", name, field)
+                }
+            },
+            CrontabSyntaxError::MixedRange { ref range, ref numeric } => write!(f, "range '{}' mixes a name and a number, which crons read differently: write '{}'", range, numeric),
+            CrontabSyntaxError::QuestionMark { extended: true } => write!(f, "'?' (any day) is only valid alone in the day of the month or day of the week field"),
+            CrontabSyntaxError::QuestionMark { extended: false } => write!(f, "'?' is not cron syntax: this looks like a Quartz expression \
+                                                                                (convert it with `crontabcheck convert --from quartz --to vixie`, \
+                                                                                or use --dialect extended, where '?' stands for '*' in the day fields)"),
+            CrontabSyntaxError::InvalidLastDay => write!(f, "'L' must be alone in the day of the month field ('L', the last day of the month), \
+                                                             or follow a day in the day of the week field ('5L', the last Friday of the month)"),
+            CrontabSyntaxError::InvalidNthDay => write!(f, "'#' must be alone in the day of the week field, between a day and \
+                                                            its occurrence in the month ('mon#2', the second Monday of the month)"),
+            CrontabSyntaxError::Parse(ref kind) => write!(f, "error: {:?}", kind),  // this should not happen
         }
     }
 }
 
+// The rest of a line being parsed, which knows where it starts in the line
+pub type Span<'a> = LocatedSpan<&'a str>;
 
-fn parse_within_bounds(input: &[u8], min: i32, max: i32) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let digits = digit(input);
-    match digits {
-        Done(remaining, result)  => {
-            from_utf8(result).ok().and_then(|x|
-                x.parse::<i32>().ok()
-            ).and_then(|int|
-                if int < min || int > max {
-                    Some(Error(error_position!(
-                        ErrorKind::Custom(CrontabSyntaxError::ValueOutOfBounds { value: int, min: min, max: max }),
-                        input
-                    )))
-                } else {
-                    Some(Done(remaining, ()))
-                }
-            ).unwrap_or(
-                Error(error_position!(ErrorKind::Custom(CrontabSyntaxError::InvalidNumericValue), input))
-            )
-        },
-        Error(..) => {
-            Error(error_position!(ErrorKind::Custom(CrontabSyntaxError::InvalidNumericValue), input))
+// A syntax error, where it is, and the error (of a part of it) which caused it, if any
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct SyntaxError<'a> {
+    // boxed, as the results of every parser carry it
+    pub error: Box<CrontabSyntaxError>,
+    pub position: Span<'a>,
+    pub causes: Vec<SyntaxError<'a>>,
+}
+
+impl<'a> SyntaxError<'a> {
+    // Byte offset in the line of the innermost error, which is the most precise
+    pub fn offset(&self) -> usize {
+        self.causes.last().map(|cause| cause.offset()).unwrap_or_else(|| self.position.location_offset())
+    }
+}
+
+impl<'a> ParseError<Span<'a>> for SyntaxError<'a> {
+    fn from_error_kind(input: Span<'a>, kind: ErrorKind) -> Self {
+        SyntaxError { error: Box::new(CrontabSyntaxError::Parse(kind)), position: input, causes: vec![] }
+    }
+
+    fn append(_: Span<'a>, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+type ParseResult<'a, O = ()> = IResult<Span<'a>, O, SyntaxError<'a>>;
+
+fn fail<'a, O>(error: CrontabSyntaxError, position: Span<'a>) -> ParseResult<'a, O> {
+    Err(nom::Err::Error(SyntaxError { error: Box::new(error), position, causes: vec![] }))
+}
+
+// An error of a part of the input (the field), caused by the error of a smaller part (a value)
+fn caused<'a>(error: CrontabSyntaxError, position: Span<'a>, cause: SyntaxError<'a>) -> SyntaxError<'a> {
+    SyntaxError { error: Box::new(error), position, causes: vec![cause] }
+}
+
+// An error of nom itself, rather than one of ours
+fn is_nom_error(error: &SyntaxError) -> bool {
+    matches!(*error.error, CrontabSyntaxError::Parse(_))
+}
+
+// The error of a parser which failed on `input`
+fn syntax_error<'a>(err: nom::Err<SyntaxError<'a>>, input: Span<'a>) -> SyntaxError<'a> {
+    match err {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e,
+        // the parsers are all complete
+        nom::Err::Incomplete(_) => SyntaxError::from_error_kind(input, ErrorKind::Complete),
+    }
+}
+
+// The input after a text it starts with
+fn after<'a>(input: Span<'a>, text: &'static str) -> Option<Span<'a>> {
+    tag::<_, _, SyntaxError>(text)(input).ok().map(|(remaining, _)| remaining)
+}
+
+fn is_space(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+fn starts_with_letter(input: Span) -> bool {
+    input.fragment().starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+
+fn parse_within_bounds(input: Span, min: i32, max: i32) -> ParseResult {
+    match digit1::<_, SyntaxError>(input) {
+        Ok((remaining, digits)) => match digits.fragment().parse::<i32>() {
+            Ok(value) if value < min || value > max => fail(CrontabSyntaxError::ValueOutOfBounds { value, min, max }, input),
+            Ok(_) => Ok((remaining, ())),
+            Err(_) => fail(CrontabSyntaxError::InvalidNumericValue, input),
         },
-        Incomplete(n) => Incomplete(n)
+        Err(_) => fail(CrontabSyntaxError::InvalidNumericValue, input),
+    }
+}
+
+// A word where a value is expected: an unknown name, with the valid one it most likely stands for ("JAN", "monday",
+// "juli")
+fn unknown_name<'a>(input: Span<'a>, names: &[&str], field: &'static str) -> Option<ParseResult<'a>> {
+    let name: String = input.fragment().chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    if name.is_empty() {
+        return None;
+    }
+    let lowercase = name.to_lowercase();
+    let suggestion = names.iter()
+        .find(|&&valid| lowercase.starts_with(valid))
+        .cloned()
+        .or_else(|| closest_match(&lowercase, names))
+        .map(|s| s.to_string());
+    Some(fail(CrontabSyntaxError::UnknownName { field, name, suggestion }, input))
+}
+
+// A value of a field which only takes numbers
+fn parse_number<'a>(input: Span<'a>, field: &'static str, min: i32, max: i32) -> ParseResult<'a> {
+    if starts_with_letter(input) {
+        let name = input.fragment().chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+        return fail(CrontabSyntaxError::NameInNumericField { field, name }, input);
     }
+    parse_within_bounds(input, min, max)
 }
 
+type ValueParser = for<'a> fn(Span<'a>) -> ParseResult<'a>;
+
 // Basic values parsers (a value is either a day or month name ("mon", "jun") or a bounded integer ("2"),
-named!(minute_value_parser<&[u8], (), CrontabSyntaxError>, apply!(parse_within_bounds, 0, 59));
-named!(hour_value_parser<&[u8], (), CrontabSyntaxError>, apply!(parse_within_bounds, 0, 24));
-named!(day_of_month_value_parser<&[u8], (), CrontabSyntaxError>, apply!(parse_within_bounds, 0, 31));
-
-fn month_value_parser(input: &[u8]) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let parsed =
-        fix_error!(input, CrontabSyntaxError,
-            alt_complete!(
-                tag!("jan")
-                | tag!("feb")
-                | tag!("mar")
-                | tag!("apr")
-                | tag!("may")
-                | tag!("jun")
-                | tag!("jul")
-                | tag!("aug")
-                | tag!("sep")
-                | tag!("oct")
-                | tag!("nov")
-                | tag!("dec")
-            )
-        );
+fn minute_value_parser(input: Span) -> ParseResult {
+    parse_number(input, "minute", 0, 59)
+}
+
+fn hour_value_parser(input: Span) -> ParseResult {
+    parse_number(input, "hour", 0, 23)
+}
+
+fn day_of_month_value_parser(input: Span) -> ParseResult {
+    parse_number(input, "day of the month", 1, 31)
+}
+
+fn month_value_parser(input: Span) -> ParseResult {
+    let parsed: ParseResult<Span> = alt((
+        tag("jan"),
+        tag("feb"),
+        tag("mar"),
+        tag("apr"),
+        tag("may"),
+        tag("jun"),
+        tag("jul"),
+        tag("aug"),
+        tag("sep"),
+        tag("oct"),
+        tag("nov"),
+        tag("dec"),
+    ))(input);
     match parsed {
-        Done(i, _) => Done(i, ()),
-        Incomplete(inc) => Incomplete(inc),
-        Error(..) => parse_within_bounds(input, 1, 12)
-    }
-}
-
-fn day_of_week_value_parser(input: &[u8]) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let parsed =
-        fix_error!(input, CrontabSyntaxError,
-            alt_complete!(
-                tag!("mon")
-                | tag!("tue")
-                | tag!("wed")
-                | tag!("thu")
-                | tag!("fri")
-                | tag!("sat")
-                | tag!("sun")
-            )
-        );
+        // not "january", which only starts with a name
+        Ok((i, _)) if !starts_with_letter(i) => Ok((i, ())),
+        _ => unknown_name(input, MONTH_NAMES, "month").unwrap_or_else(|| parse_within_bounds(input, 1, 12))
+    }
+}
+
+fn day_of_week_value_parser(input: Span) -> ParseResult {
+    let parsed: ParseResult<Span> = alt((
+        tag("mon"),
+        tag("tue"),
+        tag("wed"),
+        tag("thu"),
+        tag("fri"),
+        tag("sat"),
+        tag("sun"),
+    ))(input);
     match parsed {
-        Done(i, _) => Done(i, ()),
-        Incomplete(inc) => Incomplete(inc),
-        Error(..) => parse_within_bounds(input, 0, 7)
+        // not "monday", which only starts with a name
+        Ok((i, _)) if !starts_with_letter(i) => Ok((i, ())),
+        _ => unknown_name(input, DAY_NAMES, "day of the week").unwrap_or_else(|| parse_within_bounds(input, 0, 7))
     }
 }
 
 // parse '*/2'
-fn parse_period(input: &[u8], value_parser: fn(&[u8]) -> IResult<&[u8], (), CrontabSyntaxError>) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let out = tag!(input, "*");
-    match out {
-       Done(i, _) => {
-            let next = tag!(i, "/");
-            match next {
-                Done(ii, _) => add_return_error!(ii, ErrorKind::Custom(CrontabSyntaxError::InvalidPeriodField), value_parser),
-                _ => Done(i, ()),
-            }
-        },
-       _ => Error(error_position!(ErrorKind::Custom(CrontabSyntaxError::InvalidPeriodField), input))
+fn parse_period(input: Span, value_parser: ValueParser) -> ParseResult {
+    let i = match after(input, "*") {
+        Some(i) => i,
+        None => return fail(CrontabSyntaxError::InvalidPeriodField, input),
+    };
+    match after(i, "/") {
+        Some(ii) => value_parser(ii).map_err(|e| e.map(|e| caused(CrontabSyntaxError::InvalidPeriodField, ii, e))),
+        None => Ok((i, ())),
     }
 }
 
-fn parse_range_or_value(input: &[u8], value_parser: fn(&[u8]) -> IResult<&[u8], (), CrontabSyntaxError>) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let parsed_value = value_parser(input);
-    match parsed_value {
-        Error(..) | Incomplete(..) => parsed_value,
-        Done(i, _) => {
-            let separator = fix_error!(i, CrontabSyntaxError, tag!("-"));
-            match separator {
-                Error(..) => parsed_value,
-                Incomplete(inc) => Incomplete(inc),
-                Done(ii, _) => value_parser(ii)
-            }
+// "mon-5" as "1-5"
+fn numeric_range(range: &str) -> String {
+    range.split('-').map(|value| {
+        let name = value.to_lowercase();
+        match (DAY_NAMES.iter().position(|day| *day == name), MONTH_NAMES.iter().position(|month| *month == name)) {
+            (Some(index), _) => index.to_string(),
+            (_, Some(index)) => (index + 1).to_string(),
+            _ => value.to_string(),
         }
+    }).collect::<Vec<_>>().join("-")
+}
+
+fn parse_range_or_value(input: Span, value_parser: ValueParser) -> ParseResult {
+    let (i, ()) = value_parser(input)?;
+    let ii = match after(i, "-") {
+        Some(ii) => ii,
+        None => return Ok((i, ())),
+    };
+    let (remaining, ()) = value_parser(ii)?;
+    if starts_with_letter(input) != starts_with_letter(ii) {
+        let range = &input.fragment()[..remaining.location_offset() - input.location_offset()];
+        return fail(CrontabSyntaxError::MixedRange { range: range.to_string(), numeric: numeric_range(range) }, input);
+    }
+    Ok((remaining, ()))
+}
+
+// parse '1-9/2': cron only accepts a step after a range (or '*')
+fn parse_stepped_range_or_value(input: Span, value_parser: ValueParser) -> ParseResult {
+    let (i, ()) = parse_range_or_value(input, value_parser)?;
+    let is_range = input.fragment()[..i.location_offset() - input.location_offset()].contains('-');
+    match after(i, "/") {
+        Some(ii) if is_range => value_parser(ii),
+        _ => Ok((i, ())),
     }
 }
 
-// parse 2,12-23
-fn parse_enum(input: &[u8], value_parser: fn(&[u8]) -> IResult<&[u8], (), CrontabSyntaxError>) -> IResult<&[u8], (), CrontabSyntaxError> {
-    add_return_error!(input, ErrorKind::Custom(CrontabSyntaxError::InvalidEnumField),
-        do_parse!(
-            separated_nonempty_list!(tag!(","), apply!(parse_range_or_value, value_parser)) >>
-            ()
-        )
-    )
+type RangeParser = for<'a> fn(Span<'a>, ValueParser) -> ParseResult<'a>;
+
+// parse 2,12-23,1-9/2 (the values are not collected: valid fields are parsed without allocating)
+fn parse_enum(input: Span, value_parser: ValueParser) -> ParseResult {
+    parse_list(input, value_parser, parse_stepped_range_or_value)
+}
+
+fn parse_list(input: Span, value_parser: ValueParser, range_parser: RangeParser) -> ParseResult {
+    let parsed = range_parser(input, value_parser).and_then(|(mut i, ())| {
+        // an invalid value after a ',' ("mon,wedn") is reported rather than the ','
+        while let Some(ii) = after(i, ",") {
+            i = range_parser(ii, value_parser)?.0;
+        }
+        Ok((i, ()))
+    });
+    parsed.map_err(|e| e.map(|e| match *e.error {
+        // the messages of a misspelled name or a mixed range say more than the one of the field
+        CrontabSyntaxError::UnknownName { .. } | CrontabSyntaxError::MixedRange { .. } | CrontabSyntaxError::NameInNumericField { .. }
+            if e.causes.is_empty() => e,
+        _ => caused(CrontabSyntaxError::InvalidEnumField, input, e),
+    }))
 }
 
 // a field is either a frequency (*/2) or an enumeration (2-4,5)
-fn parse_field(input: &[u8], value_parser: fn(&[u8]) -> IResult<&[u8], (), CrontabSyntaxError>) -> IResult<&[u8], (), CrontabSyntaxError> {
-    match peek!(input, tag!("*")) {
-        IResult::Error(..) => apply!(input, parse_enum, value_parser),
-        IResult::Done(..) => apply!(input, parse_period, value_parser),
-        Incomplete(e) => Incomplete(e)
+fn parse_field(input: Span, value_parser: ValueParser) -> ParseResult {
+    if input.fragment().starts_with('*') {
+        parse_period(input, value_parser)
+    } else {
+        parse_enum(input, value_parser)
     }
 }
 
+// Jenkins: parse 'H', 'H(0-7)', 'H/15' or 'H(0-29)/10'
+fn parse_hash(input: Span, value_parser: ValueParser) -> ParseResult {
+    let mut remaining = match after(input, "H") {
+        Some(i) => i,
+        None => return fail(CrontabSyntaxError::InvalidHashField, input),
+    };
+    if let Some(i) = after(remaining, "(") {
+        let (i, ()) = parse_range_or_value(i, value_parser)?;
+        remaining = match after(i, ")") {
+            Some(i) => i,
+            None => return fail(CrontabSyntaxError::InvalidHashField, i),
+        };
+    }
+    match after(remaining, "/") {
+        Some(i) => value_parser(i),
+        None => Ok((remaining, ())),
+    }
+}
 
-fn parse_field_separator(input: &[u8]) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let parsed = space(input);
-    match parsed {
-        Done(i, _) => Done(i, ()),
-        Error(_) => Error(error_position!(ErrorKind::Custom(CrontabSyntaxError::InvalidFieldSeparator), input)),
-        Incomplete(i) => Incomplete(i)
+// Jenkins fields may also be hashes (H)
+fn parse_jenkins_field(input: Span, value_parser: ValueParser) -> ParseResult {
+    if input.fragment().starts_with('H') {
+        parse_hash(input, value_parser)
+    } else {
+        parse_field(input, value_parser)
     }
 }
 
-fn is_valid_username<T: AsRef<str>>(name: &str, allowed_usernames: Option<&[T]>) -> bool {
-    if allowed_usernames.is_none() {
-        return true;
+// OpenBSD: parse '~', '0~30', '~30' or '10~', a random value in the range (the missing bounds are those of the field),
+// as well as ranges and values
+fn parse_random_range(input: Span, value_parser: ValueParser) -> ParseResult {
+    let i = if input.fragment().starts_with('~') { input } else { value_parser(input)?.0 };
+    match after(i, "~") {
+        Some(ii) if ii.fragment().is_empty() || ii.fragment().starts_with(|c: char| is_space(c) || c == ',') => Ok((ii, ())),
+        Some(ii) => value_parser(ii),
+        None => match after(i, "-") {
+            Some(ii) => value_parser(ii),
+            None => Ok((i, ())),
+        },
     }
-    (*(allowed_usernames.unwrap())).iter().any(|el| el.as_ref() == name)
 }
 
+fn parse_openbsd_field(input: Span, value_parser: ValueParser) -> ParseResult {
+    if input.fragment().starts_with('*') {
+        parse_period(input, value_parser)
+    } else {
+        parse_list(input, value_parser, parse_random_range)
+    }
+}
 
-fn parse_user<'a, 'b, T: AsRef<str> + 'b>(input: &'a[u8], allowed_usernames: Option<&'b[T]>) -> IResult<&'a[u8], (), CrontabSyntaxError> {
-    let parsed = alphanumeric(input);
-    match parsed {
-        Done(i, o) => {
-            from_utf8(o).ok().map(|name| is_valid_username(name, allowed_usernames)).map(|valid|
-            if valid {
-                Done(i, ())
-            } else {
-                Error(error_position!(ErrorKind::Custom(CrontabSyntaxError::InvalidUsername), input))
-            }).unwrap_or(Error(error_position!(ErrorKind::Custom(CrontabSyntaxError::InvalidUsername), input)))
-        },
-        Error(_) => Error(error_position!(ErrorKind::Custom(CrontabSyntaxError::InvalidUsername), input)),
-        Incomplete(i) => Incomplete(i)
+// POSIX: numbers only
+fn parse_posix_value(input: Span, value_parser: ValueParser) -> ParseResult {
+    if starts_with_letter(input) {
+        return fail(CrontabSyntaxError::NotPosix { construct: "names of months and days" }, input);
     }
+    value_parser(input)
 }
 
-pub struct CrontabParserOptions<'a, T: AsRef<str> + 'a> {
-    pub allowed_usernames: Option<&'a [T]>
+fn parse_posix_range(input: Span, value_parser: ValueParser) -> ParseResult {
+    let (i, ()) = parse_posix_value(input, value_parser)?;
+    match after(i, "-") {
+        Some(ii) => parse_posix_value(ii, value_parser),
+        None => Ok((i, ())),
+    }
 }
 
-// consume all input, make sure there are not special characters in the command line
-fn parse_command_line(input: &[u8]) -> IResult<&[u8], (), CrontabSyntaxError> {
-    // cron limitation
-    // see https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=686223
-    if (*input).len() > 999 {
-        return Error(error_position!(ErrorKind::Custom(
-            CrontabSyntaxError::InvalidCommandLine { reason: "command line can not exceed 999 characters".to_string() }),
-            input
-        ))
+// POSIX: '*', or a list of values and ranges (without steps)
+fn parse_posix_field(input: Span, value_parser: ValueParser) -> ParseResult {
+    match after(input, "*") {
+        Some(i) if i.fragment().starts_with('/') => fail(CrontabSyntaxError::NotPosix { construct: "steps" }, i),
+        Some(i) => Ok((i, ())),
+        None => parse_list(input, value_parser, parse_posix_range),
     }
-    // FIXME: this is a dirty and inaccurate way of checking whether the '%' is escaped
-    // TODO: give the correct error position
-    let reason = "special char % should not be used unescaped".to_string();
-    let error = Error(error_position!(ErrorKind::Custom(
-                CrontabSyntaxError::InvalidCommandLine { reason }),
-                input
-            ));
-    if let Some(first_char) = input.first() {
-        if *first_char == b'%' {
-            return error;
-        }
+}
+
+// FreeBSD: the @ shortcuts, which stand for the five fields
+const FREEBSD_NICKNAMES: &[&str] = &["@reboot", "@yearly", "@annually", "@monthly", "@weekly", "@daily", "@midnight",
+                                                     "@hourly", "@every_minute", "@every_second"];
+
+// parse a FreeBSD shortcut, or '@' followed by the number of seconds between runs ("@300")
+fn parse_freebsd_nickname(input: Span) -> ParseResult {
+    let (remaining, nickname) = take_till::<_, _, SyntaxError>(is_space)(input)?;
+    let name = *nickname.fragment();
+    let seconds = name[1..].parse::<u32>().map(|seconds| seconds > 0).unwrap_or(false);
+    if FREEBSD_NICKNAMES.contains(&name) || seconds {
+        return Ok((remaining, ()));
+    }
+    let suggestion = closest_match(name, FREEBSD_NICKNAMES).map(|s| s.to_string());
+    fail(CrontabSyntaxError::UnknownName { field: "shortcut", name: name.to_string(), suggestion }, input)
+}
+
+
+fn parse_field_separator(input: Span) -> ParseResult {
+    match space1::<_, SyntaxError>(input) {
+        Ok((i, _)) => Ok((i, ())),
+        Err(_) => fail(CrontabSyntaxError::InvalidFieldSeparator, input),
+    }
+}
+
+// The input after a field (whatever it holds), to go on parsing after an invalid one
+fn skip_field(input: Span) -> Span {
+    take_till::<_, _, SyntaxError>(is_space)(input).map(|(i, _)| i).unwrap_or(input)
+}
+
+fn is_valid_username<T: AsRef<str>>(name: &str, allowed_usernames: Option<&[T]>) -> bool {
+    allowed_usernames.map(|allowed| allowed.iter().any(|el| el.as_ref() == name)).unwrap_or(true)
+}
+
+
+// the portable user names of POSIX ("www-data", "first.last", "_apt"), which do not start with '-'
+fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-'
+}
+
+fn parse_user<'a, 'b, T: AsRef<str> + 'b>(input: Span<'a>, allowed_usernames: Option<&'b [T]>) -> ParseResult<'a, Span<'a>> {
+    let (i, user) = match take_while1::<_, _, SyntaxError>(is_username_char)(input) {
+        Ok(parsed) if !parsed.1.fragment().starts_with('-') => parsed,
+        _ => return fail(CrontabSyntaxError::InvalidUsername, input),
+    };
+    let name = *user.fragment();
+    if is_valid_username(name, allowed_usernames) {
+        Ok((i, user))
+    } else {
+        let suggestion = allowed_usernames.and_then(|allowed| closest_match(name, allowed)).map(|s| s.to_string());
+        fail(CrontabSyntaxError::UnknownUsername { username: name.to_string(), suggestion }, input)
+    }
+}
+
+type FieldParser = for<'a> fn(Span<'a>, ValueParser) -> ParseResult<'a>;
+
+// Quartz expressions: '?' (no specific value) in one of the day fields, which is '*' for the extended dialect
+fn parse_question_mark(input: Span, day_field: bool, dialect: Dialect) -> ParseResult {
+    let extended = dialect == Dialect::Extended;
+    match after(input, "?") {
+        Some(i) if extended && day_field && (i.fragment().is_empty() || i.fragment().starts_with(is_space)) => Ok((i, ())),
+        _ => fail(CrontabSyntaxError::QuestionMark { extended }, input),
     }
-    for slice in input.windows(2) {
-        let c1 = slice[0];
-        let c2 = slice[1];
-        if c2 == b'%' && c1 != b'\\' {
-            return error;
+}
+
+// "L" (the last day of the month) as the day of the month field, "5L" (the last Friday of the month) as the day of
+// the week field: extended dialect only
+fn parse_last_day(input: Span, day_of_week: bool) -> ParseResult {
+    let field = input.fragment().split(is_space).next().unwrap_or("");
+    let parsed = if day_of_week && field.len() > 1 && field.ends_with('L') {
+        // the day is parsed on its own, as the name parsers would take the 'L' as a letter of the name
+        let day = input.slice(..field.len() - 1);
+        match day_of_week_value_parser(day) {
+            Ok((i, ())) if i.fragment().is_empty() => after(input.slice(field.len() - 1..), "L"),
+            _ => None,
         }
+    } else if day_of_week {
+        None
+    } else {
+        after(input, "L")
+    };
+    match parsed {
+        Some(i) if i.fragment().is_empty() || i.fragment().starts_with(is_space) => Ok((i, ())),
+        _ => fail(CrontabSyntaxError::InvalidLastDay, input),
     }
-    Done(&[], ())
 }
 
-fn parse_comment(input: &[u8]) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let out = fix_error!(input, CrontabSyntaxError, do_parse!(
-        take_while_s!(is_space) >>
-        tag!("#") >>
-        ()
-    ));
-    match out {
-        Done(..) => Done(&[], ()),
-        Error(e) => Error(e),
-        Incomplete(e) => Incomplete(e)
+// "mon#2" (the second Monday of the month) as the day of the week field: extended dialect only
+fn parse_nth_day(input: Span) -> ParseResult {
+    let field = input.fragment().split(is_space).next().unwrap_or("");
+    let index = field.find('#').unwrap_or(0);
+    // as in parse_last_day, the day is parsed on its own
+    match day_of_week_value_parser(input.slice(..index)) {
+        Ok((i, ())) if index > 0 && i.fragment().is_empty() => (),
+        _ => return fail(CrontabSyntaxError::InvalidNthDay, input),
+    }
+    // no month has a sixth Monday
+    let (i, ()) = parse_within_bounds(input.slice(index + 1..), 1, 5)?;
+    if i.fragment().is_empty() || i.fragment().starts_with(is_space) {
+        Ok((i, ()))
+    } else {
+        fail(CrontabSyntaxError::InvalidNthDay, input)
+    }
+}
+
+// The five time and date fields of a job, in the syntax of `dialect`: the input after them, unless some are missing.
+// The errors of all the invalid fields are added to `errors`.
+fn parse_schedule_fields<'a>(input: Span<'a>, dialect: Dialect, errors: &mut Vec<SyntaxError<'a>>) -> Option<Span<'a>> {
+    let field_parser: FieldParser = match dialect {
+        Dialect::Jenkins => parse_jenkins_field,
+        Dialect::OpenBsd => parse_openbsd_field,
+        Dialect::Posix => parse_posix_field,
+        _ => parse_field,
+    };
+    let value_parsers: [ValueParser; 5] = [
+        minute_value_parser,
+        hour_value_parser,
+        day_of_month_value_parser,
+        month_value_parser,
+        day_of_week_value_parser,
+    ];
+    let mut remaining = input;
+    for (index, &value_parser) in value_parsers.iter().enumerate() {
+        if index > 0 {
+            remaining = match parse_field_separator(remaining) {
+                Ok((i, ())) => i,
+                Err(e) => { errors.push(syntax_error(e, remaining)); return None; }
+            };
+        }
+        let field = remaining.fragment().split(is_space).next().unwrap_or("");
+        let parsed = if remaining.fragment().starts_with('?') {
+            parse_question_mark(remaining, index == 2 || index == 4, dialect)
+        } else if dialect == Dialect::Extended && index == 4 && field.contains('#') {
+            parse_nth_day(remaining)
+        } else if dialect == Dialect::Extended && (index == 2 || index == 4) && field.contains('L') {
+            parse_last_day(remaining, index == 4)
+        } else {
+            field_parser(remaining, value_parser)
+        };
+        remaining = match parsed {
+            Ok((i, ())) => i,
+            Err(e) => {
+                errors.push(syntax_error(e, remaining));
+                // go on with the next fields, to report all the invalid ones
+                skip_field(remaining)
+            }
+        };
     }
+    Some(remaining)
 }
 
-// TODO: more checks for this parser
-fn parse_environnment_variable(input: &[u8]) -> IResult<&[u8], (), CrontabSyntaxError> {
-    let out = fix_error!(input, CrontabSyntaxError,
-            do_parse!(
-                alphanumeric >>
-                tag!("=") >>
-                ()
-            ));
-    match out {
-        Done(..) => Done(&[], ()),
-        Error(e) => Error(e),
-        Incomplete(e) => Incomplete(e)
+// Check a schedule on its own ("*/5 * * * *"), as found outside of crontabs
+pub fn check_schedule(schedule: &str) -> Result<(), String> {
+    check_schedule_fields(schedule, Dialect::Vixie)
+}
+
+// Check a schedule of a Jenkins trigger ("H H(0-7) * * *")
+pub fn check_jenkins_schedule(schedule: &str) -> Result<(), String> {
+    check_schedule_fields(schedule, Dialect::Jenkins)
+}
+
+// Check a schedule in the syntax of `dialect`
+pub fn check_schedule_fields(schedule: &str, dialect: Dialect) -> Result<(), String> {
+    let mut errors = vec![];
+    match parse_schedule_fields(Span::new(schedule.trim()), dialect, &mut errors) {
+        Some(_) if !errors.is_empty() => Err(walk_errors(&errors)),
+        Some(remaining) if remaining.fragment().trim().is_empty() => Ok(()),
+        Some(remaining) => Err(format!("unexpected '{}' after the schedule", format_position(remaining).trim())),
+        None => Err(walk_errors(&errors)),
     }
+}
 
+pub struct CrontabParserOptions<'a, T: AsRef<str> + 'a> {
+    pub allowed_usernames: Option<&'a [T]>,
+    // users (typically root and other uid 0 accounts) jobs may not run as, unless explicitly allowed
+    pub forbidden_usernames: Option<&'a [T]>,
+    pub max_command_length: usize,
+    // user crontabs (as edited with `crontab -e`) have no user field: their jobs run as the crontab's owner
+    pub crontab_owner: Option<&'a str>,
+    // the syntax of the schedules
+    pub dialect: Dialect,
 }
 
-fn parse_empty_line(input: &[u8]) -> IResult<&[u8], (), CrontabSyntaxError> {
-    for c in input {
-        if !is_space(*c) {
-            return Error(error_position!(ErrorKind::Space, input));
+// cron limitation
+// see https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=686223
+pub const DEBIAN_MAX_COMMAND_LENGTH: usize = 999;
+// longer than the lines any cron reads (cronie's commands are up to 128 KiB): longer lines are rejected before being
+// parsed, so that the errors (which quote the invalid names) stay small whatever the input
+pub const MAX_LINE_LENGTH: usize = 256 * 1024;
+
+impl<'a, T: AsRef<str> + 'a> Default for CrontabParserOptions<'a, T> {
+    fn default() -> Self {
+        CrontabParserOptions {
+            allowed_usernames: None,
+            forbidden_usernames: None,
+            max_command_length: DEBIAN_MAX_COMMAND_LENGTH,
+            crontab_owner: None,
+            dialect: Dialect::default(),
         }
     }
-    return Done(&[], ());
 }
 
-// TODO: the caller should not have to depend on symbols exported by nom
-pub fn parse_crontab<'a, T: AsRef<str>>(input: &'a[u8], options: &CrontabParserOptions<T>) -> IResult<&'a[u8], (), CrontabSyntaxError> {
-    // We do not use the alt_complete! combinator because we want to have nice error codes
-    // Try to parse the line as an empty line, then if it fails as a comment, then as an
-    // environment variable assignation, then as an actual crontab line
-    let mut result = parse_empty_line(input);
-    if let Done(..) = result {
-        return result;
+// The owner of a user crontab does not appear on its lines: it is checked once for the whole file
+pub fn check_crontab_owner<T: AsRef<str>>(options: &CrontabParserOptions<T>) -> Result<(), CrontabSyntaxError> {
+    match options.crontab_owner {
+        Some(owner) if !is_valid_username(owner, options.allowed_usernames) => {
+            let suggestion = options.allowed_usernames.and_then(|allowed| closest_match(owner, allowed)).map(|s| s.to_string());
+            Err(CrontabSyntaxError::UnknownUsername { username: owner.to_string(), suggestion })
+        },
+        _ => Ok(()),
     }
-    result = parse_comment(input);
-    if let Done(..) = result {
-        return result;
+}
+
+// shell comment suppressing the forbidden user check for a single line
+pub const ALLOW_ROOT_MARKER: &str = "crontabcheck: allow-root";
+
+fn has_allow_root_marker(command: &str) -> bool {
+    command.split('#').skip(1).any(|comment| comment.trim() == ALLOW_ROOT_MARKER)
+}
+
+// `position` is where the user is: the user field, or the whole line for user crontabs
+fn check_forbidden_user<'a, T: AsRef<str>>(user: &str, position: Span<'a>, command: &str, forbidden_usernames: Option<&[T]>) -> Result<(), SyntaxError<'a>> {
+    let forbidden = forbidden_usernames.map(|forbidden| forbidden.iter().any(|el| el.as_ref() == user)).unwrap_or(false);
+    if forbidden && !has_allow_root_marker(command) {
+        return Err(SyntaxError {
+            error: Box::new(CrontabSyntaxError::ForbiddenUsername { username: user.to_string() }),
+            position,
+            causes: vec![],
+        });
+    }
+    Ok(())
+}
+
+// consume all input, make sure there are not special characters in the command line
+fn parse_command_line(input: Span, max_length: usize) -> ParseResult<Span> {
+    let (remaining, command) = rest::<_, SyntaxError>(input)?;
+    let text = command.fragment().as_bytes();
+    if text.len() > max_length {
+        return fail(CrontabSyntaxError::InvalidCommandLine { reason: format!("command line can not exceed {} characters", max_length) }, input);
     }
-    result = parse_environnment_variable(input);
-    if let Done(..) = result {
-        return result;
+    // FIXME: this is a dirty and inaccurate way of checking whether the '%' is escaped
+    let unescaped_percent = (0..text.len()).find(|&index| text[index] == b'%' && (index == 0 || text[index - 1] != b'\\'));
+    if let Some(index) = unescaped_percent {
+        let reason = "special char % should not be used unescaped".to_string();
+        return fail(CrontabSyntaxError::InvalidCommandLine { reason }, input.slice(index..));
     }
+    Ok((remaining, command))
+}
 
-    // actual crontab line
-    return do_parse!(input,
-        apply!(parse_field, minute_value_parser) >>
-        parse_field_separator >>
-        apply!(parse_field, hour_value_parser) >>
-        parse_field_separator >>
-        apply!(parse_field, day_of_month_value_parser) >>
-        parse_field_separator >>
-        apply!(parse_field, month_value_parser) >>
-        parse_field_separator >>
-        apply!(parse_field, day_of_week_value_parser) >>
-        parse_field_separator >>
-        apply!(parse_user, options.allowed_usernames) >>
-        parse_field_separator >>
-        parse_command_line >>
-        ()
-    )
+fn trim_spaces(input: Span) -> Span {
+    let text = *input.fragment();
+    let start = text.len() - text.trim_start_matches(is_space).len();
+    let end = text.trim_end_matches(is_space).len();
+    if start >= end {
+        input.slice(start..start)
+    } else {
+        input.slice(start..end)
+    }
 }
 
+// position of the first character not allowed by POSIX in an environment variable name
+fn invalid_environment_name_position(name: &str) -> Option<usize> {
+    name.char_indices()
+        .find(|&(i, c)| !(c.is_ascii_alphabetic() || c == '_' || (i > 0 && c.is_ascii_digit())))
+        .map(|(i, _)| i)
+}
 
-fn format_error(error: &ErrorKind<CrontabSyntaxError>) -> String {
-    match *error {
-        ErrorKind::Custom(ref e) => e.to_string(),
-        ref e => format!("error: {:?}", e).to_string()  // this should not happen
+// NAME=value, with optional spaces around the '=' and an optionally quoted value (as cron's load_env does)
+fn parse_environnment_variable(input: Span) -> ParseResult<CrontabLine> {
+    let not_an_assignment = || Err(nom::Err::Error(SyntaxError::from_error_kind(input, ErrorKind::AlphaNumeric)));
+    let line = trim_spaces(input);
+    let name_length = line.fragment().find(|c: char| is_space(c) || c == '=').unwrap_or(line.fragment().len());
+    let name = line.slice(..name_length);
+    let assignment = trim_spaces(line.slice(name_length..));
+    if name.fragment().is_empty() || !assignment.fragment().starts_with('=') {
+        return not_an_assignment();
     }
+    if let Some(index) = invalid_environment_name_position(name.fragment()) {
+        let position = name.slice(index..);
+        let character = position.fragment().chars().next().unwrap_or('=');
+        return fail(CrontabSyntaxError::InvalidEnvName { name: name.fragment().to_string(), character }, position);
+    }
+    let value = trim_spaces(assignment.slice(1..));
+    let text = *value.fragment();
+    let value = match text.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => {
+            if text.len() < 2 || !text.ends_with(quote) {
+                return fail(CrontabSyntaxError::UnterminatedQuote, value);
+            }
+            value.slice(1..text.len() - 1)
+        },
+        _ => value
+    };
+    let end = input.slice(input.fragment().len()..);
+    Ok((end, CrontabLine::EnvironmentVariable { name: name.fragment(), value: value.fragment() }))
 }
 
-pub fn walk_errors(errs: &[nom::Err<&[u8], CrontabSyntaxError>]) -> String {
+// Parse a line of a crontab. The errors of all its invalid fields are reported, with their position in the line.
+pub fn parse_crontab<'a, T: AsRef<str>>(line: &'a str, options: &CrontabParserOptions<'a, T>) -> Result<CrontabLine<'a>, Vec<SyntaxError<'a>>> {
+    let input = Span::new(line);
+    if line.len() > MAX_LINE_LENGTH {
+        return Err(vec![SyntaxError { error: Box::new(CrontabSyntaxError::LineTooLong { length: line.len(), max: MAX_LINE_LENGTH }), position: input, causes: vec![] }]);
+    }
+    // Blank lines and comments (most of the lines) are told apart by their first character, then try to parse the
+    // line as an environment variable assignation, then if it fails as an actual crontab line
+    match line.trim_start_matches(is_space).chars().next() {
+        None => return Ok(CrontabLine::Empty),
+        Some('#') => return Ok(CrontabLine::Comment),
+        _ => (),
+    }
+    match parse_environnment_variable(input) {
+        Ok((_, parsed)) => return Ok(parsed),
+        // not an assignment: a job
+        Err(nom::Err::Error(ref e)) if is_nom_error(e) => (),
+        Err(e) => return Err(vec![syntax_error(e, input)]),
+    }
+
+    // actual crontab line, which cron reads from its first non-blank character
+    let mut errors = vec![];
+    let indentation = line.len() - line.trim_start_matches(is_space).len();
+    let fields = input.slice(indentation..);
+    if options.dialect == Dialect::Posix && fields.fragment().starts_with('@') {
+        return Err(vec![SyntaxError { error: Box::new(CrontabSyntaxError::NotPosix { construct: "@ shortcuts" }), position: fields, causes: vec![] }]);
+    }
+    let remaining = if options.dialect == Dialect::FreeBsd && fields.fragment().starts_with('@') {
+        match parse_freebsd_nickname(fields) {
+            Ok((i, ())) => i,
+            Err(e) => { errors.push(syntax_error(e, fields)); skip_field(fields) }
+        }
+    } else {
+        match parse_schedule_fields(fields, options.dialect, &mut errors) {
+            Some(remaining) => remaining,
+            None => return Err(errors),
+        }
+    };
+    let schedule = &line[indentation..remaining.location_offset()];
+    let remaining = match parse_field_separator(remaining) {
+        Ok((i, ())) => i,
+        Err(e) => { errors.push(syntax_error(e, remaining)); return Err(errors); }
+    };
+    let (user, user_position, remaining) = match options.crontab_owner {
+        Some(owner) => (owner, input, remaining),
+        None => {
+            let (i, user) = match parse_user(remaining, options.allowed_usernames) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    errors.push(syntax_error(e, remaining));
+                    (skip_field(remaining), remaining)
+                }
+            };
+            match parse_field_separator(i) {
+                Ok((ii, ())) => (*user.fragment(), user, ii),
+                Err(e) => { errors.push(syntax_error(e, i)); return Err(errors); }
+            }
+        }
+    };
+    let command = match parse_command_line(remaining, options.max_command_length) {
+        Ok((_, command)) => *command.fragment(),
+        Err(e) => { errors.push(syntax_error(e, remaining)); return Err(errors); }
+    };
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    check_forbidden_user(user, user_position, command, options.forbidden_usernames).map_err(|e| vec![e])?;
+    Ok(CrontabLine::Job { schedule, user, command })
+}
+
+
+pub fn walk_errors(errors: &[SyntaxError]) -> String {
     let mut strings: Vec<String> = vec![];
-    for err in errs {
-        let formatted = match *err {
-            nom::Err::Code(ref kind) => format_error(kind),
-            nom::Err::Node(ref kind, ref next_error) => format_error(kind) + "\nCaused by: " + &walk_errors(next_error),
-            nom::Err::Position(ref kind, position) => format_error(kind) + " (at '" + format_position(position).as_str() + "')",
-            nom::Err::NodePosition(ref  kind, position, ref next_error) => format_error(kind) + " (at '" + format_position(position).as_str() + "')\nCaused by: " + &walk_errors(next_error),
-        };
+    for error in errors {
+        let mut formatted = format!("{} (at '{}')", error.error, format_position(error.position));
+        if !error.causes.is_empty() {
+            formatted = formatted + "\nCaused by: " + &walk_errors(&error.causes);
+        }
         strings.push(formatted);
     }
     strings.join("\n\n")
 }
 
-fn format_position(pos: &[u8]) -> String {
-    let mut s = from_utf8(pos).unwrap_or("(invalid UTF-8)").to_string();
-    s.truncate(15);
-    s
+// Byte offset in a line of the (first) syntax error it has, if any (e.g. to underline it in an editor)
+pub fn error_offset<T: AsRef<str>>(line: &str, options: &CrontabParserOptions<T>) -> Option<usize> {
+    parse_crontab(line, options).err().and_then(|errors| errors.first().map(SyntaxError::offset))
+}
+
+fn format_position(position: Span) -> String {
+    position.fragment().chars().take(15).collect()
 }
 
 #[cfg(test)]
 mod tests {
 
-    use nom::IResult::{Error, Done};
     use parser::*;
 
+    // The input left by a parser, or its error
+    fn remaining<'a, O>(parsed: ParseResult<'a, O>) -> Result<&'a str, CrontabSyntaxError> {
+        match parsed {
+            Ok((remaining, _)) => Ok(*remaining.fragment()),
+            Err(e) => Err(*syntax_error(e, Span::new("")).error),
+        }
+    }
+
     #[test]
     fn test_format_errors() {
         let usernames = ["root"];
-        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames) };
-        let parsed = parse_crontab("2-10 * */4 * mon  root /usr/local/bin yay".as_bytes(), options);
-        match parsed {
-            Error(e) => {
-                let errors = [e];
-                println!("{}", walk_errors(&errors));
+        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames), ..Default::default() };
+        let errors = parse_crontab("2-10 * */4 * mon  root /usr/local/bin yay", options);
+        assert_eq!(errors, Ok(CrontabLine::Job { schedule: "2-10 * */4 * mon", user: "root", command: "/usr/local/bin yay" }));
+        let errors = parse_crontab("0 25 * * * root /usr/local/bin yay", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "could not parse the field (at '25 * * * root /')\n\
+                                          Caused by: value 25 out of bounds (accepted: 0 to 23) (at '25 * * * root /')");
+        assert!(parse_crontab("0 24 * * * root /usr/local/bin yay", options).is_err());
+        assert!(parse_crontab("0 0 0 * * root /usr/local/bin yay", options).is_err());
+    }
+
+    #[test]
+    fn test_parse_valid_crontab() {
+        let usernames = ["root"];
+        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames), ..Default::default() };
+        let out = parse_crontab("* * * * * root /usr/local/bin yay", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "* * * * *", user: "root", command: "/usr/local/bin yay" }));
+
+        let out = parse_crontab("8 * * * * root /usr/local/bin yay", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "8 * * * *", user: "root", command: "/usr/local/bin yay" }));
+
+        let out = parse_crontab("*/3 2 * * * root /usr/local/bin yay", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "*/3 2 * * *", user: "root", command: "/usr/local/bin yay" }));
+
+        let out = parse_crontab("1-2 * * * * root /usr/local/bin yay", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "1-2 * * * *", user: "root", command: "/usr/local/bin yay" }));
+
+        let out = parse_crontab("1-2 * * * mon,tue root /usr/local/bin yay", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "1-2 * * * mon,tue", user: "root", command: "/usr/local/bin yay" }));
+
+        let out = parse_crontab("#This is a comment", options);
+        assert_eq!(out, Ok(CrontabLine::Comment));
+
+        let out = parse_crontab(" \t# 0 2 * * * root /usr/bin/backup", options);
+        assert_eq!(out, Ok(CrontabLine::Comment));
+
+        let out = parse_crontab("VARIABLE=VALUE", options);
+        assert_eq!(out, Ok(CrontabLine::EnvironmentVariable { name: "VARIABLE", value: "VALUE" }));
+
+        let out = parse_crontab("   ", options);
+        assert_eq!(out, Ok(CrontabLine::Empty));
+
+        let out = parse_crontab(" \t0 2 * * * root /usr/bin/backup", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "0 2 * * *", user: "root", command: "/usr/bin/backup" }));
+    }
+
+    #[test]
+    fn test_openbsd_random_values() {
+        let options = &CrontabParserOptions::<&str> { dialect: Dialect::OpenBsd, ..Default::default() };
+        for schedule in ["0~30 * * * *", "~ ~ * * *", "~15 10~ * * *", "0 1~5,20~23 * * mon~fri", "1-5 2 * * *"].iter() {
+            let line = format!("{} root /usr/bin/backup", schedule);
+            assert_eq!(parse_crontab(&line, options), Ok(CrontabLine::Job { schedule, user: "root", command: "/usr/bin/backup" }));
+        }
+        let errors = parse_crontab("0~60 * * * * root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "could not parse the field (at '0~60 * * * * ro')\n\
+                                          Caused by: value 60 out of bounds (accepted: 0 to 59) (at '60 * * * * root')");
+        assert!(parse_crontab("25~ * * * * root /usr/bin/backup", options).is_ok());
+        assert!(parse_crontab("* 25~ * * * root /usr/bin/backup", options).is_err());
+        assert!(parse_crontab("0~30 * * * * root /usr/bin/backup", &CrontabParserOptions::<&str>::default()).is_err());
+    }
+
+    #[test]
+    fn test_freebsd_nicknames() {
+        let options = &CrontabParserOptions::<&str> { dialect: Dialect::FreeBsd, ..Default::default() };
+        for schedule in ["@every_second", "@every_minute", "@daily", "@300", "0 2 * * *"].iter() {
+            let line = format!("{}\troot /usr/bin/poll", schedule);
+            assert_eq!(parse_crontab(&line, options), Ok(CrontabLine::Job { schedule, user: "root", command: "/usr/bin/poll" }));
+        }
+        let errors = parse_crontab("@every_secnd root /usr/bin/poll", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "unknown shortcut '@every_secnd', did you mean '@every_second'? (at '@every_secnd ro')");
+        assert!(parse_crontab("@0 root /usr/bin/poll", options).is_err());
+        assert!(parse_crontab("@every_second root /usr/bin/poll", &CrontabParserOptions::<&str>::default()).is_err());
+    }
+
+    #[test]
+    fn test_posix() {
+        let options = &CrontabParserOptions::<&str> { dialect: Dialect::Posix, ..Default::default() };
+        assert!(parse_crontab("0,30 8-18 * 1-6 1-5 root /usr/bin/poll", options).is_ok());
+        let error = |line| walk_errors(&parse_crontab(line, options).unwrap_err());
+        assert_eq!(error("*/5 * * * * root /usr/bin/poll"), "steps are an extension of Vixie cron, not POSIX (at '/5 * * * * root')");
+        assert_eq!(error("0 2 * * mon root /usr/bin/backup"), "could not parse the field (at 'mon root /usr/b')\n\
+                                                               Caused by: names of months and days are an extension of Vixie cron, not POSIX (at 'mon root /usr/b')");
+        assert_eq!(error("@daily root /usr/bin/backup"), "@ shortcuts are an extension of Vixie cron, not POSIX (at '@daily root /us')");
+        assert!(parse_crontab("0 2 * jan-jun * root /usr/bin/backup", options).is_err());
+    }
+
+    #[test]
+    fn test_mixed_ranges() {
+        let options = &CrontabParserOptions::<&str>::default();
+        let errors = parse_crontab("0 2 * * mon-5 root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "range 'mon-5' mixes a name and a number, which crons read differently: write '1-5' (at 'mon-5 root /usr')");
+        let errors = parse_crontab("0 2 * 3-dec,jan * root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(*errors[0].error, CrontabSyntaxError::MixedRange { range: "3-dec".to_string(), numeric: "3-12".to_string() });
+        assert!(parse_crontab("0 2 * mar-dec 1-5 root /usr/bin/backup", options).is_ok());
+    }
+
+    #[test]
+    fn test_names_in_numeric_fields() {
+        let options = &CrontabParserOptions::<&str>::default();
+        let errors = parse_crontab("jan * * * * root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "month and day names are not valid in the minute field ('jan') (at 'jan * * * * roo')");
+        let errors = parse_crontab("0 2,noon * * * root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "'noon' is not a number, which the hour field takes (at 'noon * * * root')");
+        let errors = parse_crontab("0 2 Mon * * root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(*errors[0].error, CrontabSyntaxError::NameInNumericField { field: "day of the month", name: "Mon".to_string() });
+    }
+
+    #[test]
+    fn test_question_mark() {
+        let options = &CrontabParserOptions::<&str> { dialect: Dialect::Extended, ..Default::default() };
+        assert!(parse_crontab("0 2 ? * mon root /usr/bin/backup", options).is_ok());
+        assert!(parse_crontab("0 2 1 * ? root /usr/bin/backup", options).is_ok());
+        let errors = parse_crontab("0 ? * * * root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(*errors[0].error, CrontabSyntaxError::QuestionMark { extended: true });
+        assert!(parse_crontab("0 2 1,? * * root /usr/bin/backup", options).is_err());
+        let errors = parse_crontab("0 2 ? * mon root /usr/bin/backup", &CrontabParserOptions::<&str>::default()).unwrap_err();
+        assert!(walk_errors(&errors).starts_with("'?' is not cron syntax: this looks like a Quartz expression"));
+    }
+
+    #[test]
+    fn test_last_day() {
+        let options = &CrontabParserOptions::<&str> { dialect: Dialect::Extended, ..Default::default() };
+        assert!(parse_crontab("0 23 L * * root /usr/bin/report", options).is_ok());
+        assert!(parse_crontab("0 23 ? * friL root /usr/bin/report", options).is_ok());
+        assert!(parse_crontab("0 23 * * 5L root /usr/bin/report", options).is_ok());
+        for line in &["0 23 1,L * * root /usr/bin/report", "0 23 * * L root /usr/bin/report", "0 23 * * 8L root /usr/bin/report"] {
+            let errors = parse_crontab(line, options).unwrap_err();
+            assert_eq!(*errors[0].error, CrontabSyntaxError::InvalidLastDay);
+        }
+        assert!(parse_crontab("0 23 L * * root /usr/bin/report", &CrontabParserOptions::<&str>::default()).is_err());
+    }
+
+    #[test]
+    fn test_nth_day() {
+        let options = &CrontabParserOptions::<&str> { dialect: Dialect::Extended, ..Default::default() };
+        assert!(parse_crontab("0 9 ? * mon#2 root /usr/bin/report", options).is_ok());
+        assert!(parse_crontab("0 9 * * 5#5 root /usr/bin/report", options).is_ok());
+        let errors = parse_crontab("0 9 * * mon#6 root /usr/bin/report", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "value 6 out of bounds (accepted: 1 to 5) (at '6 root /usr/bin')");
+        let errors = parse_crontab("0 9 * * mon#0 root /usr/bin/report", options).unwrap_err();
+        assert_eq!(*errors[0].error, CrontabSyntaxError::ValueOutOfBounds { value: 0, min: 1, max: 5 });
+        for line in &["0 9 * * #2 root /usr/bin/report", "0 9 * * mon#2,fri root /usr/bin/report", "0 9 * * 1-5#2 root /usr/bin/report"] {
+            let errors = parse_crontab(line, options).unwrap_err();
+            assert_eq!(*errors[0].error, CrontabSyntaxError::InvalidNthDay);
+        }
+        assert!(parse_crontab("0 9 * * mon#2 root /usr/bin/report", &CrontabParserOptions::<&str>::default()).is_err());
+    }
+
+    #[test]
+    fn test_owned_line() {
+        let options = &CrontabParserOptions::<&str>::default();
+        let line = String::from("0 2 * * * root /usr/bin/backup");
+        let owned = parse_crontab(&line, options).unwrap().to_owned_line();
+        drop(line);
+        assert_eq!(owned.as_line(), CrontabLine::Job { schedule: "0 2 * * *", user: "root", command: "/usr/bin/backup" });
+    }
+
+    #[test]
+    fn test_adversarial_lines() {
+        let usernames = ["root"];
+        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames), forbidden_usernames: Some(&usernames), ..Default::default() };
+        let lines = ["%", "=", "\"", "A=\"", "A='", "*/", "*/-", "1-", "1,", ",", "-", "H(", "\u{feff}0 2 * * * root /bin/true",
+                     "0 2 * * * root", "0 2 * * * é /bin/true", "0 2 * * mоn root /bin/true", "99999999999999999999 * * * * root /",
+                     "0\t2\t*\t*\t*\troot\t%", "é=1", "0 2 * * * root /bin/true \\%%"];
+        for line in lines.iter() {
+            if let Err(errors) = parse_crontab(line, options) {
+                assert!(!errors.is_empty());
+                walk_errors(&errors);
+            }
+            error_offset(line, options);
+        }
+
+        let line = format!("0 2 * * * root /bin/{}", "a".repeat(MAX_LINE_LENGTH));
+        let errors = parse_crontab(&line, options).unwrap_err();
+        assert_eq!(*errors[0].error, CrontabSyntaxError::LineTooLong { length: line.len(), max: MAX_LINE_LENGTH });
+    }
+
+    #[test]
+    fn test_misspelled_names() {
+        let options = &CrontabParserOptions::<&str>::default();
+        let misspelled = |line| match parse_crontab(line, options) {
+            Err(ref errors) => match *errors[0].error {
+                CrontabSyntaxError::UnknownName { ref name, ref suggestion, .. } => Some((name.clone(), suggestion.clone())),
+                _ => None,
             },
-            _ =>  ()
+            _ => None,
         };
+        assert_eq!(misspelled("0 2 * juli * root /usr/bin/backup"), Some(("juli".to_string(), Some("jul".to_string()))));
+        assert_eq!(misspelled("0 2 * * mon,wedn root /usr/bin/backup"), Some(("wedn".to_string(), Some("wed".to_string()))));
+        assert_eq!(misspelled("0 2 * * Friday root /usr/bin/backup"), Some(("Friday".to_string(), Some("fri".to_string()))));
+        assert_eq!(misspelled("0 2 * * xyz root /usr/bin/backup"), Some(("xyz".to_string(), None)));
+
+        let errors = parse_crontab("0 2 * * mon-thur root /usr/bin/backup", options).unwrap_err();
+        assert_eq!(walk_errors(&errors), "unknown day of the week 'thur', did you mean 'thu'? (at 'thur root /usr/')");
     }
 
     #[test]
-    fn test_parse_valid_crontab() {
+    fn test_multiple_errors() {
         let usernames = ["root"];
-        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames) };
-        let out = parse_crontab("* * * * * root /usr/local/bin yay".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames), ..Default::default() };
+        let errors = parse_crontab("0 25 * juli * rooot /usr/bin/backup", options).unwrap_err();
+        let offsets: Vec<usize> = errors.iter().map(SyntaxError::offset).collect();
+        assert_eq!(offsets, vec![2, 7, 14]);
+        assert_eq!(*errors[2].error, CrontabSyntaxError::UnknownUsername { username: "rooot".to_string(), suggestion: Some("root".to_string()) });
 
-        let out = parse_crontab("8 * * * * root /usr/local/bin yay".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+        // a missing field stops the parsing
+        assert_eq!(parse_crontab("0 25 * *", options).unwrap_err().len(), 2);
+    }
 
-        let out = parse_crontab("*/3 2 * * * root /usr/local/bin yay".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+    #[test]
+    fn test_error_offset() {
+        let options = &CrontabParserOptions::<&str>::default();
+        assert_eq!(error_offset("0 2 * * wedn root /usr/bin/backup", options), Some(8));
+        assert_eq!(error_offset("0 25 * * * root /usr/bin/backup", options), Some(2));
+        assert_eq!(error_offset("0 2 * * * root /usr/bin/backup 50%", options), Some(33));
+        assert_eq!(error_offset("FOO-BAR=1", options), Some(3));
+        assert_eq!(error_offset("0 2 * * * root /usr/bin/backup", options), None);
+    }
 
-        let out = parse_crontab("1-2 * * * * root /usr/local/bin yay".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+    #[test]
+    fn test_forbidden_usernames() {
+        let usernames = ["root", "backup"];
+        let forbidden = ["root"];
+        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames), forbidden_usernames: Some(&forbidden), ..Default::default() };
+        let out = parse_crontab("* * * * * backup /usr/local/bin yay", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "* * * * *", user: "backup", command: "/usr/local/bin yay" }));
 
-        let out = parse_crontab("1-2 * * * mon,tue root /usr/local/bin yay".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+        let errors = parse_crontab("* * * * * root /usr/local/bin yay", options).unwrap_err();
+        assert_eq!(*errors[0].error, CrontabSyntaxError::ForbiddenUsername { username: "root".to_string() });
+        assert_eq!(errors[0].offset(), 10);
 
-        let out = parse_crontab("#This is a comment".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+        let out = parse_crontab("* * * * * root /usr/local/bin yay # crontabcheck: allow-root", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "* * * * *", user: "root", command: "/usr/local/bin yay # crontabcheck: allow-root" }));
+    }
 
-        let out = parse_crontab("VARIABLE=VALUE".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+    #[test]
+    fn test_to_crontab_string() {
+        let options = &CrontabParserOptions::<&str>::default();
+        let canonical = |line| parse_crontab(line, options).unwrap().to_string();
+        assert_eq!(canonical("0  2\t* jan-mar 7   root  /usr/bin/backup  --full"), "0 2 * 1-3 0 root /usr/bin/backup  --full");
+        assert_eq!(canonical("  GREETING = ' hello'"), "GREETING=\" hello\"");
+        assert_eq!(canonical("MAILTO = ops@example.com"), "MAILTO=ops@example.com");
+        assert_eq!(canonical("# backups"), "#");
+        assert_eq!(canonical(""), "");
+        let options = &CrontabParserOptions::<&str> { crontab_owner: Some("alice"), ..Default::default() };
+        let line = parse_crontab("*/5 * * * mon /usr/bin/poll", options).unwrap();
+        assert_eq!(line.to_crontab_string(Some("alice")), "*/5 * * * 1 /usr/bin/poll");
+        assert_eq!(line.to_owned_line().to_string(), "*/5 * * * 1 alice /usr/bin/poll");
+        for text in &["0 2 * 1-3 0 root /usr/bin/backup", "GREETING=\" hello\""] {
+            assert_eq!(parse_crontab(text, &CrontabParserOptions::<&str>::default()).unwrap().to_string(), *text);
+        }
+    }
 
-        let out = parse_crontab("   ".as_bytes(), options);
-        assert_eq!(out, Done("".as_bytes(), ()));
+    #[test]
+    fn test_parse_environment_variable() {
+        let env = |line| parse_environnment_variable(Span::new(line)).map(|(_, parsed)| parsed).map_err(|e| syntax_error(e, Span::new(line)));
+        let variable = |name, value| Ok(CrontabLine::EnvironmentVariable { name, value });
+        assert_eq!(env("MAILTO="), variable("MAILTO", ""));
+        assert_eq!(env("  MY_VAR = some value  "), variable("MY_VAR", "some value"));
+        assert_eq!(env("MAILTO=\"\""), variable("MAILTO", ""));
+        assert_eq!(env("GREETING='hello world'"), variable("GREETING", "hello world"));
+        assert_eq!(env("EQUATION=a=b"), variable("EQUATION", "a=b"));
+        assert_eq!(*env("GREETING=\"hello").unwrap_err().error, CrontabSyntaxError::UnterminatedQuote);
+        let error = env("FOO-BAR=1").unwrap_err();
+        assert_eq!(*error.error, CrontabSyntaxError::InvalidEnvName { name: "FOO-BAR".to_string(), character: '-' });
+        assert_eq!(*error.position.fragment(), "-BAR");
+        assert!(matches!(*env("9FOO = 1").unwrap_err().error, CrontabSyntaxError::InvalidEnvName { character: '9', .. }));
+        assert!(env("* * * * * root /bin/true").is_err());
+        assert!(env("0 1 * * * root FOO=bar /bin/true").is_err());
+    }
+
+    #[test]
+    fn test_user_crontab() {
+        let options = &CrontabParserOptions::<&str> { crontab_owner: Some("alice"), ..Default::default() };
+        let out = parse_crontab("*/5 * * * * /usr/local/bin/yay --now", options);
+        assert_eq!(out, Ok(CrontabLine::Job { schedule: "*/5 * * * *", user: "alice", command: "/usr/local/bin/yay --now" }));
+    }
+
+    #[test]
+    fn test_check_crontab_owner() {
+        let usernames = ["alice", "bob"];
+        let options = &CrontabParserOptions { allowed_usernames: Some(&usernames), crontab_owner: Some("alice"), ..Default::default() };
+        assert_eq!(check_crontab_owner(options), Ok(()));
+        let options = &CrontabParserOptions { crontab_owner: Some("alicia"), ..*options };
+        assert_eq!(check_crontab_owner(options).unwrap_err().to_string(), "unknown user 'alicia', did you mean 'alice'?");
+        assert_eq!(check_crontab_owner(&CrontabParserOptions { allowed_usernames: Some(&usernames), ..Default::default() }), Ok(()));
+    }
+
+    #[test]
+    fn test_check_schedule() {
+        assert_eq!(check_schedule("*/5 1-3 * jan mon,fri"), Ok(()));
+        assert!(check_schedule("*/5 * * *").is_err());
+        assert!(check_schedule("61 * * * *").is_err());
+        assert_eq!(check_schedule("0 0 * * * *"), Err("unexpected '*' after the schedule".to_string()));
+    }
+
+    #[test]
+    fn test_check_jenkins_schedule() {
+        assert_eq!(check_jenkins_schedule("H H(0-7) * * *"), Ok(()));
+        assert_eq!(check_jenkins_schedule("H/15 * * * 1-5"), Ok(()));
+        assert_eq!(check_jenkins_schedule("H(0-29)/10 H(9-16) * * mon-fri"), Ok(()));
+        assert!(check_jenkins_schedule("H(0-70) * * * *").is_err());
+        assert!(check_jenkins_schedule("H(0-7 * * * *").is_err());
+        assert!(check_schedule("H * * * *").is_err());
+    }
+
+    #[test]
+    fn test_max_command_length() {
+        let options = &CrontabParserOptions::<&str> { max_command_length: 10, ..Default::default() };
+        assert!(parse_crontab("* * * * * root /bin/true", options).is_ok());
+        assert!(parse_crontab("* * * * * root /usr/bin/true", options).is_err());
+
+        let long_command = "* * * * * root /bin/echo ".to_string() + &"a".repeat(1000);
+        assert!(parse_crontab(&long_command, &CrontabParserOptions::<&str>::default()).is_err());
     }
 
     #[test]
     fn test_parse_user() {
-        assert_eq!(parse_user("whatever".as_bytes(), None as Option<&[String]>), Done("".as_bytes(), ()));
+        assert_eq!(remaining(parse_user(Span::new("whatever"), None as Option<&[String]>)), Ok(""));
         let users = ["root"];
-        match  parse_user("whatever".as_bytes(), Some(&users)) {
-            Error(_) => (),
-            _ => assert!(false)
-        };
-        assert_eq!(parse_user("root /usr/bin/local".as_bytes(), None as Option<&[String]>), Done(" /usr/bin/local".as_bytes(), ()));
+        assert!(remaining(parse_user(Span::new("whatever"), Some(&users))).is_err());
+        assert_eq!(remaining(parse_user(Span::new("rooot"), Some(&users))).unwrap_err().to_string(), "unknown user 'rooot', did you mean 'root'?");
+        assert_eq!(remaining(parse_user(Span::new("root /usr/bin/local"), None as Option<&[String]>)), Ok(" /usr/bin/local"));
+        for user in &["www-data /usr/bin/php", "first.last /usr/bin/php", "_apt /usr/bin/php"] {
+            assert_eq!(remaining(parse_user(Span::new(user), None as Option<&[String]>)), Ok(" /usr/bin/php"));
+        }
+        assert!(remaining(parse_user(Span::new("-root /usr/bin/php"), None as Option<&[String]>)).is_err());
+        assert_eq!(remaining(parse_user(Span::new("root:root /usr/bin/php"), None as Option<&[String]>)), Ok(":root /usr/bin/php"));
     }
 
     #[test]
     fn test_is_valid_username() {
-        assert_eq!(true, is_valid_username("whatever", None as Option<&[String]>));
-        assert_eq!(true, is_valid_username("root", Some(&["root", "notroot"])));
-        assert_eq!(false, is_valid_username("bfaucon", Some(&["root", "notroot"])));
+        assert!(is_valid_username("whatever", None as Option<&[String]>));
+        assert!(is_valid_username("root", Some(&["root", "notroot"])));
+        assert!(!is_valid_username("bfaucon", Some(&["root", "notroot"])));
     }
 
     #[test]
     fn test_day_of_week_value_parser() {
-        assert_eq!(day_of_week_value_parser("mon".as_bytes()), Done("".as_bytes(), ()));
-        assert_eq!(day_of_week_value_parser("mon ".as_bytes()), Done(" ".as_bytes(), ()));
-        assert_eq!(day_of_week_value_parser("0 ".as_bytes()), Done(" ".as_bytes(), ()));
-        assert_eq!(day_of_week_value_parser("1 ".as_bytes()), Done(" ".as_bytes(), ()));
+        assert_eq!(remaining(day_of_week_value_parser(Span::new("mon"))), Ok(""));
+        assert_eq!(remaining(day_of_week_value_parser(Span::new("mon "))), Ok(" "));
+        assert_eq!(remaining(day_of_week_value_parser(Span::new("0 "))), Ok(" "));
+        assert_eq!(remaining(day_of_week_value_parser(Span::new("1 "))), Ok(" "));
     }
 
     #[test]
     fn test_parse_period() {
-        assert_eq!(parse_period("* ".as_bytes(), minute_value_parser), Done(" ".as_bytes(), ()));
-        assert_eq!(parse_period("*/2 ".as_bytes(), minute_value_parser), Done(" ".as_bytes(), ()));
+        assert_eq!(remaining(parse_period(Span::new("* "), minute_value_parser)), Ok(" "));
+        assert_eq!(remaining(parse_period(Span::new("*/2 "), minute_value_parser)), Ok(" "));
     }
 
     #[test]
     fn test_parse_range_or_value() {
-        assert_eq!(parse_range_or_value("1-2".as_bytes(), minute_value_parser), Done("".as_bytes(), ()));
+        assert_eq!(remaining(parse_range_or_value(Span::new("1-2"), minute_value_parser)), Ok(""));
     }
 
     #[test]
     fn test_parse_enum() {
-        assert_eq!(parse_enum("1-2,3,4-5 *".as_bytes(), minute_value_parser), Done(" *".as_bytes(), ()));
-        assert_eq!(parse_enum("mon-tue ".as_bytes(), day_of_week_value_parser), Done(" ".as_bytes(), ()));
+        assert_eq!(remaining(parse_enum(Span::new("1-2,3,4-5 *"), minute_value_parser)), Ok(" *"));
+        assert_eq!(remaining(parse_enum(Span::new("mon-tue "), day_of_week_value_parser)), Ok(" "));
+        assert_eq!(remaining(parse_enum(Span::new("0-59/15,1-9/2 *"), minute_value_parser)), Ok(" *"));
+        assert_eq!(remaining(parse_enum(Span::new("5/15 *"), minute_value_parser)), Ok("/15 *"));
+        assert!(remaining(parse_enum(Span::new("0-59/60 *"), minute_value_parser)).is_err());
     }
 
     #[test]
     fn test_parse_field() {
-        assert_eq!(parse_field("mon-tue ".as_bytes(), day_of_week_value_parser), Done(" ".as_bytes(), ()));
+        assert_eq!(remaining(parse_field(Span::new("mon-tue "), day_of_week_value_parser)), Ok(" "));
     }
 
 }
@@ -0,0 +1,67 @@
+// The [[policies]] of the configuration: rules of an organization on the jobs of its crontabs
+
+use config::Policy;
+use schedule::{nickname_schedule, parse_schedule};
+
+
+// Whether a job runs more often than `policy` allows (@reboot jobs run once)
+fn is_more_frequent(policy: &Policy, schedule: &str) -> bool {
+    let limit = match policy.more_frequent_than {
+        Some(limit) => limit,
+        None => return true,
+    };
+    let schedule = nickname_schedule(schedule).unwrap_or(schedule);
+    match parse_schedule(schedule).ok().and_then(|schedule| schedule.min_interval()) {
+        Some(interval) => interval < limit,
+        None => false,
+    }
+}
+
+fn applies_to<T: AsRef<str>>(policy: &Policy, user: &str, schedule: &str, command: &str, variables: &[T]) -> bool {
+    let is_set = |name: &String| variables.iter().any(|variable| variable.as_ref() == &name[..]);
+    policy.users.as_ref().map(|users| users.iter().any(|policy_user| policy_user == user)).unwrap_or(true)
+        && !policy.except_users.iter().any(|except_user| except_user == user)
+        && policy.command.as_ref().map(|pattern| pattern.is_match(command)).unwrap_or(true)
+        && policy.env_set.iter().all(&is_set)
+        && !policy.env_unset.iter().any(&is_set)
+        && is_more_frequent(policy, schedule)
+}
+
+// The policies a job breaks, `variables` being the environment variables set before it
+pub fn policy_violations<'p, T: AsRef<str>>(policies: &'p [Policy], user: &str, schedule: &str, command: &str, variables: &[T]) -> Vec<&'p Policy> {
+    policies.iter().filter(|policy| applies_to(policy, user, schedule, command, variables)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use config::parse_config;
+    use policy::*;
+
+    #[test]
+    fn test_policy_violations() {
+        let config = parse_config(r#"
+            [[policies]]
+            message = "non-root users may not schedule jobs more frequent than hourly"
+            except_users = ["root"]
+            more_frequent_than = "1h"
+
+            [[policies]]
+            message = "backups must set MAILTO"
+            level = "warning"
+            command = '^/usr/bin/backup'
+            env_unset = ["MAILTO"]
+        "#).unwrap();
+        let no_variables: &[&str] = &[];
+        let violations = |user: &str, schedule: &str, command: &str, variables: &[&str]| -> Vec<String> {
+            policy_violations(&config.policies, user, schedule, command, variables).iter().map(|policy| policy.message.clone()).collect()
+        };
+        assert_eq!(violations("alice", "*/10 * * * *", "/usr/bin/poll", no_variables), vec!["non-root users may not schedule jobs more frequent than hourly"]);
+        assert!(violations("alice", "@hourly", "/usr/bin/poll", no_variables).is_empty());
+        assert!(violations("alice", "@reboot", "/usr/bin/poll", no_variables).is_empty());
+        assert!(violations("root", "* * * * *", "/usr/bin/poll", no_variables).is_empty());
+        assert_eq!(violations("root", "0 2 * * *", "/usr/bin/backup --full", no_variables), vec!["backups must set MAILTO"]);
+        assert!(violations("root", "0 2 * * *", "/usr/bin/backup --full", &["MAILTO"]).is_empty());
+    }
+
+}
@@ -0,0 +1,79 @@
+// Progress of long scans (--system, --archive, audits), on a status line of stderr: scans of thousands of files do
+// not look hung. Nothing is shown for scans taking less than a second, or when stderr is not a terminal.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+
+const DELAY: Duration = Duration::from_secs(1);
+const INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Progress {
+    total: usize,
+    enabled: bool,
+    start: Instant,
+    // files done, and when the status line was last printed
+    state: Mutex<(usize, Option<Instant>)>,
+}
+
+impl Progress {
+    pub fn new(total: usize, quiet: bool) -> Self {
+        Progress {
+            total,
+            enabled: !quiet && io::stderr().is_terminal(),
+            start: Instant::now(),
+            state: Mutex::new((0, None)),
+        }
+    }
+
+    // A file is done (from any thread)
+    pub fn tick(&self) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        state.0 += 1;
+        let now = Instant::now();
+        let due = match state.1 {
+            Some(printed) => now.duration_since(printed) >= INTERVAL,
+            None => now.duration_since(self.start) >= DELAY,
+        };
+        if self.enabled && due {
+            eprint!("\r{}", status_line(state.0, self.total));
+            let _ = io::stderr().flush();
+            state.1 = Some(now);
+        }
+    }
+
+    // Clear the status line, before the results are printed
+    pub fn finish(&self) {
+        if let Ok(state) = self.state.lock() {
+            if self.enabled && state.1.is_some() {
+                eprint!("\r{:width$}\r", "", width = status_line(self.total, self.total).len());
+            }
+        }
+    }
+}
+
+// "checked 1200/4000 files (30%)"
+fn status_line(done: usize, total: usize) -> String {
+    format!("checked {}/{} files ({}%)", done, total, done * 100 / total.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use progress::*;
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(status_line(1200, 4000), "checked 1200/4000 files (30%)");
+        assert_eq!(status_line(0, 0), "checked 0/0 files (0%)");
+        let progress = Progress::new(3, true);
+        progress.tick();
+        progress.tick();
+        assert_eq!(progress.state.lock().unwrap().0, 2);
+    }
+
+}
@@ -0,0 +1,228 @@
+// Schedules of other cron-like schedulers: Quartz (Java) and AWS EventBridge cron expressions, and their
+// conversion to and from classic cron schedules
+
+use std::fmt;
+use std::str::FromStr;
+
+use parser::check_schedule;
+
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum ExpressionFormat {
+    // the five fields of (Vixie) cron
+    Vixie,
+    // seconds, the five fields and an optional year; days of the week from 1 (Sunday) to 7
+    Quartz,
+    // cron(...) of AWS EventBridge: the five fields and a year; days of the week as in Quartz
+    Aws,
+}
+
+pub const EXPRESSION_FORMAT_NAMES: &[&str] = &["vixie", "quartz", "aws"];
+
+impl FromStr for ExpressionFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vixie" => Ok(ExpressionFormat::Vixie),
+            "quartz" => Ok(ExpressionFormat::Quartz),
+            "aws" => Ok(ExpressionFormat::Aws),
+            _ => Err(format!("unknown expression format '{}' (expected one of: {})", s, EXPRESSION_FORMAT_NAMES.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for ExpressionFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExpressionFormat::Vixie => write!(f, "vixie"),
+            ExpressionFormat::Quartz => write!(f, "quartz"),
+            ExpressionFormat::Aws => write!(f, "aws"),
+        }
+    }
+}
+
+// "5/15" -> "5-59/15": Vixie cron only accepts steps after "*" or a range
+fn explicit_ranges(field: &str, max: u32) -> String {
+    field.split(',').map(|item| {
+        let mut parts = item.splitn(2, '/');
+        match (parts.next(), parts.next()) {
+            (Some(start), Some(step)) if start != "*" && !start.contains('-') => format!("{}-{}/{}", start, max, step),
+            _ => item.to_string(),
+        }
+    }).collect::<Vec<String>>().join(",")
+}
+
+// Map the numbers of the ranges of a field (not the steps, nor the names)
+fn map_values(field: &str, map: &dyn Fn(u32) -> u32) -> String {
+    field.split(',').map(|item| {
+        let mut parts = item.splitn(2, '/');
+        let range = parts.next().unwrap_or("");
+        let range = range.split('-')
+            .map(|value| value.parse::<u32>().map(|number| map(number).to_string()).unwrap_or_else(|_| value.to_string()))
+            .collect::<Vec<String>>()
+            .join("-");
+        match parts.next() {
+            Some(step) => format!("{}/{}", range, step),
+            None => range,
+        }
+    }).collect::<Vec<String>>().join(",")
+}
+
+// Quartz and AWS fields (from the minutes) as classic cron fields, adding what cannot be converted to `problems`
+fn classic_from_quartz(fields: &[&str], problems: &mut Vec<String>) -> Vec<String> {
+    let (day_of_month, day_of_week) = (fields[2], fields[4]);
+    if day_of_month.contains(['L', 'W']) {
+        problems.push(format!("'{}': days of the month relative to the end of the month (L) or to weekdays (W) have no cron equivalent", day_of_month));
+    }
+    if day_of_week.contains('#') {
+        problems.push(format!("'{}': the n-th day of the week of the month (#) has no cron equivalent", day_of_week));
+    }
+    if day_of_week.contains('L') {
+        problems.push(format!("'{}': the last day of the week of the month (L) has no cron equivalent", day_of_week));
+    }
+    if day_of_month != "?" && day_of_week != "?" && day_of_month != "*" && day_of_week != "*" {
+        problems.push("both the day of the month and the day of the week are set: cron would run the job when either matches".to_string());
+    }
+    // names are case insensitive, cron only knows them in lowercase
+    let question_mark = |field: &str| if field == "?" { "*".to_string() } else { field.to_lowercase() };
+    let day_of_week = explicit_ranges(&question_mark(day_of_week), 7);
+    vec![
+        explicit_ranges(fields[0], 59),
+        explicit_ranges(fields[1], 23),
+        explicit_ranges(&question_mark(day_of_month), 31),
+        explicit_ranges(&fields[3].to_lowercase(), 12),
+        // Sunday is 1 in Quartz, 0 in cron
+        map_values(&day_of_week, &|day| day.saturating_sub(1)),
+    ]
+}
+
+fn check_field_count(expression: &str, fields: &[&str], counts: &[usize], format: ExpressionFormat) -> Result<(), Vec<String>> {
+    if counts.contains(&fields.len()) {
+        Ok(())
+    } else {
+        Err(vec![format!("'{}' is not a {} expression: it has {} fields", expression, format, fields.len())])
+    }
+}
+
+// A classic cron schedule from an expression, or why it cannot be converted
+fn to_classic(expression: &str, from: ExpressionFormat) -> Result<Vec<String>, Vec<String>> {
+    let mut problems = vec![];
+    let expression = expression.trim();
+    let fields: Vec<&str> = match from {
+        ExpressionFormat::Aws if expression.starts_with("cron(") && expression.ends_with(')') => {
+            expression[5..expression.len() - 1].split_whitespace().collect()
+        },
+        _ => expression.split_whitespace().collect(),
+    };
+    let fields = match from {
+        ExpressionFormat::Vixie => {
+            check_field_count(expression, &fields, &[5], from)?;
+            check_schedule(expression).map_err(|e| vec![format!("invalid schedule '{}': {}", expression, e)])?;
+            return Ok(fields.iter().map(|field| field.to_string()).collect());
+        },
+        ExpressionFormat::Quartz => {
+            check_field_count(expression, &fields, &[6, 7], from)?;
+            if fields[0] != "0" {
+                problems.push(format!("'{}': cron only runs jobs at the start of a minute (the seconds must be 0)", fields[0]));
+            }
+            &fields[1..]
+        },
+        ExpressionFormat::Aws => {
+            check_field_count(expression, &fields, &[6], from)?;
+            &fields[..]
+        },
+    };
+    if let Some(year) = fields.get(5) {
+        if *year != "*" {
+            problems.push(format!("'{}': cron has no year field", year));
+        }
+    }
+    let classic = classic_from_quartz(fields, &mut problems);
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    // e.g. out of bounds values
+    let schedule = classic.join(" ");
+    check_schedule(&schedule).map_err(|e| vec![format!("'{}' converted to the invalid cron schedule '{}': {}", expression, schedule, e)])?;
+    Ok(classic)
+}
+
+// Days of the week of a classic schedule (0 or 7 for Sunday) in Quartz, where Sunday is 1
+fn quartz_day_of_week(field: &str) -> Result<String, String> {
+    let items: Result<Vec<String>, String> = field.split(',').map(|item| {
+        let mut parts = item.splitn(2, '/');
+        let (range, step) = (parts.next().unwrap_or(""), parts.next());
+        let bounds: Vec<Option<u32>> = range.split('-').map(|value| value.parse::<u32>().ok()).collect();
+        match (&bounds[..], step) {
+            (&[Some(0), Some(7)], _) => Ok(format!("1-7{}", step.map(|step| format!("/{}", step)).unwrap_or_default())),
+            // "5-7" (Friday to Sunday) -> "6-7,1"
+            (&[Some(start), Some(7)], None) if start > 0 => Ok(format!("{}-7,1", start + 1)),
+            (&[Some(start), Some(7)], Some(_)) if start > 0 => Err(format!("'{}': ranges ending on Sunday (7) with a step cannot be converted", item)),
+            _ => Ok(map_values(item, &|day| day % 7 + 1)),
+        }
+    }).collect();
+    items.map(|items| items.join(","))
+}
+
+// Convert a schedule expression from a format to another, or explain why it cannot be done
+pub fn convert_expression(expression: &str, from: ExpressionFormat, to: ExpressionFormat) -> Result<String, Vec<String>> {
+    let fields = to_classic(expression, from)?;
+    match to {
+        ExpressionFormat::Vixie => Ok(fields.join(" ")),
+        ExpressionFormat::Quartz | ExpressionFormat::Aws => {
+            // one of the days must be "?"
+            let (day_of_month, day_of_week) = match (&fields[2][..], &fields[4][..]) {
+                (day_of_month, "*") => (day_of_month.to_string(), "?".to_string()),
+                ("*", day_of_week) => ("?".to_string(), quartz_day_of_week(day_of_week).map_err(|e| vec![e])?),
+                _ => return Err(vec!["both the day of the month and the day of the week are set: cron runs the job when either matches, which cannot be expressed".to_string()]),
+            };
+            let fields = [fields[0].clone(), fields[1].clone(), day_of_month, fields[3].clone(), day_of_week];
+            Ok(match to {
+                ExpressionFormat::Aws => format!("cron({} *)", fields.join(" ")),
+                _ => format!("0 {}", fields.join(" ")),
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use quartz::*;
+
+    fn convert(expression: &str, from: &str, to: &str) -> Result<String, Vec<String>> {
+        convert_expression(expression, from.parse().unwrap(), to.parse().unwrap())
+    }
+
+    #[test]
+    fn test_quartz_to_vixie() {
+        assert_eq!(convert("0 0 12 * * ?", "quartz", "vixie"), Ok("0 12 * * *".to_string()));
+        assert_eq!(convert("0 0/15 8-17 ? * 2-6", "quartz", "vixie"), Ok("0-59/15 8-17 * * 1-5".to_string()));
+        assert_eq!(convert("0 5/15 * ? * *", "quartz", "vixie"), Ok("5-59/15 * * * *".to_string()));
+        assert_eq!(convert("0 30 2 ? JAN MON,FRI 2030", "quartz", "vixie").unwrap_err().len(), 1);
+        assert!(convert("0 0 12 L * ?", "quartz", "vixie").is_err());
+        assert!(convert("0 0 12 ? * 6#3", "quartz", "vixie").is_err());
+        assert!(convert("30 0 12 * * ?", "quartz", "vixie").is_err());
+        assert!(convert("0 12 * * *", "quartz", "vixie").is_err());
+    }
+
+    #[test]
+    fn test_aws_to_vixie() {
+        assert_eq!(convert("cron(0 18 ? * MON-FRI *)", "aws", "vixie"), Ok("0 18 * * mon-fri".to_string()));
+        assert_eq!(convert("15 10 1 * ? *", "aws", "vixie"), Ok("15 10 1 * *".to_string()));
+        assert!(convert("0 8 LW * ? *", "aws", "vixie").is_err());
+    }
+
+    #[test]
+    fn test_vixie_to_quartz() {
+        assert_eq!(convert("*/5 * * * *", "vixie", "quartz"), Ok("0 */5 * * * ?".to_string()));
+        assert_eq!(convert("30 2 * * 0,6", "vixie", "quartz"), Ok("0 30 2 ? * 1,7".to_string()));
+        assert_eq!(convert("0 0 * * 5-7", "vixie", "quartz"), Ok("0 0 0 ? * 6-7,1".to_string()));
+        assert_eq!(convert("0 0 1 * *", "vixie", "aws"), Ok("cron(0 0 1 * ? *)".to_string()));
+        assert!(convert("0 0 1 * 1", "vixie", "quartz").is_err());
+    }
+
+}
@@ -0,0 +1,91 @@
+// The repl subcommand: schedules typed at a prompt are checked as cron would, explained in English, and followed by
+// their next run times, to experiment with expressions.
+
+use std::io::{self, BufRead, Write};
+
+use chrono::NaiveDateTime;
+
+use parser::check_schedule;
+use schedule::{describe_schedule, nickname_schedule, parse_schedule};
+
+const PROMPT: &str = "cron> ";
+const NEXT_RUNS: usize = 5;
+
+// What the REPL answers to `expression` ("*/5 * * * *", "@daily"...), the next runs being counted from `now`
+pub fn evaluate(expression: &str, now: NaiveDateTime) -> String {
+    if expression == "@reboot" {
+        return "valid: runs at startup\n".to_string();
+    }
+    let schedule = match nickname_schedule(expression) {
+        Some(schedule) => schedule,
+        None if expression.starts_with('@') => return format!("invalid: unknown shortcut '{}'\n", expression),
+        None => expression,
+    };
+    let parsed = match check_schedule(schedule).and_then(|_| parse_schedule(schedule)) {
+        Ok(parsed) => parsed,
+        Err(e) => return format!("invalid: {}\n", e),
+    };
+    let mut answer = format!("valid: runs {}\n", describe_schedule(expression));
+    let runs = parsed.next_runs(now, NEXT_RUNS);
+    if runs.is_empty() {
+        // e.g. "0 0 30 2 *"
+        answer.push_str("never runs in the next 4 years\n");
+    } else {
+        answer.push_str("next runs:\n");
+        for run in runs {
+            answer.push_str(&format!("  {}\n", run.format("%Y-%m-%d %H:%M")));
+        }
+    }
+    answer
+}
+
+// Answer the expressions of `input` until its end or "quit", `now` giving the time to count the next runs from
+pub fn run_repl<R: BufRead, W: Write, F: Fn() -> NaiveDateTime>(input: &mut R, output: &mut W, now: F) -> io::Result<()> {
+    loop {
+        output.write_all(PROMPT.as_bytes())?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            // end of input (Ctrl-D): leave the terminal on a new line
+            output.write_all(b"\n")?;
+            return Ok(());
+        }
+        match line.trim() {
+            "" => continue,
+            "quit" | "exit" => return Ok(()),
+            "help" => output.write_all(b"type a schedule (e.g. */15 9-17 * * mon-fri, or @daily) to see whether it is valid, \
+                                          what it means and when it runs next; quit to leave\n")?,
+            expression => output.write_all(evaluate(expression, now()).as_bytes())?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::NaiveDate;
+
+    use repl::*;
+
+    #[test]
+    fn test_evaluate() {
+        // a Friday
+        let now = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(10, 7, 30).unwrap();
+        assert_eq!(evaluate("*/20 9-17 * * mon-fri", now),
+                   "valid: runs on \"*/20 9-17 * * mon-fri\"\nnext runs:\n  2024-03-01 10:20\n  2024-03-01 10:40\n  \
+                    2024-03-01 11:00\n  2024-03-01 11:20\n  2024-03-01 11:40\n");
+        assert!(evaluate("@weekly", now).starts_with("valid: runs every Sunday at 00:00\nnext runs:\n  2024-03-03 00:00\n"));
+        assert_eq!(evaluate("0 0 30 2 *", now), "valid: runs yearly on February 30 at 00:00\nnever runs in the next 4 years\n");
+        assert!(evaluate("61 * * * *", now).starts_with("invalid: "));
+        assert_eq!(evaluate("@sometimes", now), "invalid: unknown shortcut '@sometimes'\n");
+    }
+
+    #[test]
+    fn test_run_repl() {
+        let now = || NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(10, 7, 30).unwrap();
+        let mut output = vec![];
+        run_repl(&mut &b"\n@reboot\nquit\n0 * * * *\n"[..], &mut output, now).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "cron> cron> valid: runs at startup\ncron> ");
+    }
+
+}
@@ -0,0 +1,307 @@
+// Reports on when the jobs of crontabs run: upcoming runs, as a list, a timeline or an iCalendar file, and the
+// number of jobs and runs of each user
+
+use chrono::{Duration, Local, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde_json;
+
+use parser::{CrontabLine, CrontabParserOptions, parse_crontab};
+use schedule::parse_schedule;
+
+
+// timeline characters for 0, 1, 2... runs in a minute (the last one for more)
+const DENSITY: &[char] = &[' ', '.', ':', '+', '*', '#'];
+
+// A run of a job, in the local time of the machine running cron
+pub struct Run {
+    pub time: NaiveDateTime,
+    pub user: String,
+    pub command: String,
+    // the crontab file and line of the job
+    pub source: String,
+    pub line_number: usize,
+}
+
+// "30m", "24h", "7d", "2w"
+pub fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}' (expected e.g. 30m, 24h, 7d or 2w)", duration);
+    let unit_index = duration.char_indices().last().map(|(index, _)| index).unwrap_or(0);
+    let count = i64::from(duration[..unit_index].parse::<u32>().map_err(|_| invalid())?);
+    match &duration[unit_index..] {
+        "m" => Ok(Duration::minutes(count)),
+        "h" => Ok(Duration::hours(count)),
+        "d" => Ok(Duration::days(count)),
+        "w" => Ok(Duration::weeks(count)),
+        _ => Err(invalid()),
+    }
+}
+
+// The runs of the jobs of a crontab, from `start` (included) to `end` (excluded), or its first invalid line
+pub fn crontab_runs<T: AsRef<str>>(contents: &str, source: &str, parser_options: &CrontabParserOptions<T>, start: NaiveDateTime, end: NaiveDateTime) -> Result<Vec<Run>, String> {
+    let mut runs = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let (schedule, user, command) = match parse_crontab(line, parser_options) {
+            Ok(CrontabLine::Job { schedule, user, command }) => (schedule, user, command),
+            Ok(_) => continue,
+            Err(_) => return Err(format!("{}: line {}: invalid line", source, line_number)),
+        };
+        let schedule = parse_schedule(schedule).map_err(|e| format!("{}: line {}: {}", source, line_number, e))?;
+        runs.extend(schedule.runs_between(start, end).into_iter().map(|time| Run {
+            time,
+            user: user.to_string(),
+            command: command.to_string(),
+            source: source.to_string(),
+            line_number,
+        }));
+    }
+    Ok(runs)
+}
+
+// The users of the jobs of a (valid) crontab, one per job
+pub fn crontab_job_users<T: AsRef<str>>(contents: &str, parser_options: &CrontabParserOptions<T>) -> Vec<String> {
+    contents.lines().filter_map(|line| match parse_crontab(line, parser_options) {
+        Ok(CrontabLine::Job { user, .. }) => Some(user.to_string()),
+        _ => None,
+    }).collect()
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct UserStats {
+    pub user: String,
+    pub jobs: usize,
+    // in the period of the report
+    pub runs: usize,
+    pub runs_per_day: f64,
+}
+
+// The jobs and runs of each user over `period`, most runs first
+pub fn user_stats(job_users: &[String], runs: &[Run], period: Duration) -> Vec<UserStats> {
+    let mut stats: Vec<UserStats> = vec![];
+    for user in job_users {
+        match stats.iter_mut().find(|stats| &stats.user == user) {
+            Some(stats) => stats.jobs += 1,
+            None => stats.push(UserStats { user: user.clone(), jobs: 1, runs: 0, runs_per_day: 0.0 }),
+        }
+    }
+    let days = period.num_minutes().max(1) as f64 / (24.0 * 60.0);
+    for stats in &mut stats {
+        stats.runs = runs.iter().filter(|run| run.user == stats.user).count();
+        stats.runs_per_day = stats.runs as f64 / days;
+    }
+    stats.sort_by(|a, b| b.runs.cmp(&a.runs).then(a.user.cmp(&b.user)));
+    stats
+}
+
+// A table of the jobs and runs of each user, with the totals
+pub fn users_text(stats: &[UserStats]) -> String {
+    let width = stats.iter().map(|stats| stats.user.len()).chain(Some(4)).max().unwrap_or(4);
+    let mut text = format!("{:width$}  {:>6}  {:>8}  {:>10}\n", "USER", "JOBS", "RUNS", "RUNS/DAY", width = width);
+    for stats in stats {
+        text.push_str(&format!("{:width$}  {:>6}  {:>8}  {:>10.1}\n", stats.user, stats.jobs, stats.runs, stats.runs_per_day, width = width));
+    }
+    let (jobs, runs) = stats.iter().fold((0, 0), |(jobs, runs), stats| (jobs + stats.jobs, runs + stats.runs));
+    text.push_str(&format!("{} users, {} jobs, {} runs\n", stats.len(), jobs, runs));
+    text
+}
+
+// "2024-03-01T02:00:00+01:00"
+fn local_time(time: &NaiveDateTime) -> String {
+    match Local.from_local_datetime(time).earliest() {
+        Some(time) => time.to_rfc3339(),
+        // skipped when the clocks go forward
+        None => time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+    }
+}
+
+// One line per run: "2024-03-01 02:00  root  /etc/cron.d/backup:3  /usr/bin/backup"
+pub fn upcoming_text(runs: &[Run]) -> String {
+    runs.iter()
+        .map(|run| format!("{}  {}  {}:{}  {}\n", run.time.format("%Y-%m-%d %H:%M"), run.user, run.source, run.line_number, run.command))
+        .collect()
+}
+
+#[derive(Serialize)]
+struct UpcomingRun<'a> {
+    time: String,
+    user: &'a str,
+    command: &'a str,
+    source: &'a str,
+    line: usize,
+}
+
+// A JSON array of the runs, e.g. to compare with the logs of cron
+pub fn upcoming_json(runs: &[Run]) -> String {
+    let upcoming: Vec<UpcomingRun> = runs.iter().map(|run| UpcomingRun {
+        time: local_time(&run.time),
+        user: &run.user,
+        command: &run.command,
+        source: &run.source,
+        line: run.line_number,
+    }).collect();
+    serde_json::to_string_pretty(&upcoming).unwrap_or_default()
+}
+
+// A 24 x 60 grid of the number of runs at each minute of the day, with the number of runs per hour,
+// which shows at a glance when jobs pile up
+pub fn timeline(runs: &[Run]) -> String {
+    let mut counts = [[0usize; 60]; 24];
+    for run in runs {
+        counts[run.time.hour() as usize][run.time.minute() as usize] += 1;
+    }
+    let ticks: String = (0..6).map(|tens| format!("{:<10}", tens * 10)).collect();
+    let mut timeline = format!("    {}\n", ticks.trim_end());
+    for (hour, minutes) in counts.iter().enumerate() {
+        let row: String = minutes.iter().map(|&count| DENSITY[count.min(DENSITY.len() - 1)]).collect();
+        timeline.push_str(&format!("{:02} |{}| {}\n", hour, row, minutes.iter().sum::<usize>()));
+    }
+    timeline.push_str("runs per minute: '.' 1, ':' 2, '+' 3, '*' 4, '#' 5 or more\n");
+    timeline
+}
+
+// A TEXT value of an iCalendar file (RFC 5545)
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+// Lines longer than 75 bytes are continued on the next line, after a space
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+// An iCalendar file with an event per run. The times are "floating": calendars show them as they are, in the
+// time zone they are viewed in, which is right as long as it is the time zone of the machine running cron.
+pub fn ics_calendar(runs: &[Run]) -> String {
+    let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec!["BEGIN:VCALENDAR".to_string(), "VERSION:2.0".to_string(), "PRODID:-//crontabcheck//EN".to_string()];
+    for run in runs {
+        let start = run.time.format("%Y%m%dT%H%M%S").to_string();
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", ics_text(&format!("{}-{}-{}@crontabcheck", start, run.source, run.line_number))));
+        lines.push(format!("DTSTAMP:{}", timestamp));
+        lines.push(format!("DTSTART:{}", start));
+        lines.push(format!("SUMMARY:{}", ics_text(&run.command)));
+        lines.push(format!("DESCRIPTION:{}", ics_text(&format!("run as {}\n{}, line {}", run.user, run.source, run.line_number))));
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold_ics_line(line) + "\r\n").collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{Duration, NaiveDate};
+    use serde_json::{self, Value};
+
+    use parser::CrontabParserOptions;
+    use report::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_duration("24h"), Ok(Duration::hours(24)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::days(14)));
+        assert!(parse_duration("24").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("-1d").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn test_crontab_runs() {
+        let crontab = "# nightly\n0 2 * * * root /usr/bin/backup\n*/30 * * * * www-data /usr/bin/poll\n";
+        let start = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(1, 0, 0).unwrap();
+        let runs = crontab_runs(crontab, "jobs", &CrontabParserOptions::<&str>::default(), start, start + Duration::hours(2)).unwrap();
+        assert_eq!(runs.len(), 5);
+        assert_eq!(runs[0].time, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(2, 0, 0).unwrap());
+        assert_eq!(runs[0].line_number, 2);
+        assert_eq!(runs[1].user, "www-data");
+        let crontab = "0 2 * * * root /usr/bin/backup\n0 25 * * * root /usr/bin/poll\n";
+        let runs = crontab_runs(crontab, "jobs", &CrontabParserOptions::<&str>::default(), start, start + Duration::hours(2));
+        assert_eq!(runs.err(), Some("jobs: line 2: invalid line".to_string()));
+    }
+
+    fn backup_run() -> Run {
+        Run {
+            time: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(2, 0, 0).unwrap(),
+            user: "root".to_string(),
+            command: "/usr/bin/backup \"$HOME\"".to_string(),
+            source: "/etc/cron.d/backup".to_string(),
+            line_number: 3,
+        }
+    }
+
+    #[test]
+    fn test_upcoming_text() {
+        assert_eq!(upcoming_text(&[backup_run()]), "2024-03-01 02:00  root  /etc/cron.d/backup:3  /usr/bin/backup \"$HOME\"\n");
+    }
+
+    #[test]
+    fn test_upcoming_json() {
+        let upcoming: Value = serde_json::from_str(&upcoming_json(&[backup_run()])).unwrap();
+        assert!(upcoming[0]["time"].as_str().unwrap().starts_with("2024-03-01T02:00:00"));
+        assert_eq!(upcoming[0]["command"], "/usr/bin/backup \"$HOME\"");
+        assert_eq!(upcoming[0]["source"], "/etc/cron.d/backup");
+        assert_eq!(upcoming[0]["line"], 3);
+        assert_eq!(upcoming_json(&[]), "[]");
+    }
+
+    #[test]
+    fn test_timeline() {
+        let mut runs: Vec<Run> = (0..6).map(|_| backup_run()).collect();
+        runs[0].time = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(13, 30, 0).unwrap();
+        let timeline = timeline(&runs);
+        let lines: Vec<&str> = timeline.lines().collect();
+        assert_eq!(lines.len(), 26);
+        assert!(lines[0].starts_with("    0         10        20"));
+        assert_eq!(lines[3], format!("02 |#{}| 5", " ".repeat(59)));
+        assert_eq!(lines[14].chars().nth(4 + 30), Some('.'));
+        assert!(lines[1].ends_with("| 0"));
+    }
+
+    #[test]
+    fn test_user_stats() {
+        let crontab = "0 2 * * * root /usr/bin/backup\n*/30 * * * * www /usr/bin/poll\n0 * * * * root /usr/bin/rotate\n";
+        let options = CrontabParserOptions::<&str>::default();
+        let start = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let runs = crontab_runs(crontab, "jobs", &options, start, start + Duration::days(2)).unwrap();
+        let stats = user_stats(&crontab_job_users(crontab, &options), &runs, Duration::days(2));
+        assert_eq!(stats, vec![
+            UserStats { user: "www".to_string(), jobs: 1, runs: 96, runs_per_day: 48.0 },
+            UserStats { user: "root".to_string(), jobs: 2, runs: 50, runs_per_day: 25.0 },
+        ]);
+        assert_eq!(users_text(&stats), "USER    JOBS      RUNS    RUNS/DAY\n\
+                                        www        1        96        48.0\n\
+                                        root       2        50        25.0\n\
+                                        2 users, 3 jobs, 146 runs\n");
+    }
+
+    #[test]
+    fn test_ics_calendar() {
+        let run = Run {
+            time: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(2, 0, 0).unwrap(),
+            user: "root".to_string(),
+            command: format!("/usr/bin/backup --exclude=a,b; {}", "x".repeat(80)),
+            source: "/etc/cron.d/backup".to_string(),
+            line_number: 3,
+        };
+        let calendar = ics_calendar(&[run]);
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.contains("\r\nDTSTART:20240301T020000\r\n"));
+        assert!(calendar.contains("SUMMARY:/usr/bin/backup --exclude=a\\,b\\; xxx"));
+        assert!(calendar.contains("DESCRIPTION:run as root\\n/etc/cron.d/backup\\, line 3\r\n"));
+        assert!(calendar.lines().all(|line| line.len() <= 75));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+    }
+
+}
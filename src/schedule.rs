@@ -0,0 +1,652 @@
+// The values of the five time and date fields of a job, for the features which need more than validation
+// (conversions, next run times...). Schedules are expected to have been validated by the parser.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Deserializer};
+use serde::de;
+
+use dialect::Dialect;
+use parser::check_schedule_fields;
+
+pub const MONTH_NAMES: &[&str] = &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+// cron numbers days of the week from 0 (Sunday), 7 is Sunday too
+pub const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+const ENGLISH_MONTH_NAMES: &[&str] = &["January", "February", "March", "April", "May", "June", "July",
+                                                       "August", "September", "October", "November", "December"];
+const ENGLISH_DAY_NAMES: &[&str] = &["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+// The schedules of the @ shortcuts (@reboot has none)
+pub fn nickname_schedule(nickname: &str) -> Option<&'static str> {
+    match nickname {
+        "@yearly" | "@annually" => Some("0 0 1 1 *"),
+        "@monthly" => Some("0 0 1 * *"),
+        "@weekly" => Some("0 0 * * 0"),
+        "@daily" | "@midnight" => Some("0 0 * * *"),
+        "@hourly" => Some("0 * * * *"),
+        // FreeBSD
+        "@every_minute" => Some("* * * * *"),
+        _ => None,
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum FieldValue {
+    // "*", "*/5"
+    All { step: u32 },
+    // "5", "1-5", "1-10/2"
+    Range { start: u32, end: u32, step: u32 },
+    // extended dialect: "L" in the day of the month field
+    LastDayOfMonth,
+    // extended dialect: "5L" in the day of the week field, the last Friday of the month
+    LastDayOfWeek { day: u32 },
+    // extended dialect: "mon#2" in the day of the week field, the second Monday of the month
+    NthDayOfWeek { day: u32, n: u32 },
+}
+
+// comma-separated values
+#[derive(PartialEq, Debug, Clone)]
+pub struct Field {
+    pub values: Vec<FieldValue>,
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Field {
+    // "*"
+    pub fn is_any(&self) -> bool {
+        self.values == [FieldValue::All { step: 1 }]
+    }
+
+    // "*", "*/2", "*,5"...: cron only checks the day fields together when neither starts with "*"
+    pub fn starts_with_star(&self) -> bool {
+        matches!(self.values.first(), Some(&FieldValue::All { .. }))
+    }
+
+    // all the values matched by the field, sorted
+    pub fn expand(&self) -> Vec<u32> {
+        let mut expanded: Vec<u32> = (self.min..self.max + 1).filter(|&value| self.matches(value)).collect();
+        expanded.dedup();
+        expanded
+    }
+
+    // the days which depend on the month ("L", "5L", "mon#2") are matched by Schedule::runs_on
+    pub fn matches(&self, value: u32) -> bool {
+        self.values.iter().any(|field_value| match *field_value {
+            FieldValue::All { step } => (value - self.min).is_multiple_of(step),
+            FieldValue::Range { start, end, step } => value >= start && value <= end && (value - start).is_multiple_of(step),
+            FieldValue::LastDayOfMonth | FieldValue::LastDayOfWeek { .. } | FieldValue::NthDayOfWeek { .. } => false,
+        })
+    }
+
+    fn month_relative_days(&self) -> Vec<FieldValue> {
+        self.values.iter().filter(|field_value| {
+            matches!(**field_value, FieldValue::LastDayOfMonth | FieldValue::LastDayOfWeek { .. } | FieldValue::NthDayOfWeek { .. })
+        }).cloned().collect()
+    }
+
+    // bit n is set when the field matches n (fields have values up to 59)
+    pub fn bits(&self) -> u64 {
+        (self.min..self.max + 1).filter(|&value| self.matches(value)).fold(0, |bits, value| bits | 1 << value)
+    }
+}
+
+// The canonical form of a field: numbers rather than names, "*" for '?', and 0 for Sunday ("7" -> "0", "5/10" ->
+// "5-59/10")
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sunday = |day: u32| if self.max == 7 { day % 7 } else { day };
+        let values: Vec<String> = self.values.iter().map(|value| match *value {
+            FieldValue::All { step: 1 } => "*".to_string(),
+            FieldValue::All { step } => format!("*/{}", step),
+            FieldValue::Range { start, end, step: 1 } if start == end => sunday(start).to_string(),
+            FieldValue::Range { start, end, step: 1 } => format!("{}-{}", start, end),
+            FieldValue::Range { start, end, step } => format!("{}-{}/{}", start, end, step),
+            FieldValue::LastDayOfMonth => "L".to_string(),
+            FieldValue::LastDayOfWeek { day } => format!("{}L", sunday(day)),
+            FieldValue::NthDayOfWeek { day, n } => format!("{}#{}", sunday(day), n),
+        }).collect();
+        write!(f, "{}", values.join(","))
+    }
+}
+
+// The number of days of the month of `date`
+fn month_length(date: NaiveDate) -> u32 {
+    let mut last = date;
+    while let Some(next) = last.succ_opt().filter(|next| next.month() == date.month()) {
+        last = next;
+    }
+    last.day()
+}
+
+fn has_bit(bits: u64, n: u32) -> bool {
+    bits.checked_shr(n).map(|bits| bits & 1 != 0).unwrap_or(false)
+}
+
+// The values matched by the fields of a schedule as bitmasks (bit n set when n matches), for schedulers matching
+// many times against the same schedule
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ScheduleBits {
+    pub minutes: u64,
+    pub hours: u32,
+    // bits 1 to 31
+    pub days_of_month: u32,
+    // bits 1 to 12
+    pub months: u16,
+    // bits 0 (Sunday) to 6
+    pub days_of_week: u8,
+    // whether the day fields start with "*" (see matches_day)
+    pub day_of_month_star: bool,
+    pub day_of_week_star: bool,
+}
+
+impl ScheduleBits {
+    pub fn has_minute(&self, minute: u32) -> bool {
+        has_bit(self.minutes, minute)
+    }
+
+    pub fn has_hour(&self, hour: u32) -> bool {
+        has_bit(u64::from(self.hours), hour)
+    }
+
+    pub fn has_day_of_month(&self, day_of_month: u32) -> bool {
+        has_bit(u64::from(self.days_of_month), day_of_month)
+    }
+
+    pub fn has_month(&self, month: u32) -> bool {
+        has_bit(u64::from(self.months), month)
+    }
+
+    // Sunday is both 0 and 7
+    pub fn has_day_of_week(&self, day_of_week: u32) -> bool {
+        day_of_week <= 7 && has_bit(u64::from(self.days_of_week), day_of_week % 7)
+    }
+
+    // cron runs a job when either the day of the month or the day of the week matches, unless one of them starts with
+    // "*" (DOM_STAR and DOW_STAR in Vixie cron): then both must match
+    pub fn matches_day(&self, day_of_month: u32, month: u32, day_of_week: u32) -> bool {
+        if !self.has_month(month) {
+            return false;
+        }
+        if self.day_of_month_star || self.day_of_week_star {
+            self.has_day_of_month(day_of_month) && self.has_day_of_week(day_of_week)
+        } else {
+            self.has_day_of_month(day_of_month) || self.has_day_of_week(day_of_week)
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Schedule {
+    pub minute: Field,
+    pub hour: Field,
+    pub day_of_month: Field,
+    pub month: Field,
+    pub day_of_week: Field,
+}
+
+impl Schedule {
+    pub fn bits(&self) -> ScheduleBits {
+        let days_of_week = self.day_of_week.bits();
+        ScheduleBits {
+            minutes: self.minute.bits(),
+            hours: self.hour.bits() as u32,
+            days_of_month: self.day_of_month.bits() as u32,
+            months: self.month.bits() as u16,
+            // 7 is Sunday too
+            days_of_week: ((days_of_week | days_of_week >> 7) & 0x7f) as u8,
+            day_of_month_star: self.day_of_month.starts_with_star(),
+            day_of_week_star: self.day_of_week.starts_with_star(),
+        }
+    }
+
+    // The days which the bits cannot hold, depending on the month ("L", "5L", "mon#2")
+    pub fn month_relative_days(&self) -> Vec<FieldValue> {
+        let mut days = self.day_of_month.month_relative_days();
+        days.extend(self.day_of_week.month_relative_days());
+        days
+    }
+
+    // Whether the job runs on `date`, `bits` being those of the schedule: unlike ScheduleBits::matches_day, it knows the days
+    // which depend on the month ("L", "5L", "mon#2")
+    pub fn runs_on(&self, bits: &ScheduleBits, date: NaiveDate) -> bool {
+        let (day, month, day_of_week) = (date.day(), date.month(), date.weekday().num_days_from_sunday());
+        if self.month_relative_days().is_empty() {
+            return bits.matches_day(day, month, day_of_week);
+        }
+        if !bits.has_month(month) {
+            return false;
+        }
+        let last_week = day + 7 > month_length(date);
+        let day_of_month_matches = bits.has_day_of_month(day) || self.day_of_month.values.iter().any(|value| match *value {
+            FieldValue::LastDayOfMonth => day == month_length(date),
+            _ => false,
+        });
+        let day_of_week_matches = bits.has_day_of_week(day_of_week) || self.day_of_week.values.iter().any(|value| match *value {
+            FieldValue::LastDayOfWeek { day } => day % 7 == day_of_week && last_week,
+            FieldValue::NthDayOfWeek { day: nth_day, n } => nth_day % 7 == day_of_week && (day - 1) / 7 + 1 == n,
+            _ => false,
+        });
+        if bits.day_of_month_star || bits.day_of_week_star {
+            day_of_month_matches && day_of_week_matches
+        } else {
+            day_of_month_matches || day_of_week_matches
+        }
+    }
+
+    // The shortest time between two runs of the job, None if it runs less than twice in 4 years (on February 29th
+    // or never)
+    pub fn min_interval(&self) -> Option<Duration> {
+        // minutes of the day the job runs at, sorted
+        let mut times = vec![];
+        for hour in self.hour.expand() {
+            times.extend(self.minute.expand().into_iter().map(|minute| hour * 60 + minute));
+        }
+        let (first, last) = (i64::from(*times.first()?), i64::from(*times.last()?));
+        let mut shortest = times.windows(2).map(|pair| i64::from(pair[1] - pair[0])).min();
+        // days between the days the job runs, over 4 years for the leap day
+        let bits = self.bits();
+        let mut date = NaiveDate::from_ymd_opt(2024, 1, 1)?;
+        let end = NaiveDate::from_ymd_opt(2028, 1, 1)?;
+        let (mut day, mut last_day) = (0i64, None);
+        while date < end {
+            if self.runs_on(&bits, date) {
+                if let Some(last_day) = last_day {
+                    let between_days = (day - last_day) * 24 * 60 - last + first;
+                    shortest = Some(shortest.map_or(between_days, |shortest| shortest.min(between_days)));
+                }
+                last_day = Some(day);
+            }
+            date = date.succ_opt()?;
+            day += 1;
+        }
+        shortest.map(Duration::minutes)
+    }
+
+    // When the job runs in English ("every 5 minutes", "daily at 02:30", "every Monday and Friday at 08:00"...), None
+    // for schedules with no simple description
+    pub fn describe(&self) -> Option<String> {
+        if !self.month_relative_days().is_empty() {
+            return None;
+        }
+        let bits = self.bits();
+        let times = self.describe_times()?;
+        // single times of the day read "daily at 02:30", other times "every 5 minutes" on every day
+        let at_times = times.starts_with("at ");
+        let days_of_week: Vec<u32> = (0..7).filter(|&day| bits.has_day_of_week(day)).collect();
+        let days_of_month = self.day_of_month.expand();
+        let mut description = match (!self.day_of_month.is_any(), !self.day_of_week.is_any()) {
+            (false, false) if at_times => format!("daily {}", times),
+            (false, false) => times,
+            (false, true) if days_of_week == [1, 2, 3, 4, 5] => format!("{} on weekdays", times),
+            (false, true) => {
+                let days = english_list(days_of_week.iter().map(|&day| ENGLISH_DAY_NAMES[day as usize].to_string()).collect());
+                if at_times { format!("every {} {}", days, times) } else { format!("{} on {}", times, days) }
+            },
+            (true, false) => {
+                if let ([day], [month], true) = (&days_of_month[..], &self.month.expand()[..], at_times) {
+                    return Some(format!("yearly on {} {} {}", ENGLISH_MONTH_NAMES[*month as usize - 1], day, times));
+                }
+                let days = english_list(days_of_month.iter().map(|day| day.to_string()).collect());
+                let days = format!("on day{} {} of the month", if days_of_month.len() > 1 { "s" } else { "" }, days);
+                if at_times && !self.month.is_any() { format!("{} {}", days, times) }
+                else if at_times { format!("monthly {} {}", days, times) }
+                else { format!("{} {}", times, days) }
+            },
+            // cron runs the job on either, or on both when one starts with "*"
+            (true, true) => return None,
+        };
+        if !self.month.is_any() {
+            let months = self.month.expand().iter().map(|&month| ENGLISH_MONTH_NAMES[month as usize - 1].to_string()).collect();
+            description.push_str(&format!(" in {}", english_list(months)));
+        }
+        Some(description)
+    }
+
+    // "every minute", "every 5 minutes", "hourly", "every 2 hours at minute 30", "at 02:30 and 14:30"...
+    fn describe_times(&self) -> Option<String> {
+        let every = |field: &Field| match &field.values[..] {
+            [FieldValue::All { step }] => Some(*step),
+            _ => None,
+        };
+        let at_minute = |minute: u32| if minute == 0 { String::new() } else { format!(" at minute {}", minute) };
+        let minutes = self.minute.expand();
+        match (every(&self.minute), &minutes[..], every(&self.hour)) {
+            (Some(1), _, Some(1)) => Some("every minute".to_string()),
+            (Some(step), _, Some(1)) => Some(format!("every {} minutes", step)),
+            (None, &[minute], Some(1)) => Some(format!("hourly{}", at_minute(minute))),
+            (None, &[minute], Some(step)) => Some(format!("every {} hours{}", step, at_minute(minute))),
+            (None, _, None) => {
+                let mut times = vec![];
+                for hour in self.hour.expand() {
+                    times.extend(minutes.iter().map(|minute| format!("{:02}:{:02}", hour, minute)));
+                }
+                if times.len() > 4 {
+                    return None;
+                }
+                Some(format!("at {}", english_list(times)))
+            },
+            _ => None,
+        }
+    }
+
+    // The next `count` runs from `start` (included), looking up to 4 years ahead
+    pub fn next_runs(&self, start: NaiveDateTime, count: usize) -> Vec<NaiveDateTime> {
+        let mut runs = vec![];
+        for &days in &[1, 31, 366, 4 * 366] {
+            runs = self.runs_between(start, start + Duration::days(days));
+            if runs.len() >= count {
+                break;
+            }
+        }
+        runs.truncate(count);
+        runs
+    }
+
+    // The times the job runs at, from `start` (included) to `end` (excluded)
+    pub fn runs_between(&self, start: NaiveDateTime, end: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let mut runs = vec![];
+        let bits = self.bits();
+        let mut date = start.date();
+        while date <= end.date() {
+            if self.runs_on(&bits, date) {
+                for hour in (0..24).filter(|&hour| bits.has_hour(hour)) {
+                    for minute in (0..60).filter(|&minute| bits.has_minute(minute)) {
+                        match date.and_hms_opt(hour, minute, 0) {
+                            Some(time) if time >= start && time < end => runs.push(time),
+                            _ => (),
+                        }
+                    }
+                }
+            }
+            date = match date.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        runs
+    }
+}
+
+// "*/5 2 * * mon".parse::<Schedule>(): the schedule is checked first, with the messages of the parser (of the extended
+// dialect, whose '?', 'L' and '#' schedules can hold). @ shortcuts are read as their schedule.
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(schedule: &str) -> Result<Schedule, String> {
+        let schedule = match nickname_schedule(schedule.trim()) {
+            Some(schedule) => schedule,
+            None if schedule.trim() == "@reboot" => return Err("@reboot has no schedule".to_string()),
+            None if schedule.trim().starts_with('@') => return Err(format!("unknown shortcut '{}'", schedule.trim())),
+            None => schedule,
+        };
+        check_schedule_fields(schedule, Dialect::Extended)?;
+        parse_schedule(schedule)
+    }
+}
+
+// A schedule in the configuration of an application (`backup_schedule: "0 2 * * *"` in YAML), checked when it is
+// loaded as by FromStr
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Schedule, D::Error> {
+        let schedule = String::deserialize(deserializer)?;
+        schedule.parse().map_err(|e| de::Error::custom(format!("invalid cron schedule '{}': {}", schedule, e)))
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {} {}", self.minute, self.hour, self.day_of_month, self.month, self.day_of_week)
+    }
+}
+
+// "a", "a and b", "a, b and c"
+fn english_list(items: Vec<String>) -> String {
+    match items.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => items.join(""),
+    }
+}
+
+// A schedule ("0 2 * * *", or a @ shortcut) in English, or the schedule itself when it has no simple description
+pub fn describe_schedule(schedule: &str) -> String {
+    if schedule == "@reboot" {
+        return "at startup".to_string();
+    }
+    match parse_schedule(nickname_schedule(schedule).unwrap_or(schedule)).ok().and_then(|parsed| parsed.describe()) {
+        Some(description) => description,
+        None => format!("on \"{}\"", schedule),
+    }
+}
+
+fn parse_value(value: &str, names: &[&str], offset: u32, min: u32, max: u32) -> Result<u32, String> {
+    let lowercase = value.to_lowercase();
+    if let Some(index) = names.iter().position(|name| *name == lowercase) {
+        return Ok(index as u32 + offset);
+    }
+    match value.parse::<u32>() {
+        Ok(number) if number >= min && number <= max => Ok(number),
+        Ok(number) => Err(format!("value {} out of bounds (accepted: {} to {})", number, min, max)),
+        Err(_) => Err(format!("invalid value '{}'", value)),
+    }
+}
+
+// `names` are the names of the values, from `offset`
+fn parse_field(field: &str, names: &[&str], offset: u32, min: u32, max: u32) -> Result<Field, String> {
+    let values = field.split(',').map(|item| {
+        let (range, step) = match item.find('/') {
+            Some(index) => match item[index + 1..].parse::<u32>() {
+                Ok(step) if step > 0 => (&item[..index], step),
+                _ => return Err(format!("invalid step in '{}'", item)),
+            },
+            None => (item, 1),
+        };
+        // '?' (in the day fields of the extended dialect) is '*'
+        if range == "*" || range == "?" {
+            return Ok(FieldValue::All { step });
+        }
+        let mut bounds = range.splitn(2, '-');
+        let start = parse_value(bounds.next().unwrap_or(""), names, offset, min, max)?;
+        let end = match bounds.next() {
+            Some(end) => parse_value(end, names, offset, min, max)?,
+            // "5/10" is "5-59/10"
+            None if step > 1 => max,
+            None => start,
+        };
+        if end < start {
+            return Err(format!("invalid range '{}'", range));
+        }
+        Ok(FieldValue::Range { start, end, step })
+    }).collect::<Result<Vec<_>, String>>()?;
+    Ok(Field { values, min, max })
+}
+
+// The day of the week field, with the days of the extended dialect which depend on the month ("5L", "mon#2")
+fn parse_day_of_week_field(field: &str) -> Result<Field, String> {
+    if let Some(index) = field.find('#') {
+        let day = parse_value(&field[..index], DAY_NAMES, 0, 0, 7)?;
+        let n = parse_value(&field[index + 1..], &[], 0, 1, 5)?;
+        return Ok(Field { values: vec![FieldValue::NthDayOfWeek { day, n }], min: 0, max: 7 });
+    }
+    if field.len() > 1 && field.ends_with('L') {
+        let day = parse_value(&field[..field.len() - 1], DAY_NAMES, 0, 0, 7)?;
+        return Ok(Field { values: vec![FieldValue::LastDayOfWeek { day }], min: 0, max: 7 });
+    }
+    parse_field(field, DAY_NAMES, 0, 0, 7)
+}
+
+// Parse the five fields of a schedule ("*/5 1-3 * jan mon,fri")
+pub fn parse_schedule(schedule: &str) -> Result<Schedule, String> {
+    let fields: Vec<&str> = schedule.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(format!("expected 5 fields in the schedule, got {}", fields.len()));
+    }
+    Ok(Schedule {
+        minute: parse_field(fields[0], &[], 0, 0, 59)?,
+        hour: parse_field(fields[1], &[], 0, 0, 23)?,
+        day_of_month: match fields[2] {
+            "L" => Field { values: vec![FieldValue::LastDayOfMonth], min: 1, max: 31 },
+            field => parse_field(field, &[], 0, 1, 31)?,
+        },
+        month: parse_field(fields[3], MONTH_NAMES, 1, 1, 12)?,
+        day_of_week: parse_day_of_week_field(fields[4])?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::NaiveDate;
+
+    use schedule::*;
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule = parse_schedule("*/15 1-3 * jan mon,fri").unwrap();
+        assert_eq!(schedule.minute.expand(), vec![0, 15, 30, 45]);
+        assert_eq!(schedule.hour.expand(), vec![1, 2, 3]);
+        assert!(schedule.day_of_month.is_any());
+        assert_eq!(schedule.month.expand(), vec![1]);
+        assert_eq!(schedule.day_of_week.expand(), vec![1, 5]);
+        assert_eq!(parse_schedule("5/20 * * * *").unwrap().minute.expand(), vec![5, 25, 45]);
+        assert!(parse_schedule("* 24 * * *").is_err());
+        assert!(parse_schedule("* * * *").is_err());
+        assert!(parse_schedule("5-1 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let canonical = |schedule| parse_schedule(schedule).unwrap().to_string();
+        assert_eq!(canonical("*/5  2 * * mon"), "*/5 2 * * 1");
+        assert_eq!(canonical("0,30 9-17/2 ? JAN-mar 7"), "0,30 9-17/2 * 1-3 0");
+        assert_eq!(canonical("5/10 0 L * *"), "5-59/10 0 L * *");
+        assert_eq!(canonical("0 9 * * friL"), "0 9 * * 5L");
+        assert_eq!(parse_schedule("0 9 * * sun#2").unwrap().to_string(), "0 9 * * 0#2");
+        for schedule in &["0 2 1,15 * 1-5", "*/15 * * 6-8 *"] {
+            assert_eq!(parse_schedule(&canonical(schedule)), parse_schedule(schedule));
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("*/5 2 * * mon".parse::<Schedule>(), parse_schedule("*/5 2 * * mon"));
+        assert_eq!("@daily".parse::<Schedule>(), parse_schedule("0 0 * * *"));
+        assert_eq!("0 9 ? * mon#2".parse::<Schedule>().map(|schedule| schedule.to_string()), Ok("0 9 * * 1#2".to_string()));
+        assert!("61 * * * *".parse::<Schedule>().unwrap_err().contains("value 61 out of bounds (accepted: 0 to 59)"));
+        assert!("0 2 * *".parse::<Schedule>().is_err());
+        assert_eq!("@reboot".parse::<Schedule>(), Err("@reboot has no schedule".to_string()));
+        assert_eq!("@sometimes".parse::<Schedule>(), Err("unknown shortcut '@sometimes'".to_string()));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{Error, StrDeserializer};
+
+        let deserialize = |schedule: &str| {
+            let deserializer: StrDeserializer<Error> = schedule.into_deserializer();
+            Schedule::deserialize(deserializer).map_err(|e| e.to_string())
+        };
+        assert_eq!(deserialize("30 2 * * mon-fri"), parse_schedule("30 2 * * mon-fri"));
+        assert_eq!(deserialize("@hourly"), parse_schedule("0 * * * *"));
+        assert!(deserialize("0 2 * * mon-8").unwrap_err().starts_with("invalid cron schedule '0 2 * * mon-8': "));
+    }
+
+    #[test]
+    fn test_matches_day() {
+        // on the 1st and 15th, and on Mondays
+        let bits = parse_schedule("0 0 1,15 * mon").unwrap().bits();
+        assert!(bits.matches_day(1, 3, 4));
+        assert!(bits.matches_day(2, 3, 1));
+        assert!(!bits.matches_day(2, 3, 2));
+        let bits = parse_schedule("0 0 * * 7").unwrap().bits();
+        assert!(bits.matches_day(5, 1, 0));
+        // on the Mondays of the odd days: the day of the month starts with "*"
+        let bits = parse_schedule("0 0 */2 * mon").unwrap().bits();
+        assert!(bits.matches_day(3, 3, 1));
+        assert!(!bits.matches_day(2, 3, 1));
+        assert!(!bits.matches_day(3, 3, 2));
+    }
+
+    #[test]
+    fn test_bits() {
+        let bits = parse_schedule("*/15 1-3 * jan mon,fri,sun").unwrap().bits();
+        assert_eq!(bits.minutes, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(bits.hours, 0b1110);
+        assert_eq!(bits.days_of_month, 0xffff_fffe);
+        assert_eq!(bits.months, 0b10);
+        assert_eq!(bits.days_of_week, 0b100011);
+        assert!(bits.has_minute(30) && !bits.has_minute(31) && !bits.has_minute(100));
+        assert!(bits.has_day_of_week(7) && !bits.has_day_of_week(8));
+        assert!(bits.day_of_month_star && !bits.day_of_week_star);
+        assert_eq!(parse_schedule("0 0 * * 7").unwrap().bits().days_of_week, 1);
+    }
+
+    #[test]
+    fn test_min_interval() {
+        let interval = |schedule| parse_schedule(schedule).unwrap().min_interval();
+        assert_eq!(interval("*/15 * * * *"), Some(Duration::minutes(15)));
+        assert_eq!(interval("0 22,23 * * *"), Some(Duration::hours(1)));
+        assert_eq!(interval("0 1,23 * * *"), Some(Duration::hours(2)));
+        assert_eq!(interval("30 2 * * mon,tue"), Some(Duration::days(1)));
+        assert_eq!(interval(nickname_schedule("@weekly").unwrap()), Some(Duration::weeks(1)));
+        assert_eq!(interval("0 0 1 1 *"), Some(Duration::days(365)));
+        assert_eq!(interval("0 0 29 2 *"), None);
+        assert_eq!(interval("0 0 30 2 *"), None);
+    }
+
+    #[test]
+    fn test_describe_schedule() {
+        assert_eq!(describe_schedule("* * * * *"), "every minute");
+        assert_eq!(describe_schedule("*/5 * * * *"), "every 5 minutes");
+        assert_eq!(describe_schedule("@hourly"), "hourly");
+        assert_eq!(describe_schedule("15 */2 * * *"), "every 2 hours at minute 15");
+        assert_eq!(describe_schedule("30 2 * * *"), "daily at 02:30");
+        assert_eq!(describe_schedule("0 8,20 * * *"), "daily at 08:00 and 20:00");
+        assert_eq!(describe_schedule("0 8 * * 1-5"), "at 08:00 on weekdays");
+        assert_eq!(describe_schedule("@weekly"), "every Sunday at 00:00");
+        assert_eq!(describe_schedule("*/10 * * * mon,fri"), "every 10 minutes on Monday and Friday");
+        assert_eq!(describe_schedule("@monthly"), "monthly on day 1 of the month at 00:00");
+        assert_eq!(describe_schedule("0 0 1,15 * *"), "monthly on days 1 and 15 of the month at 00:00");
+        assert_eq!(describe_schedule("@yearly"), "yearly on January 1 at 00:00");
+        assert_eq!(describe_schedule("0 6 * jun-aug *"), "daily at 06:00 in June, July and August");
+        assert_eq!(describe_schedule("@reboot"), "at startup");
+        assert_eq!(describe_schedule("0 0 1 * mon"), "on \"0 0 1 * mon\"");
+        assert_eq!(describe_schedule("0 2 */2 * 1"), "on \"0 2 */2 * 1\"");
+        assert_eq!(describe_schedule("*/5 9-17 * * *"), "on \"*/5 9-17 * * *\"");
+    }
+
+    #[test]
+    fn test_runs_between() {
+        let start = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(23, 10, 0).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 3, 3).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let runs = parse_schedule("0,30 0,23 * * *").unwrap().runs_between(start, end);
+        assert_eq!(runs, vec![
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(23, 30, 0).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 2).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 2).unwrap().and_hms_opt(0, 30, 0).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 2).unwrap().and_hms_opt(23, 0, 0).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 2).unwrap().and_hms_opt(23, 30, 0).unwrap(),
+        ]);
+        // March 2nd, 2024 was a Saturday
+        assert_eq!(parse_schedule("0 12 * * sat").unwrap().runs_between(start, end).len(), 1);
+        let runs = parse_schedule("0 0 L * *").unwrap().next_runs(start, 3);
+        assert_eq!(runs, vec![NaiveDate::from_ymd_opt(2024, 3, 31).unwrap().and_hms_opt(0, 0, 0).unwrap(), NaiveDate::from_ymd_opt(2024, 4, 30).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+                              NaiveDate::from_ymd_opt(2024, 5, 31).unwrap().and_hms_opt(0, 0, 0).unwrap()]);
+        let runs = parse_schedule("0 0 * 2 friL").unwrap().next_runs(start, 2);
+        assert_eq!(runs, vec![NaiveDate::from_ymd_opt(2025, 2, 28).unwrap().and_hms_opt(0, 0, 0).unwrap(), NaiveDate::from_ymd_opt(2026, 2, 27).unwrap().and_hms_opt(0, 0, 0).unwrap()]);
+        assert_eq!(parse_schedule("0 0 L 2 *").unwrap().min_interval(), Some(Duration::days(365)));
+        let runs = parse_schedule("30 9 * * mon#2").unwrap().next_runs(start, 2);
+        assert_eq!(runs, vec![NaiveDate::from_ymd_opt(2024, 3, 11).unwrap().and_hms_opt(9, 30, 0).unwrap(), NaiveDate::from_ymd_opt(2024, 4, 8).unwrap().and_hms_opt(9, 30, 0).unwrap()]);
+        // February only has a fifth Friday in the leap years where it starts on a Friday
+        assert!(parse_schedule("0 0 * 2 5#5").unwrap().next_runs(start, 1).is_empty());
+        assert!(parse_schedule("0 0 * * mon#6").is_err());
+        let runs = parse_schedule("0 0 29 2 *").unwrap().next_runs(start, 2);
+        assert_eq!(runs, vec![NaiveDate::from_ymd_opt(2028, 2, 29).unwrap().and_hms_opt(0, 0, 0).unwrap()]);
+        assert_eq!(parse_schedule("*/20 * * * *").unwrap().next_runs(start, 3).len(), 3);
+    }
+
+}
@@ -0,0 +1,54 @@
+use std::cmp;
+
+
+// Levenshtein distance between two strings (on chars)
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b_chars.len() + 1).collect();
+    let mut current: Vec<usize> = vec![0; b_chars.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current[j + 1] = cmp::min(substitution, cmp::min(previous[j + 1] + 1, current[j] + 1));
+        }
+        previous.clone_from(&current);
+    }
+    previous[b_chars.len()]
+}
+
+// Return the candidate closest to `word`, if it is close enough to be a plausible typo
+pub fn closest_match<'a, T: AsRef<str>>(word: &str, candidates: &'a [T]) -> Option<&'a str> {
+    let max_distance = cmp::max(1, word.chars().count() / 3);
+    candidates.iter()
+        .map(|candidate| candidate.as_ref())
+        .filter(|candidate| !candidate.is_empty() && *candidate != word)
+        .map(|candidate| (edit_distance(word, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use suggest::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("root", "root"), 0);
+        assert_eq!(edit_distance("rooot", "root"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_closest_match() {
+        let candidates = ["root", "www-data", "backup"];
+        assert_eq!(closest_match("rooot", &candidates), Some("root"));
+        assert_eq!(closest_match("bakcup", &candidates), Some("backup"));
+        assert_eq!(closest_match("nobody", &candidates), None);
+        assert_eq!(closest_match("x", &["", "y"]), Some("y"));
+    }
+
+}
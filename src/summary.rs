@@ -0,0 +1,156 @@
+// The recap printed at the end of a check of files: files and lines checked, errors and warnings, and the rules which
+// found the most, so that CI logs end with a summary of the findings. Also the JSON document of --format json.
+
+use std::collections::HashMap;
+
+use serde_json;
+
+use checker::{Diagnostic, Severity};
+use codes::rule_code;
+
+
+// rules listed in the summary
+const TOP_RULES: usize = 5;
+
+#[derive(Default)]
+pub struct Summary {
+    pub files_checked: usize,
+    pub invalid_files: usize,
+    pub lines: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    // number of errors and warnings of each rule
+    rules: HashMap<&'static str, usize>,
+}
+
+impl Summary {
+    // Count a checked file of `lines` lines, returns whether it is valid
+    pub fn record(&mut self, lines: usize, diagnostics: &[Diagnostic]) -> bool {
+        let errors = diagnostics.iter().filter(|diagnostic| self.count(diagnostic)).count();
+        self.record_file(lines, errors == 0)
+    }
+
+    // Count a diagnostic of the file being checked (e.g. read from a pipe), returns whether it is an error
+    pub fn count(&mut self, diagnostic: &Diagnostic) -> bool {
+        match diagnostic.severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Info => return false,
+        }
+        *self.rules.entry(diagnostic.rule).or_insert(0) += 1;
+        diagnostic.severity == Severity::Error
+    }
+
+    // Count a checked file of `lines` lines, once its diagnostics are counted. Returns `valid`.
+    pub fn record_file(&mut self, lines: usize, valid: bool) -> bool {
+        self.files_checked += 1;
+        self.lines += lines;
+        if !valid {
+            self.invalid_files += 1;
+        }
+        valid
+    }
+
+    // The rules which found the most, most first (then by name)
+    pub fn top_rules(&self) -> Vec<(&'static str, usize)> {
+        let mut rules: Vec<(&'static str, usize)> = self.rules.iter().map(|(&rule, &count)| (rule, count)).collect();
+        rules.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        rules.truncate(TOP_RULES);
+        rules
+    }
+
+    // "12 files checked (340 lines), 1 invalid: 2 errors, 5 warnings (most found: relative-program 3, syntax 2...)"
+    pub fn text(&self) -> String {
+        let mut text = format!("{} files checked ({} lines), {} invalid: {} errors, {} warnings",
+                               self.files_checked, self.lines, self.invalid_files, self.errors, self.warnings);
+        let top_rules = self.top_rules();
+        if !top_rules.is_empty() {
+            let rules: Vec<String> = top_rules.iter().map(|&(rule, count)| format!("{} {}", rule, count)).collect();
+            text.push_str(&format!(" (most found: {})", rules.join(", ")));
+        }
+        text
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        json!({
+            "files_checked": self.files_checked,
+            "invalid_files": self.invalid_files,
+            "lines": self.lines,
+            "errors": self.errors,
+            "warnings": self.warnings,
+            "top_rules": self.top_rules().iter().map(|&(rule, count)| json!({"rule": rule, "count": count})).collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "info",
+    }
+}
+
+// The diagnostics of a file in the JSON document of --format json
+pub fn file_json(path: &str, diagnostics: &[Diagnostic]) -> serde_json::Value {
+    json!({
+        "path": path,
+        "diagnostics": diagnostics.iter().map(|diagnostic| json!({
+            "line": diagnostic.line_number,
+            "severity": severity_name(diagnostic.severity),
+            "rule": diagnostic.rule,
+            "code": rule_code(diagnostic.rule),
+            "message": diagnostic.message,
+            "text": diagnostic.line,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+// {"files": [{"path": "/etc/crontab", "diagnostics": [...]}...], "summary": {"files_checked": 12...}}
+pub fn check_json(files: Vec<serde_json::Value>, summary: &Summary) -> String {
+    serde_json::to_string_pretty(&json!({ "files": files, "summary": summary.json() })).unwrap_or_default()
+}
+
+// The number of lines of a file, as cron reads them (a last line without newline counts)
+pub fn line_count(contents: &[u8]) -> usize {
+    let newlines = contents.iter().filter(|&&c| c == b'\n').count();
+    if contents.is_empty() || contents.ends_with(b"\n") { newlines } else { newlines + 1 }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use checker::{Diagnostic, Severity};
+    use summary::*;
+
+    fn diagnostic(severity: Severity, rule: &'static str) -> Diagnostic {
+        Diagnostic { line_number: 1, line: String::new(), severity, rule, message: String::new() }
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut summary = Summary::default();
+        assert!(!summary.record(10, &[diagnostic(Severity::Error, "syntax"), diagnostic(Severity::Warning, "relative-program"), diagnostic(Severity::Info, "env-order")]));
+        assert!(summary.record(5, &[diagnostic(Severity::Warning, "relative-program")]));
+        assert_eq!(summary.top_rules(), vec![("relative-program", 2), ("syntax", 1)]);
+        assert_eq!(summary.text(), "2 files checked (15 lines), 1 invalid: 1 errors, 2 warnings (most found: relative-program 2, syntax 1)");
+        assert_eq!(summary.json()["top_rules"][0]["rule"], "relative-program");
+        assert_eq!(Summary::default().text(), "0 files checked (0 lines), 0 invalid: 0 errors, 0 warnings");
+        let json = check_json(vec![file_json("/etc/crontab", &[diagnostic(Severity::Error, "syntax")])], &summary);
+        assert!(json.contains("\"rule\": \"syntax\""));
+        assert!(json.contains("\"code\": \"CTC001\""));
+        assert!(json.contains("\"invalid_files\": 1"));
+        assert!(summary.count(&diagnostic(Severity::Error, "syntax")));
+        assert!(!summary.count(&diagnostic(Severity::Info, "env-order")));
+        assert!(!summary.record_file(3, false));
+        assert_eq!(summary.text(), "3 files checked (18 lines), 2 invalid: 2 errors, 2 warnings (most found: relative-program 2, syntax 2)");
+    }
+
+    #[test]
+    fn test_line_count() {
+        assert_eq!(line_count(b""), 0);
+        assert_eq!(line_count(b"a\nb\n"), 2);
+        assert_eq!(line_count(b"a\nb"), 2);
+    }
+
+}
@@ -0,0 +1,93 @@
+// Findings sent to the system log (syslog, or the journal, which reads the same socket), for scheduled audits whose
+// standard output nobody reads
+
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::process;
+
+use checker::{Diagnostic, Severity};
+
+
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+const LOG_USER: u8 = 1;
+
+pub struct Syslog {
+    socket: UnixDatagram,
+    // "crontabcheck[1234]"
+    tag: String,
+}
+
+impl Syslog {
+    pub fn connect() -> io::Result<Self> {
+        Syslog::connect_to(Path::new(SYSLOG_SOCKET))
+    }
+
+    fn connect_to(path: &Path) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Syslog { socket, tag: format!("crontabcheck[{}]", process::id()) })
+    }
+
+    pub fn log(&self, severity: Severity, message: &str) {
+        let priority = LOG_USER * 8 + match severity {
+            Severity::Error => 3,
+            Severity::Warning => 4,
+            Severity::Info => 5,
+        };
+        // a record per finding: the lines of a message ("Caused by: ...") are joined
+        let message = message.lines().map(str::trim).collect::<Vec<&str>>().join(" ");
+        if let Err(e) = self.socket.send(format!("<{}>{}: {}", priority, self.tag, message).as_bytes()) {
+            eprintln!("could not log to syslog: {}", e);
+        }
+    }
+
+    // Log diagnostics ("/etc/cron.d/backup:3: ..."), returns false if one of them is an error
+    pub fn log_diagnostics(&self, diagnostics: &[Diagnostic], path: Option<&str>) -> bool {
+        let mut valid = true;
+        for diagnostic in diagnostics {
+            let location = match (path, diagnostic.line_number) {
+                (Some(path), 0) => path.to_string(),
+                (Some(path), line_number) => format!("{}:{}", path, line_number),
+                (None, 0) => "stdin".to_string(),
+                (None, line_number) => format!("stdin:{}", line_number),
+            };
+            self.log(diagnostic.severity, &format!("{}: {}", location, diagnostic.message));
+            if let Severity::Error = diagnostic.severity {
+                valid = false;
+            }
+        }
+        valid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+
+    use syslog::*;
+
+    #[test]
+    fn test_log_diagnostics() {
+        let path = env::temp_dir().join(format!("crontabcheck-syslog-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        let syslog = Syslog::connect_to(&path).unwrap();
+        let diagnostics = [Diagnostic {
+            line_number: 3,
+            line: "0 2 * * wedn root /usr/bin/backup".to_string(),
+            severity: Severity::Error,
+            rule: "syntax",
+            message: "could not parse the field\nCaused by: value out of bounds".to_string(),
+        }];
+        assert!(!syslog.log_diagnostics(&diagnostics, Some("/etc/cron.d/backup")));
+        let mut buffer = [0; 512];
+        let length = server.recv(&mut buffer).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer[..length]),
+                   format!("<11>crontabcheck[{}]: /etc/cron.d/backup:3: could not parse the field Caused by: value out of bounds", process::id()));
+        fs::remove_file(&path).unwrap();
+    }
+
+}
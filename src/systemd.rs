@@ -0,0 +1,267 @@
+// systemd timers: calendar event expressions (OnCalendar=), as described in systemd.time(7)
+
+use std::path::Path;
+
+use checker::{Diagnostic, Severity};
+use environment::check_timezone;
+
+
+// One value of a calendar component: "5", "1..5", "0/15" or "1..10/3"
+#[derive(PartialEq, Debug, Clone)]
+pub struct CalendarValue {
+    pub start: u32,
+    pub end: Option<u32>,
+    pub repeat: Option<u32>,
+}
+
+// The values of a component of the date or time, None for "*"
+pub type CalendarComponent = Option<Vec<CalendarValue>>;
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct CalendarEvent {
+    // ranges of days of the week, from 1 (Monday) to 7 (Sunday), None for every day
+    pub weekdays: Option<Vec<(u32, u32)>>,
+    pub years: CalendarComponent,
+    pub months: CalendarComponent,
+    pub days: CalendarComponent,
+    // days are counted from the end of the month ("*-02~03")
+    pub last_days: bool,
+    pub hours: CalendarComponent,
+    pub minutes: CalendarComponent,
+    pub seconds: CalendarComponent,
+    pub timezone: Option<String>,
+}
+
+const WEEKDAYS: &[&str] = &["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+const SHORTHANDS: &[(&str, &str)] = &[
+    ("minutely", "*-*-* *:*:00"),
+    ("hourly", "*-*-* *:00:00"),
+    ("daily", "*-*-* 00:00:00"),
+    ("weekly", "Mon *-*-* 00:00:00"),
+    ("monthly", "*-*-01 00:00:00"),
+    ("quarterly", "*-01,04,07,10-01 00:00:00"),
+    ("semiannually", "*-01,07-01 00:00:00"),
+    ("yearly", "*-01-01 00:00:00"),
+    ("annually", "*-01-01 00:00:00"),
+];
+
+// "Mon", "monday"... -> 1
+fn weekday_number(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    WEEKDAYS.iter()
+        .position(|day| name.len() >= 3 && day.starts_with(&name[..]))
+        .map(|index| index as u32 + 1)
+}
+
+fn parse_weekdays(token: &str) -> Result<Vec<(u32, u32)>, String> {
+    token.split(',').map(|range| {
+        let mut bounds = range.splitn(2, "..");
+        let start = bounds.next().unwrap_or("");
+        let end = bounds.next().unwrap_or(start);
+        match (weekday_number(start), weekday_number(end)) {
+            (Some(start), Some(end)) => Ok((start, end)),
+            _ => Err(format!("invalid day of the week '{}'", range)),
+        }
+    }).collect()
+}
+
+fn parse_number(value: &str, name: &str, min: u32, max: u32) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(number) if number >= min && number <= max => Ok(number),
+        Ok(number) => Err(format!("{} {} out of bounds (accepted: {} to {})", name, number, min, max)),
+        Err(_) => Err(format!("invalid {} '{}'", name, value)),
+    }
+}
+
+fn parse_component(component: &str, name: &str, min: u32, max: u32) -> Result<CalendarComponent, String> {
+    if component == "*" {
+        return Ok(None);
+    }
+    component.split(',').map(|value| {
+        let (range, repeat) = match value.find('/') {
+            Some(index) => (&value[..index], Some(parse_number(&value[index + 1..], "repetition", 1, max.max(1))?)),
+            None => (value, None),
+        };
+        let mut bounds = range.splitn(2, "..");
+        let start = match bounds.next().unwrap_or("") {
+            "*" if repeat.is_some() => min,
+            start => parse_number(start, name, min, max)?,
+        };
+        let end = match bounds.next() {
+            Some(end) => Some(parse_number(end, name, start, max)?),
+            None => None,
+        };
+        Ok(CalendarValue { start, end, repeat })
+    }).collect::<Result<Vec<_>, String>>().map(Some)
+}
+
+fn parse_date(token: &str, event: &mut CalendarEvent) -> Result<(), String> {
+    let (date, day) = match token.rfind('~') {
+        Some(index) => {
+            event.last_days = true;
+            (&token[..index], &token[index + 1..])
+        },
+        None => match token.rfind('-') {
+            Some(index) => (&token[..index], &token[index + 1..]),
+            None => return Err(format!("invalid date '{}'", token)),
+        },
+    };
+    let parts: Vec<&str> = date.split('-').collect();
+    let (year, month) = match parts.len() {
+        1 => ("*", parts[0]),
+        2 => (parts[0], parts[1]),
+        _ => return Err(format!("invalid date '{}'", token)),
+    };
+    event.years = parse_component(year, "year", 1970, 2199)?;
+    event.months = parse_component(month, "month", 1, 12)?;
+    event.days = parse_component(day, "day", 1, 31)?;
+    Ok(())
+}
+
+fn parse_time(token: &str, event: &mut CalendarEvent) -> Result<(), String> {
+    let parts: Vec<&str> = token.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return Err(format!("invalid time '{}'", token));
+    }
+    event.hours = parse_component(parts[0], "hour", 0, 23)?;
+    event.minutes = parse_component(parts[1], "minute", 0, 59)?;
+    // fractions of seconds are allowed, but cron has nothing to do with them
+    let seconds = parts.get(2).map(|seconds| seconds.split('.').next().unwrap_or("")).unwrap_or("00");
+    event.seconds = parse_component(seconds, "second", 0, 59)?;
+    Ok(())
+}
+
+// Parse an OnCalendar= expression, e.g. "Mon..Fri *-*-* 02:00" or "daily"
+pub fn parse_calendar(expression: &str) -> Result<CalendarEvent, String> {
+    let expression = expression.trim();
+    if let Some(&(_, expanded)) = SHORTHANDS.iter().find(|&&(name, _)| name == expression.to_lowercase()) {
+        return parse_calendar(expanded);
+    }
+    let mut event = CalendarEvent::default();
+    let midnight = Some(vec![CalendarValue { start: 0, end: None, repeat: None }]);
+    event.hours = midnight.clone();
+    event.minutes = midnight.clone();
+    event.seconds = midnight;
+    let mut tokens = expression.split_whitespace().peekable();
+    if let Some(weekdays) = tokens.peek().and_then(|token| parse_weekdays(token).ok()) {
+        event.weekdays = Some(weekdays);
+        tokens.next();
+    }
+    let (mut date_seen, mut time_seen) = (false, false);
+    for token in tokens {
+        if event.timezone.is_some() {
+            return Err(format!("unexpected '{}' after the time zone", token));
+        }
+        if token.contains(':') && !time_seen {
+            parse_time(token, &mut event)?;
+            time_seen = true;
+        } else if !date_seen && !time_seen && token.contains(['-', '~'])
+            && token.starts_with(|c: char| c.is_ascii_digit() || c == '*') {
+            parse_date(token, &mut event)?;
+            date_seen = true;
+        } else if date_seen || time_seen {
+            event.timezone = Some(token.to_string());
+        } else {
+            return Err(format!("unexpected '{}' (expected a day of the week, a date or a time)", token));
+        }
+    }
+    if event.weekdays.is_none() && !date_seen && !time_seen {
+        return Err(format!("invalid calendar expression '{}'", expression));
+    }
+    Ok(event)
+}
+
+pub fn check_calendar(expression: &str, zoneinfo: &Path) -> Result<(), String> {
+    let event = parse_calendar(expression)?;
+    match event.timezone {
+        Some(ref timezone) => check_timezone("OnCalendar", timezone, zoneinfo),
+        None => Ok(()),
+    }
+}
+
+// A "Key=value" line of a unit file
+pub struct UnitSetting<'a> {
+    pub line_number: usize,
+    pub line: &'a str,
+    // e.g. "[Timer]"
+    pub section: &'a str,
+    pub key: &'a str,
+    pub value: &'a str,
+}
+
+// The settings of a unit file, in order (continuation lines are not supported)
+pub fn unit_settings<'a>(contents: &'a str) -> Vec<UnitSetting<'a>> {
+    let mut settings = vec![];
+    let mut section = "";
+    for (index, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            section = trimmed;
+            continue;
+        }
+        if trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        let mut setting = trimmed.splitn(2, '=');
+        if let (Some(key), Some(value)) = (setting.next(), setting.next()) {
+            settings.push(UnitSetting { line_number: index + 1, line, section, key: key.trim(), value: value.trim() });
+        }
+    }
+    settings
+}
+
+// Check the OnCalendar= settings of a .timer unit
+pub fn check_timer_unit(contents: &str, zoneinfo: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for setting in unit_settings(contents) {
+        // an empty value resets the list of events
+        if setting.section == "[Timer]" && setting.key == "OnCalendar" && !setting.value.is_empty() {
+            if let Err(message) = check_calendar(setting.value, zoneinfo) {
+                diagnostics.push(Diagnostic { line_number: setting.line_number, line: setting.line.to_string(), severity: Severity::Error, rule: "oncalendar", message });
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+
+    use systemd::*;
+
+    #[test]
+    fn test_parse_calendar() {
+        let event = parse_calendar("Mon..Fri *-*-* 02:00").unwrap();
+        assert_eq!(event.weekdays, Some(vec![(1, 5)]));
+        assert_eq!(event.days, None);
+        assert_eq!(event.hours, Some(vec![CalendarValue { start: 2, end: None, repeat: None }]));
+        assert_eq!(event.seconds, Some(vec![CalendarValue { start: 0, end: None, repeat: None }]));
+
+        let event = parse_calendar("*:0/15").unwrap();
+        assert_eq!(event.hours, None);
+        assert_eq!(event.minutes, Some(vec![CalendarValue { start: 0, end: None, repeat: Some(15) }]));
+
+        let event = parse_calendar("*-02~03 Europe/Paris").unwrap();
+        assert!(event.last_days);
+        assert_eq!(event.timezone, Some("Europe/Paris".to_string()));
+
+        assert_eq!(parse_calendar("weekly").unwrap().weekdays, Some(vec![(1, 1)]));
+        assert!(parse_calendar("Sat,Sun 10:00").is_ok());
+        assert!(parse_calendar("2024-13-01").is_err());
+        assert!(parse_calendar("*-*-* 25:00").is_err());
+        assert!(parse_calendar("Someday 10:00").is_err());
+        assert!(parse_calendar("").is_err());
+    }
+
+    #[test]
+    fn test_check_timer_unit() {
+        let unit = "[Unit]\nDescription=Backup\n\n[Timer]\nOnCalendar=daily\nOnCalendar=*-*-* 24:00\nOnCalendar=\n";
+        let diagnostics = check_timer_unit(unit, Path::new("/nonexistent"));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 6);
+    }
+
+}
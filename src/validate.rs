@@ -0,0 +1,67 @@
+// Validation of a whole crontab by the parser alone, for the library
+
+use std::slice;
+
+use parser::{CrontabLine, CrontabParserOptions, SyntaxError, parse_crontab, walk_errors};
+
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct ValidationError {
+    pub line_number: usize,
+    // byte offset of the error in the line (None when it is not at a place of the line)
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+// A line, or its errors (one per invalid field)
+pub fn validate_line<'a, T: AsRef<str>>(line_number: usize, line: &'a str, options: &CrontabParserOptions<'a, T>) -> Result<CrontabLine<'a>, Vec<ValidationError>> {
+    let error = |error: &SyntaxError| ValidationError { line_number, column: Some(error.offset()), message: walk_errors(slice::from_ref(error)) };
+    parse_crontab(line, options).map_err(|errors| errors.iter().map(error).collect())
+}
+
+pub fn validate_crontab<T: AsRef<str>>(contents: &str, options: &CrontabParserOptions<T>) -> Vec<ValidationError> {
+    let mut errors = vec![];
+    let mut last_line = None;
+    for (index, line) in contents.lines().enumerate() {
+        match validate_line(index + 1, line, options) {
+            Ok(parsed) => last_line = Some((index + 1, parsed)),
+            Err(line_errors) => errors.extend(line_errors),
+        }
+    }
+    // vixie cron silently ignores a last line which is not terminated
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        match last_line {
+            Some((line_number, CrontabLine::Job { .. })) | Some((line_number, CrontabLine::EnvironmentVariable { .. })) => {
+                errors.push(ValidationError {
+                    line_number,
+                    column: None,
+                    message: "the file does not end with a newline: cron ignores this last line".to_string(),
+                });
+            },
+            _ => (),
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+
+    use validate::*;
+
+    #[test]
+    fn test_validate_crontab() {
+        let options = CrontabParserOptions::<&str>::default();
+        assert_eq!(validate_crontab("MAILTO=ops@example.com\n0 2 * * * root /usr/bin/backup\n", &options), vec![]);
+        let errors = validate_crontab("# backups\n0 2 * * wedn root /usr/bin/backup\n0 3 * * * root /usr/bin/sync", &options);
+        assert_eq!(errors.len(), 2);
+        assert_eq!((errors[0].line_number, errors[0].column), (2, Some(8)));
+        assert!(errors[0].message.contains("did you mean 'wed'?"));
+        assert_eq!((errors[1].line_number, errors[1].column), (3, None));
+
+        let errors = validate_crontab("0 25 * juli * root /usr/bin/backup\n", &options);
+        assert_eq!(errors.iter().map(|error| (error.line_number, error.column)).collect::<Vec<_>>(), vec![(1, Some(2)), (1, Some(7))]);
+    }
+
+}
@@ -0,0 +1,54 @@
+// WebAssembly bindings, built with the wasm feature (e.g. `wasm-pack build -- --features wasm`), so that web UIs
+// validate crontabs with the same parser as the command
+
+use wasm_bindgen::prelude::*;
+
+use dialect::Dialect;
+use parser::{CrontabParserOptions, DEBIAN_MAX_COMMAND_LENGTH};
+use validate::validate_crontab;
+
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct ValidateOptions {
+    // owner of a user crontab, whose lines have no user field
+    owner: Option<String>,
+    allowed_usernames: Option<Vec<String>>,
+    max_command_length: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Diagnostic {
+    line: usize,
+    // in UTF-16 code units, as JavaScript strings
+    column: usize,
+    message: String,
+}
+
+// The options and the diagnostics are JSON text (JSON.stringify / JSON.parse on the JavaScript side):
+// validate("0 2 * * wedn root backup\n", '{"allowedUsernames": ["root"]}')
+//     -> '[{"line":1,"column":8,"message":"unknown day of the week \'wedn\', did you mean \'wed\'? ..."}]'
+#[wasm_bindgen]
+pub fn validate(text: &str, options: Option<String>) -> Result<String, JsValue> {
+    let options: ValidateOptions = match options {
+        Some(options) => serde_json::from_str(&options).map_err(|e| JsValue::from_str(&format!("invalid options: {}", e)))?,
+        None => ValidateOptions::default(),
+    };
+    let parser_options = CrontabParserOptions {
+        allowed_usernames: options.allowed_usernames.as_ref().map(|usernames| &usernames[..]),
+        forbidden_usernames: None,
+        max_command_length: options.max_command_length.unwrap_or(DEBIAN_MAX_COMMAND_LENGTH),
+        crontab_owner: options.owner.as_ref().map(|owner| &owner[..]),
+        dialect: Dialect::default(),
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    let diagnostics: Vec<Diagnostic> = validate_crontab(text, &parser_options).into_iter().map(|error| {
+        let line = lines.get(error.line_number - 1).cloned().unwrap_or("");
+        Diagnostic {
+            line: error.line_number,
+            column: error.column.and_then(|column| line.get(..column)).map(|start| start.encode_utf16().count()).unwrap_or(0),
+            message: error.message,
+        }
+    }).collect();
+    serde_json::to_string(&diagnostics).map_err(|e| JsValue::from_str(&e.to_string()))
+}